@@ -1,15 +1,11 @@
 use std::f64::consts::PI;
 
 use ::mini_gpu::{
-    components::{
-        controller::map::MapController,
-        lights::directional_light::{DirectionalLight, DirectionalLightUniform},
-        mesh::Mesh,
-    },
-    entity::Entity,
+    app::{App, AppConfig},
+    components::{lights::directional_light::DirectionalLight, mesh::Mesh},
+    entity::{Entity, EntityId},
     geometry::sphere,
     mini_gpu::MiniGPU,
-    system::mesh_render::MeshRender,
 };
 use mini_gpu::{
     components::{
@@ -17,102 +13,45 @@ use mini_gpu::{
         material::MaterialTrait,
         materials::basic::{BasicMaterial, BasicMaterialConfig},
     },
-    mini_gpu::MiniGPUConfig,
     utils::texture,
 };
-use winit::{
-    event::{Event, WindowEvent},
-    event_loop::EventLoop,
-    window::Window,
-};
 
 fn main() {
-    pollster::block_on(run());
-}
-
-async fn run() {
     env_logger::init();
-    let event_loop = EventLoop::new().unwrap();
-    let window = Window::new(&event_loop).unwrap();
-    let size = window.inner_size();
-    let mut mini_gpu = MiniGPU::new(
-        MiniGPUConfig {
-            width: size.width,
-            height: size.height,
-        },
-        window,
-    )
-    .await;
-    make_test_mesh(&mut mini_gpu);
-
-    // add light
-    let entity_id = mini_gpu.scene.add_entity(Entity::new());
-    let light = DirectionalLight::new(
-        &mini_gpu.renderer,
-        2,
-        DirectionalLightUniform {
-            intensity: 1.,
-            direction: [1., 1., -1.],
-            color: [1., 1., 0.8, 1.0],
-        },
-    );
-    let light_index = mini_gpu.scene.set_entity_component::<Box<dyn LightTrait>>(
-        entity_id,
-        Box::new(light),
-        "light",
-    );
+    pollster::block_on(MiniGPU::run(
+        AppConfig::default(),
+        GeometryExample::default(),
+    ))
+    .unwrap();
+}
 
-    let mut camera_controller = MapController::default();
+#[derive(Default)]
+struct GeometryExample {
+    light_entity: Option<EntityId>,
+}
 
-    mini_gpu
-        .renderer
-        .add_system("render".to_string(), Box::new(MeshRender {}));
-    event_loop
-        .run(move |event, target| match event {
-            Event::WindowEvent {
-                ref event,
-                window_id,
-            } if window_id == mini_gpu.renderer.window.id() => {
-                camera_controller.process_events(event);
-                match event {
-                    WindowEvent::RedrawRequested => {
-                        let camera = mini_gpu.scene.get_default_camera().unwrap();
-                        update_light(&mini_gpu, light_index);
-                        camera_controller.update(camera);
-                        camera.update_bind_group(&mini_gpu.renderer);
-                        if let Err(e) = mini_gpu.renderer.render(&mini_gpu.scene) {
-                            println!("Failed to render: {}", e);
-                        }
-                    }
-                    WindowEvent::Resized(physical_size) => {
-                        let camera = mini_gpu.scene.get_default_camera().unwrap();
-                        mini_gpu
-                            .renderer
-                            .resize(physical_size.width, physical_size.height);
-                        camera.set_aspect(
-                            physical_size.width as f32 / physical_size.height as f32,
-                            &mini_gpu.renderer,
-                        );
-                        mini_gpu.renderer.window.request_redraw();
-                    }
-                    WindowEvent::CloseRequested => target.exit(),
-                    _ => {}
-                }
-            }
+impl App for GeometryExample {
+    fn setup(&mut self, mini_gpu: &mut MiniGPU) {
+        make_test_mesh(mini_gpu);
+        // rotate the default light
+        self.light_entity = mini_gpu.scene.default_light;
+    }
 
-            Event::AboutToWait => {
-                mini_gpu.renderer.window.request_redraw();
-            }
-            _ => {}
-        })
-        .unwrap();
+    fn update(&mut self, mini_gpu: &mut MiniGPU, _dt: f32) {
+        if let Some(light_entity) = self.light_entity {
+            update_light(mini_gpu, light_entity);
+        }
+    }
 }
 
-fn update_light(mini_gpu: &MiniGPU, light_index: usize) {
+fn update_light(mini_gpu: &mut MiniGPU, light_entity: EntityId) {
     // update light position
-    let light_trait = mini_gpu
+    let Ok(light_trait) = mini_gpu
         .scene
-        .get_component::<Box<dyn LightTrait>>(light_index);
+        .get_entity_component_mut::<Box<dyn LightTrait>>(light_entity, "light")
+    else {
+        return;
+    };
     let now_time = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
@@ -122,7 +61,6 @@ fn update_light(mini_gpu: &MiniGPU, light_index: usize) {
         println!("light.uniform.direction {:?}", light.uniform.direction);
         light.uniform.direction[0] = f32::sin(delta as f32);
         light.uniform.direction[2] = 1.0 * f32::cos(delta as f32);
-        light.update_buffer(&mini_gpu.renderer);
     }
 }
 
@@ -1,3 +1,5 @@
+#include <Lights>
+
 struct CameraUniform {
     view_projection: mat4x4<f32>,
     projection_matrix: mat4x4<f32>,
@@ -18,15 +20,8 @@ struct VertexInput {
     @location(2) tex_coord: vec2<f32>,
 }
 
-struct DirectionLight{
-    direction: vec3<f32>,
-    color: vec3<f32>,
-    intensity: f32,
-}
-
 @group(0) @binding(0) var<uniform> color: vec4f;
 @group(1) @binding(0) var<uniform> camera: CameraUniform;
-@group(1) @binding(2) var<uniform> direction_light: DirectionLight;
 
 
 @vertex
@@ -51,18 +46,23 @@ var s_diffuse: sampler;
 fn fs_main(in: VertexOutput) -> @location(0) vec4f {
     let color =  textureSample(t_diffuse, s_diffuse, in.tex_coord);
 
-    let ambient_strength = 0.1;
-    let ambient_color = direction_light.color * ambient_strength;
-
-    let light_dir = normalize(-direction_light.direction);
+    var ambient_color = vec3<f32>(0.);
+    var diffuse_color = vec3<f32>(0.);
+    var specular_color = vec3<f32>(0.);
     let view_dir = normalize(camera.position.xyz - in.position);
-    let half_dir = normalize(view_dir + light_dir);
 
-    let diffuse_strength = max(dot(in.normal, light_dir), 0.0);
-    let diffuse_color = direction_light.color * diffuse_strength;
+    for (var i = 0u; i < light_count(); i = i + 1u) {
+        let light = lights.lights[i];
+        ambient_color = ambient_color + ambient_light(light, in.normal);
+        let light_sample = sample_light(light, in.position);
+        let half_dir = normalize(view_dir + light_sample.direction);
+
+        let diffuse_strength = max(dot(in.normal, light_sample.direction), 0.0);
+        diffuse_color = diffuse_color + light_sample.radiance * diffuse_strength;
 
-    let specular_strength = pow(max(dot(in.normal, half_dir), 0.0), 32.0);
-    let specular_color = specular_strength * direction_light.color;
+        let specular_strength = pow(max(dot(in.normal, half_dir), 0.0), 32.0);
+        specular_color = specular_color + specular_strength * light_sample.radiance;
+    }
 
     let result = (ambient_color + diffuse_color + specular_color) * color.rgb;
 
@@ -1,83 +1,31 @@
 use ::mini_gpu::{
+    app::{App, AppConfig},
     components::controller::map::MapController,
     entity::{sprite_entity, Entity},
     mini_gpu::MiniGPU,
-    system::mesh_render::MeshRender,
+    system::camera_controller_system::CameraControllerSystem,
 };
 use image::{ImageBuffer, Rgba};
-use mini_gpu::{
-    components::materials::sprite::SpriteMaterialConfig, mini_gpu::MiniGPUConfig, utils::texture,
-};
-use winit::{
-    event::{Event, WindowEvent},
-    event_loop::EventLoop,
-    window::Window,
-};
+use mini_gpu::{components::materials::sprite::SpriteMaterialConfig, utils::texture};
 fn main() {
-    pollster::block_on(run());
-    print!("Hello, world!");
+    env_logger::init();
+    pollster::block_on(MiniGPU::run(AppConfig::default(), GraphExample {})).unwrap();
 }
 
-async fn run() {
-    env_logger::init();
-    let event_loop = EventLoop::new().unwrap();
-    let window = Window::new(&event_loop).unwrap();
-    let size = window.inner_size();
-    let mut mini_gpu = MiniGPU::new(
-        MiniGPUConfig {
-            width: size.width,
-            height: size.height,
-        },
-        window,
-    )
-    .await;
-    make_test_mesh(&mut mini_gpu);
-    mini_gpu::utils::camera::default_orthographic_camera(&mut mini_gpu);
-    let mut camera_controller = MapController::default();
-    camera_controller.config.pan_speed = 0.007;
-    mini_gpu
-        .renderer
-        .add_system("render".to_string(), Box::new(MeshRender {}));
-    event_loop
-        .run(move |event, target| {
-            let window = &mini_gpu.renderer.window;
-            let camera = mini_gpu.scene.get_default_camera().unwrap();
-            match event {
-                Event::WindowEvent {
-                    ref event,
-                    window_id,
-                } if window_id == window.id() => {
-                    camera_controller.process_events(event);
-                    match event {
-                        WindowEvent::RedrawRequested => {
-                            camera_controller.update(camera);
-                            camera.update_bind_group(&mini_gpu.renderer);
-                            if let Err(e) = mini_gpu.renderer.render(&mini_gpu.scene) {
-                                println!("Failed to render: {}", e);
-                            }
-                        }
-                        WindowEvent::Resized(physical_size) => {
-                            mini_gpu
-                                .renderer
-                                .resize(physical_size.width, physical_size.height);
-                            camera.set_aspect(
-                                physical_size.width as f32 / physical_size.height as f32,
-                                &mini_gpu.renderer,
-                            );
-                            mini_gpu.renderer.window.request_redraw();
-                        }
-                        WindowEvent::CloseRequested => target.exit(),
-                        _ => {}
-                    }
-                }
+struct GraphExample {}
 
-                Event::AboutToWait => {
-                    mini_gpu.renderer.window.request_redraw();
-                }
-                _ => {}
-            }
-        })
-        .unwrap();
+impl App for GraphExample {
+    fn setup(&mut self, mini_gpu: &mut MiniGPU) {
+        make_test_mesh(mini_gpu);
+        mini_gpu::utils::camera::default_orthographic_camera(mini_gpu);
+        let mut camera_controller = MapController::default();
+        camera_controller.config.pan_speed = 0.007;
+        // replace the default controller
+        mini_gpu.renderer.add_system(
+            "controller".to_string(),
+            Box::new(CameraControllerSystem::new(camera_controller)),
+        );
+    }
 }
 
 fn create_solid_color_image(width: u32, height: u32, color: [u8; 4]) -> image::DynamicImage {
@@ -114,9 +62,10 @@ fn make_test_mesh(mini_gpu: &mut MiniGPU) {
         },
         entity_id,
     );
-    let material_id = &mini_gpu
+    let material_id = mini_gpu
         .scene
-        .get_entity_component_index(entity_id, "material");
+        .get_entity_component_index(entity_id, "material")
+        .unwrap();
 
     let entity_id2 = mini_gpu.scene.add_entity(Entity::new());
     sprite_entity::make_mesh(
@@ -127,7 +76,8 @@ fn make_test_mesh(mini_gpu: &mut MiniGPU) {
     );
     mini_gpu
         .scene
-        .set_entity_component_index(entity_id2, *material_id, "material");
+        .set_entity_component_index(entity_id2, material_id, "material")
+        .unwrap();
     //add line
     let entity_line_id = mini_gpu.scene.add_entity(Entity::new());
     mini_gpu::entity::mesh_line::make_mesh(
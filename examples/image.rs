@@ -1,4 +1,5 @@
 use ::mini_gpu::{
+    app::{App, AppConfig},
     components::{
         material::MaterialTrait,
         materials::basic::{BasicMaterial, BasicMaterialConfig},
@@ -6,74 +7,21 @@ use ::mini_gpu::{
     },
     entity::Entity,
     geometry::plane::{make_plane_mesh, MakePlaneConfig},
-    mini_gpu::{self, MiniGPU},
-    system::mesh_render::MeshRender,
+    mini_gpu::MiniGPU,
     utils::texture::Texture,
 };
-use winit::{
-    dpi::LogicalSize,
-    event::{Event, WindowEvent},
-    event_loop::EventLoop,
-    window::Window,
-};
+use winit::dpi::LogicalSize;
 fn main() {
-    pollster::block_on(run());
-    print!("Hello, world!");
+    env_logger::init();
+    pollster::block_on(MiniGPU::run(AppConfig::default(), ImageExample {})).unwrap();
 }
 
-async fn run() {
-    env_logger::init();
-    let event_loop = EventLoop::new().unwrap();
-    let window = Window::new(&event_loop).unwrap();
-    let size = window.inner_size();
-    let mut mini_gpu = mini_gpu::MiniGPU::new(
-        mini_gpu::MiniGPUConfig {
-            width: size.width,
-            height: size.height,
-        },
-        window,
-    )
-    .await;
-    make_test_mesh(&mut mini_gpu);
-    mini_gpu
-        .renderer
-        .add_system("render".to_string(), Box::new(MeshRender {}));
+struct ImageExample {}
 
-    event_loop
-        .run(move |event, target| {
-            let window = &mini_gpu.renderer.window;
-            let camera = mini_gpu.scene.get_default_camera().unwrap();
-            match event {
-                Event::WindowEvent {
-                    ref event,
-                    window_id,
-                } if window_id == window.id() => match event {
-                    WindowEvent::RedrawRequested => {
-                        camera.update_bind_group(&mini_gpu.renderer);
-                        if let Err(e) = mini_gpu.renderer.render(&mut mini_gpu.scene) {
-                            println!("Failed to render: {}", e);
-                        }
-                    }
-                    WindowEvent::Resized(physical_size) => {
-                        mini_gpu
-                            .renderer
-                            .resize(physical_size.width, physical_size.height);
-                        camera.set_aspect(
-                            physical_size.width as f32 / physical_size.height as f32,
-                            &mini_gpu.renderer,
-                        );
-                        mini_gpu.renderer.window.request_redraw();
-                    }
-                    WindowEvent::CloseRequested => target.exit(),
-                    _ => {}
-                },
-                Event::AboutToWait => {
-                    mini_gpu.renderer.window.request_redraw();
-                }
-                _ => {}
-            }
-        })
-        .unwrap();
+impl App for ImageExample {
+    fn setup(&mut self, mini_gpu: &mut MiniGPU) {
+        make_test_mesh(mini_gpu);
+    }
 }
 
 fn make_test_mesh(mini_gpu: &mut MiniGPU) {
@@ -106,7 +54,7 @@ fn make_test_mesh(mini_gpu: &mut MiniGPU) {
         &mini_gpu.renderer,
     );
 
-    let camera = mini_gpu.scene.get_default_camera().unwrap();
+    let camera = mini_gpu.scene.get_default_camera_mut().unwrap();
     let perspective_camera = camera.as_any().downcast_mut::<PerspectiveCamera>().unwrap();
     perspective_camera.config.position = glam::Vec3::new(0., 0., 2.);
     camera.update_bind_group(&mini_gpu.renderer);
@@ -119,5 +67,7 @@ fn make_test_mesh(mini_gpu: &mut MiniGPU) {
     let _ = mini_gpu
         .renderer
         .window
+        .as_ref()
+        .unwrap()
         .request_inner_size(LogicalSize::new(image.width(), image.height()));
 }
@@ -1,92 +1,37 @@
 use std::f32::consts::PI;
 
 use ::mini_gpu::{
+    app::{App, AppConfig},
     components::{
-        controller::map::MapController,
-        instance::{self, Instance},
+        instance::{Instance, InstanceLayout},
         material::MaterialTrait,
         materials::basic::{BasicMaterial, BasicMaterialConfig},
         perspective_camera::PerspectiveCamera,
     },
     entity::Entity,
     geometry::plane::{make_plane_mesh, MakePlaneConfig},
-    mini_gpu::{self, MiniGPU},
-    system::mesh_render::MeshRender,
+    mini_gpu::MiniGPU,
     utils::texture::Texture,
 };
-use winit::{
-    dpi::LogicalSize,
-    event::{Event, WindowEvent},
-    event_loop::EventLoop,
-    window::Window,
-};
+use winit::dpi::LogicalSize;
 fn main() {
-    pollster::block_on(run());
-    print!("Hello, world!");
-}
-
-async fn run() {
     env_logger::init();
-    let event_loop = EventLoop::new().unwrap();
-    let window = Window::new(&event_loop).unwrap();
-    let size = window.inner_size();
-    let mut mini_gpu = mini_gpu::MiniGPU::new(
-        mini_gpu::MiniGPUConfig {
-            width: size.width,
-            height: size.height,
-        },
-        window,
-    )
-    .await;
-    make_test_mesh(&mut mini_gpu);
-    let mut camera_controller = MapController::default();
-    camera_controller.config.scale_factor = mini_gpu.renderer.viewport.scale_factor;
-
-    mini_gpu
-        .renderer
-        .add_system("render".to_string(), Box::new(MeshRender {}));
+    pollster::block_on(MiniGPU::run(AppConfig::default(), InstanceExample {})).unwrap();
+}
 
-    event_loop
-        .run(move |event, target| {
-            let window = &mini_gpu.renderer.window;
-            let camera = mini_gpu.scene.get_default_camera().unwrap();
+struct InstanceExample {}
 
-            match event {
-                Event::WindowEvent {
-                    ref event,
-                    window_id,
-                } if window_id == window.id() => {
-                    camera_controller.process_events(event);
-                    match event {
-                        WindowEvent::RedrawRequested => {
-                            camera_controller.update(camera);
-                            camera.update_bind_group(&mini_gpu.renderer);
-                            if let Err(e) = mini_gpu.renderer.render(&mut mini_gpu.scene) {
-                                println!("Failed to render: {}", e);
-                            }
-                        }
-                        WindowEvent::Resized(physical_size) => {
-                            mini_gpu
-                                .renderer
-                                .resize(physical_size.width, physical_size.height);
-                            camera.set_aspect(
-                                physical_size.width as f32 / physical_size.height as f32,
-                                &mini_gpu.renderer,
-                            );
-                            mini_gpu.renderer.window.request_redraw();
-                        }
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct InstanceValue {
+    model_matrix: [[f32; 4]; 4],
+    color: [f32; 4],
+}
 
-                        WindowEvent::CloseRequested => target.exit(),
-                        _ => {}
-                    }
-                }
-                Event::AboutToWait => {
-                    mini_gpu.renderer.window.request_redraw();
-                }
-                _ => {}
-            }
-        })
-        .unwrap();
+impl App for InstanceExample {
+    fn setup(&mut self, mini_gpu: &mut MiniGPU) {
+        make_test_mesh(mini_gpu);
+    }
 }
 
 fn make_test_mesh(mini_gpu: &mut MiniGPU) {
@@ -98,10 +43,13 @@ fn make_test_mesh(mini_gpu: &mut MiniGPU) {
         Some("image"),
     )
     .unwrap();
+    // a model matrix and a tint per instance
+    let layout =
+        InstanceLayout::default().with_attribute("color", wgpu::VertexFormat::Float32x4);
     let material = BasicMaterial::new(
         BasicMaterialConfig {
             texture: Some(texture),
-            shader: Some(include_str!("./instance.wgsl").to_string()),
+            shader: Some(layout.parse_shader(include_str!("./instance.wgsl"))),
             ..Default::default()
         },
         &mini_gpu.renderer,
@@ -118,7 +66,7 @@ fn make_test_mesh(mini_gpu: &mut MiniGPU) {
         &mini_gpu.renderer,
     );
 
-    let camera = mini_gpu.scene.get_default_camera().unwrap();
+    let camera = mini_gpu.scene.get_default_camera_mut().unwrap();
     let perspective_camera = camera.as_any().downcast_mut::<PerspectiveCamera>().unwrap();
     perspective_camera.config.position = glam::Vec3::new(0., 0., 2.);
     camera.update_bind_group(&mini_gpu.renderer);
@@ -131,6 +79,8 @@ fn make_test_mesh(mini_gpu: &mut MiniGPU) {
     let size_result = mini_gpu
         .renderer
         .window
+        .as_ref()
+        .unwrap()
         .request_inner_size(LogicalSize::new(image.width(), image.height()));
     println!("resize: {:?}", size_result);
     //instance
@@ -146,12 +96,13 @@ fn make_test_mesh(mini_gpu: &mut MiniGPU) {
                 glam::Quat::from_euler(glam::EulerRot::XYZ, i as f32 / 10. * PI, 0., 0.),
                 glam::vec3(i as f32, j as f32, 0.0),
             );
-            instance_data.push(instance::InstanceData {
-                data: matdata.to_cols_array_2d(),
+            instance_data.push(InstanceValue {
+                model_matrix: matdata.to_cols_array_2d(),
+                color: [i as f32 / 10., j as f32 / 10., 1., 1.],
             });
         }
     }
-    let instance = Instance::new(instance_data, &mini_gpu.renderer);
+    let instance = Instance::with_layout(layout, &instance_data, &mini_gpu.renderer);
     mini_gpu
         .scene
         .set_entity_component(entity_id, instance, "instance");
@@ -7,17 +7,13 @@ struct VertexInput {
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coord: vec2<f32>,
-    @location(1) z: f32,
+    @location(1) color: vec4<f32>,
 }
 struct CameraUniform {
     view_projection: mat4x4<f32>
 }
-struct InstanceInput {
-    @location(5) model_matrix_0: vec4<f32>,
-    @location(6) model_matrix_1: vec4<f32>,
-    @location(7) model_matrix_2: vec4<f32>,
-    @location(8) model_matrix_3: vec4<f32>,
-};
+// built from the InstanceLayout, see InstanceLayout::parse_shader
+#include <InstanceInput>
 
 @group(1) @binding(0) // 1.
 var<uniform> camera: CameraUniform;
@@ -27,16 +23,14 @@ fn vs_main(
     model: VertexInput,
     instance: InstanceInput,
 ) -> VertexOutput {
-    let instance_matrix = mat4x4<f32>(
-        instance.model_matrix_0,
-        instance.model_matrix_1,
-        instance.model_matrix_2,
-        instance.model_matrix_3,
-    );
     var out: VertexOutput;
     out.tex_coord = model.tex_coord;
-    out.clip_position = camera.view_projection * instance_matrix * vec4<f32>(model.position, 1.0);
-    out.z = instance.model_matrix_3.x/10.0;
+    out.clip_position = camera.view_projection * instance_model_matrix(instance) * vec4<f32>(model.position, 1.0);
+#ifdef INSTANCE_COLOR
+    out.color = instance.color;
+#else
+    out.color = vec4<f32>(1.0);
+#endif
     return out;
 }
 
@@ -49,7 +43,5 @@ var s_diffuse: sampler;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    var color =  textureSample(t_diffuse, s_diffuse, in.tex_coord);
-    color.r = in.z;
-    return color;
-}
\ No newline at end of file
+    return textureSample(t_diffuse, s_diffuse, in.tex_coord) * in.color;
+}
@@ -1,85 +1,27 @@
 use ::mini_gpu::{
-    components::{controller::map::MapController, orthographic_camera::OrthographicCamera},
-    mini_gpu::{self, MiniGPU},
-    system::mesh_render::MeshRender,
-    utils::{self, axis, texture::Texture},
-};
-use winit::{
-    event::{Event, WindowEvent},
-    event_loop::EventLoop,
-    window::Window,
+    app::{App, AppConfig},
+    components::orthographic_camera::OrthographicCamera,
+    mini_gpu::MiniGPU,
+    utils::{self, axis},
 };
 
 fn main() {
-    pollster::block_on(run());
-    print!("Hello, world!");
-}
-
-async fn run() {
     env_logger::init();
-    let event_loop = EventLoop::new().unwrap();
-    let window = Window::new(&event_loop).unwrap();
-    let size = window.inner_size();
-    let mut mini_gpu = mini_gpu::MiniGPU::new(
-        mini_gpu::MiniGPUConfig {
-            width: size.width,
-            height: size.height,
-        },
-        window,
-    )
-    .await;
-    utils::camera::default_orthographic_camera(&mut mini_gpu);
-    make_test_mesh(&mut mini_gpu).await;
-    axis::add_xyz_line(&mut mini_gpu, Some(10.));
-    let mut camera_controller = MapController::default();
-    camera_controller.config.width = mini_gpu.renderer.viewport.width;
-    camera_controller.config.height = mini_gpu.renderer.viewport.height;
+    pollster::block_on(MiniGPU::run(
+        AppConfig::default(),
+        OrthographicCameraExample {},
+    ))
+    .unwrap();
+}
 
-    mini_gpu
-        .renderer
-        .add_system("render".to_string(), Box::new(MeshRender {}));
-    event_loop
-        .run(move |event, target| {
-            let window = &mini_gpu.renderer.window;
-            let camera = mini_gpu.scene.get_default_camera().unwrap();
-            match event {
-                Event::WindowEvent {
-                    ref event,
-                    window_id,
-                } if window_id == window.id() => {
-                    camera_controller.process_events(event);
-                    match event {
-                        WindowEvent::RedrawRequested => {
-                            camera_controller.update(camera);
-                            camera.update_bind_group(&mini_gpu.renderer);
-                            if let Err(e) = mini_gpu.renderer.render(&mini_gpu.scene) {
-                                println!("Failed to render: {}", e);
-                            }
-                        }
-                        WindowEvent::Resized(physical_size) => {
-                            mini_gpu
-                                .renderer
-                                .resize(physical_size.width, physical_size.height);
-                            camera.set_aspect(
-                                physical_size.width as f32 / physical_size.height as f32,
-                                &mini_gpu.renderer,
-                            );
-                            camera_controller.config.width = mini_gpu.renderer.viewport.width;
-                            camera_controller.config.height = mini_gpu.renderer.viewport.height;
-                            mini_gpu.renderer.window.request_redraw();
-                        }
-                        WindowEvent::CloseRequested => target.exit(),
-                        _ => {}
-                    }
-                }
+struct OrthographicCameraExample {}
 
-                Event::AboutToWait => {
-                    mini_gpu.renderer.window.request_redraw();
-                }
-                _ => {}
-            }
-        })
-        .unwrap();
+impl App for OrthographicCameraExample {
+    fn setup(&mut self, mini_gpu: &mut MiniGPU) {
+        utils::camera::default_orthographic_camera(mini_gpu);
+        pollster::block_on(make_test_mesh(mini_gpu));
+        axis::add_xyz_line(mini_gpu, Some(10.));
+    }
 }
 
 async fn make_test_mesh(mini_gpu: &mut MiniGPU) {
@@ -94,7 +36,7 @@ async fn make_test_mesh(mini_gpu: &mut MiniGPU) {
         }
     }
 
-    let camera = mini_gpu.scene.get_default_camera().unwrap();
+    let camera = mini_gpu.scene.get_default_camera_mut().unwrap();
     let orthographic_camera = camera
         .as_any()
         .downcast_mut::<OrthographicCamera>()
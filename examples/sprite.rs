@@ -1,81 +1,20 @@
 use ::mini_gpu::{
-    components::controller::map::MapController,
+    app::{App, AppConfig},
     entity::{sprite_entity, Entity},
     mini_gpu::MiniGPU,
-    system::mesh_render::MeshRender,
-};
-use mini_gpu::{
-    components::materials::sprite::SpriteMaterialConfig, mini_gpu::MiniGPUConfig, utils::texture,
-};
-use winit::{
-    event::{Event, WindowEvent},
-    event_loop::EventLoop,
-    window::Window,
 };
+use mini_gpu::{components::materials::sprite::SpriteMaterialConfig, utils::texture};
 fn main() {
-    pollster::block_on(run());
-    print!("Hello, world!");
-}
-
-async fn run() {
     env_logger::init();
-    let event_loop = EventLoop::new().unwrap();
-    let window = winit::window::WindowBuilder::new().build(&event_loop).unwrap();
-    let size = window.inner_size();
-    let mut mini_gpu = MiniGPU::new(
-        MiniGPUConfig {
-            width: size.width,
-            height: size.height,
-        },
-        window,
-    )
-    .await;
-    make_test_mesh(&mut mini_gpu);
-    let mut camera_controller = MapController::default();
+    pollster::block_on(MiniGPU::run(AppConfig::default(), SpriteExample {})).unwrap();
+}
 
-    mini_gpu
-        .renderer
-        .add_system("render".to_string(), Box::new(MeshRender {}));
-    event_loop
-        .run(move |event, target| {
-            let window = &mini_gpu.renderer.window;
-            let camera = mini_gpu.scene.get_default_camera().unwrap();
+struct SpriteExample {}
 
-            match event {
-                Event::WindowEvent {
-                    ref event,
-                    window_id,
-                } if window_id == window.id() => {
-                    camera_controller.process_events(event);
-                    match event {
-                        WindowEvent::RedrawRequested => {
-                            camera_controller.update(camera);
-                            camera.update_bind_group(&mini_gpu.renderer);
-                            if let Err(e) = mini_gpu.renderer.render(&mini_gpu.scene) {
-                                println!("Failed to render: {}", e);
-                            }
-                        }
-                        WindowEvent::Resized(physical_size) => {
-                            mini_gpu
-                                .renderer
-                                .resize(physical_size.width, physical_size.height);
-                            camera.set_aspect(
-                                physical_size.width as f32 / physical_size.height as f32,
-                                &mini_gpu.renderer,
-                            );
-                            mini_gpu.renderer.window.request_redraw();
-                        }
-                        WindowEvent::CloseRequested => target.exit(),
-                        _ => {}
-                    }
-                }
-                Event::AboutToWait => {
-                    mini_gpu.renderer.window.request_redraw();
-                }
-                _ => {}
-            }
-        })
-        .unwrap();
+impl App for SpriteExample {
+    fn setup(&mut self, mini_gpu: &mut MiniGPU) {
+        make_test_mesh(mini_gpu);
+    }
 }
 
 fn make_test_mesh(mini_gpu: &mut MiniGPU) {
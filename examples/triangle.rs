@@ -1,73 +1,24 @@
 use ::mini_gpu::{
+    app::{App, AppConfig},
     components::{
         material::{Material, MaterialConfig, MaterialTrait},
+        materials::render_state::RenderState,
         mesh::Mesh,
     },
     entity::Entity,
-    geometry::plane::{make_plane_mesh, MakePlaneConfig},
     mini_gpu::MiniGPU,
-    system::mesh_render::MeshRender,
-};
-use mini_gpu::mini_gpu;
-use winit::{
-    event::{Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
-    window::Window,
 };
 fn main() {
-    pollster::block_on(run());
-    print!("Hello, world!");
-}
-
-async fn run() {
     env_logger::init();
-    let event_loop = EventLoop::new().unwrap();
-    let window = winit::window::WindowBuilder::new()
-        .build(&event_loop)
-        .unwrap();
-    let size = window.inner_size();
-    let mut mini_gpu = mini_gpu::MiniGPU::new(
-        mini_gpu::MiniGPUConfig {
-            width: size.width,
-            height: size.height,
-        },
-        window,
-    )
-    .await;
-    make_test_mesh(&mut mini_gpu);
-    mini_gpu
-        .renderer
-        .add_system("render".to_string(), Box::new(MeshRender {}));
+    pollster::block_on(MiniGPU::run(AppConfig::default(), TriangleExample {})).unwrap();
+}
 
-    event_loop
-        .run(move |event, target| {
-            let window = &mini_gpu.renderer.window;
-            match event {
-                Event::WindowEvent { window_id, event } if window_id == window.id() => {
-                    match event {
-                        WindowEvent::RedrawRequested => {
-                            if let Err(e) = mini_gpu.renderer.render(&mini_gpu.scene) {
-                                println!("Failed to render: {}", e);
-                            }
-                        }
-                        WindowEvent::Resized(physical_size) => {
-                            mini_gpu
-                                .renderer
-                                .resize(physical_size.width, physical_size.height);
-                            mini_gpu.renderer.window.request_redraw();
-                        }
+struct TriangleExample {}
 
-                        WindowEvent::CloseRequested => target.exit(),
-                        _ => {}
-                    }
-                }
-                Event::AboutToWait => {
-                    mini_gpu.renderer.window.request_redraw();
-                }
-                _ => {}
-            }
-        })
-        .unwrap();
+impl App for TriangleExample {
+    fn setup(&mut self, mini_gpu: &mut MiniGPU) {
+        make_test_mesh(mini_gpu);
+    }
 }
 
 fn make_test_mesh(mini_gpu: &mut MiniGPU) {
@@ -87,6 +38,7 @@ fn make_test_mesh(mini_gpu: &mut MiniGPU) {
             shader: include_str!("./triangle.wgsl").to_string(),
             topology: wgpu::PrimitiveTopology::TriangleList,
             uniforms: vec![1., 0., 0.5, 1.],
+            render_state: RenderState::transparent(),
         },
         &mini_gpu.renderer,
     );
@@ -115,5 +67,6 @@ fn make_test_mesh(mini_gpu: &mut MiniGPU) {
         .set_entity_component::<Mesh>(entity_3, mesh_2, "mesh");
     mini_gpu
         .scene
-        .set_entity_component_index(entity_3, material_index, "material");
+        .set_entity_component_index(entity_3, material_index, "material")
+        .unwrap();
 }
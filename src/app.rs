@@ -0,0 +1,38 @@
+use winit::event::WindowEvent;
+
+use crate::mini_gpu::MiniGPU;
+
+/// user logic driven by `MiniGPU::run`, every method has an empty default
+pub trait App {
+    /// called once after the window and renderer are created, add entities and systems here
+    fn setup(&mut self, _mini_gpu: &mut MiniGPU) {}
+
+    /// called before each frame is rendered, dt is the seconds since last frame
+    fn update(&mut self, _mini_gpu: &mut MiniGPU, _dt: f32) {}
+
+    /// called for every window event before the runner handles it
+    fn on_event(&mut self, _mini_gpu: &mut MiniGPU, _event: &WindowEvent) {}
+}
+
+pub struct AppConfig {
+    pub title: String,
+    // logical size, the surface is created with the physical size
+    pub width: u32,
+    pub height: u32,
+    // wasm only, id of the element the canvas is appended to, use body if None
+    pub canvas_parent_id: Option<String>,
+    // add MeshRender and a map camera controller before setup
+    pub default_systems: bool,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            title: "MiniGPU".to_string(),
+            width: 800,
+            height: 600,
+            canvas_parent_id: None,
+            default_systems: true,
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+use crate::entity::EntityId;
+
+/// handle to a component in the scene's store, keeps the type so accesses can be checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ComponentId {
+    type_id: TypeId,
+    type_name: &'static str,
+    pub index: u32,
+    pub generation: u32,
+}
+
+impl ComponentId {
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    pub fn is<T: Any>(&self) -> bool {
+        self.type_id == TypeId::of::<T>()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ComponentError {
+    EntityNotFound(EntityId),
+    /// the entity has no component with this name
+    Missing(String),
+    /// the component was dropped, or the handle belongs to a reused slot
+    NotFound(ComponentId),
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+}
+
+impl std::fmt::Display for ComponentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ComponentError::EntityNotFound(id) => write!(f, "entity not found: {}", id),
+            ComponentError::Missing(name) => write!(f, "component not found: {}", name),
+            ComponentError::NotFound(id) => write!(
+                f,
+                "component {} of type {} has been dropped",
+                id.index, id.type_name
+            ),
+            ComponentError::TypeMismatch { expected, found } => write!(
+                f,
+                "type mismatch: expected {}, but found {}",
+                expected, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ComponentError {}
+
+struct ComponentSlot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// components of one type, a freed slot is reused without shifting the others
+struct TypedStorage<T> {
+    slots: Vec<ComponentSlot<T>>,
+    free_slots: Vec<u32>,
+}
+
+impl<T> TypedStorage<T> {
+    fn new() -> Self {
+        TypedStorage {
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+        }
+    }
+}
+
+trait AnyStorage {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn len(&self) -> usize;
+    fn contains(&self, index: u32, generation: u32) -> bool;
+    fn remove(&mut self, index: u32, generation: u32) -> bool;
+}
+
+impl<T: Any> AnyStorage for TypedStorage<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn len(&self) -> usize {
+        self.slots.len() - self.free_slots.len()
+    }
+
+    fn contains(&self, index: u32, generation: u32) -> bool {
+        self.slots
+            .get(index as usize)
+            .is_some_and(|slot| slot.generation == generation && slot.value.is_some())
+    }
+
+    fn remove(&mut self, index: u32, generation: u32) -> bool {
+        let Some(slot) = self
+            .slots
+            .get_mut(index as usize)
+            .filter(|slot| slot.generation == generation && slot.value.is_some())
+        else {
+            return false;
+        };
+        slot.value = None;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(index);
+        true
+    }
+}
+
+/// per-type component storages keyed by TypeId, components are dropped with the store
+#[derive(Default)]
+pub struct ComponentStore {
+    storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+}
+
+impl ComponentStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert<T: Any>(&mut self, component: T) -> ComponentId {
+        let storage = self.storage_mut_or_insert::<T>();
+        let (index, generation) = if let Some(index) = storage.free_slots.pop() {
+            let slot = &mut storage.slots[index as usize];
+            slot.value = Some(component);
+            (index, slot.generation)
+        } else {
+            storage.slots.push(ComponentSlot {
+                generation: 0,
+                value: Some(component),
+            });
+            ((storage.slots.len() - 1) as u32, 0)
+        };
+        ComponentId {
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+            index,
+            generation,
+        }
+    }
+
+    pub fn get<T: Any>(&self, id: ComponentId) -> Result<&T, ComponentError> {
+        Self::check_type::<T>(id)?;
+        self.storages
+            .get(&id.type_id)
+            .and_then(|storage| storage.as_any().downcast_ref::<TypedStorage<T>>())
+            .and_then(|storage| storage.slots.get(id.index as usize))
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.value.as_ref())
+            .ok_or(ComponentError::NotFound(id))
+    }
+
+    pub fn get_mut<T: Any>(&mut self, id: ComponentId) -> Result<&mut T, ComponentError> {
+        Self::check_type::<T>(id)?;
+        self.storages
+            .get_mut(&id.type_id)
+            .and_then(|storage| storage.as_any_mut().downcast_mut::<TypedStorage<T>>())
+            .and_then(|storage| storage.slots.get_mut(id.index as usize))
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.value.as_mut())
+            .ok_or(ComponentError::NotFound(id))
+    }
+
+    pub fn contains(&self, id: ComponentId) -> bool {
+        self.storages
+            .get(&id.type_id)
+            .is_some_and(|storage| storage.contains(id.index, id.generation))
+    }
+
+    /// remove the component and hand it back, the slot can be reused by the next insert
+    pub fn remove<T: Any>(&mut self, id: ComponentId) -> Result<T, ComponentError> {
+        Self::check_type::<T>(id)?;
+        let storage = self
+            .storages
+            .get_mut(&id.type_id)
+            .and_then(|storage| storage.as_any_mut().downcast_mut::<TypedStorage<T>>())
+            .ok_or(ComponentError::NotFound(id))?;
+        let slot = storage
+            .slots
+            .get_mut(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .ok_or(ComponentError::NotFound(id))?;
+        let value = slot.value.take().ok_or(ComponentError::NotFound(id))?;
+        slot.generation = slot.generation.wrapping_add(1);
+        storage.free_slots.push(id.index);
+        Ok(value)
+    }
+
+    /// drop a component without knowing its type, false if it was already dropped
+    pub fn remove_any(&mut self, id: ComponentId) -> bool {
+        self.storages
+            .get_mut(&id.type_id)
+            .is_some_and(|storage| storage.remove(id.index, id.generation))
+    }
+
+    /// number of alive components of type T
+    pub fn count<T: Any>(&self) -> usize {
+        self.storages
+            .get(&TypeId::of::<T>())
+            .map_or(0, |storage| storage.len())
+    }
+
+    fn check_type<T: Any>(id: ComponentId) -> Result<(), ComponentError> {
+        if !id.is::<T>() {
+            return Err(ComponentError::TypeMismatch {
+                expected: std::any::type_name::<T>(),
+                found: id.type_name,
+            });
+        }
+        Ok(())
+    }
+
+    fn storage_mut_or_insert<T: Any>(&mut self) -> &mut TypedStorage<T> {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(TypedStorage::<T>::new()))
+            .as_any_mut()
+            .downcast_mut::<TypedStorage<T>>()
+            .expect("component storage type mismatch")
+    }
+}
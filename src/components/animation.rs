@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+
+use glam::{Quat, Vec4};
+
+use crate::entity::EntityId;
+
+/// the transform property a channel drives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnimationProperty {
+    Translation,
+    Rotation,
+    Scale,
+    /// morph target weights of the target's MorphWeights, or of its primitive children
+    Weights,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    Step,
+    /// every keyframe stores in tangent, value and out tangent
+    CubicSpline,
+}
+
+/// keyframes of one property of one entity, values are flattened,
+/// 3 floats per key for translation and scale, 4 (xyzw) for rotation, one per morph target for weights
+pub struct AnimationChannel {
+    pub target: EntityId,
+    pub property: AnimationProperty,
+    pub interpolation: Interpolation,
+    pub times: Vec<f32>,
+    pub values: Vec<f32>,
+}
+
+impl AnimationChannel {
+    /// floats per value
+    pub fn stride(&self) -> usize {
+        let keys = match self.interpolation {
+            Interpolation::CubicSpline => self.times.len() * 3,
+            _ => self.times.len(),
+        };
+        self.values.len().checked_div(keys).unwrap_or(0)
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.times.last().copied().unwrap_or(0.0)
+    }
+
+    /// value at time, clamped to the first and last keyframe
+    pub fn sample(&self, time: f32) -> Vec<f32> {
+        let stride = self.stride();
+        let Some(last) = self.times.len().checked_sub(1) else {
+            return vec![];
+        };
+        if time <= self.times[0] {
+            return self.value(0, stride).to_vec();
+        }
+        if time >= self.times[last] {
+            return self.value(last, stride).to_vec();
+        }
+        // times are increasing, find the key before time
+        let next = self.times.partition_point(|t| *t <= time);
+        let prev = next - 1;
+        let delta = self.times[next] - self.times[prev];
+        let t = if delta > 0.0 {
+            (time - self.times[prev]) / delta
+        } else {
+            0.0
+        };
+
+        let (a, b) = (self.value(prev, stride), self.value(next, stride));
+        let mut result: Vec<f32> = match self.interpolation {
+            Interpolation::Step => a.to_vec(),
+            Interpolation::Linear => {
+                if self.property == AnimationProperty::Rotation {
+                    let a = Quat::from_slice(a);
+                    let b = Quat::from_slice(b);
+                    return a.slerp(b, t).to_array().to_vec();
+                }
+                a.iter().zip(b).map(|(a, b)| a + (b - a) * t).collect()
+            }
+            Interpolation::CubicSpline => {
+                // hermite spline, tangents are scaled by the key interval
+                let out_tangent = &self.values[(prev * 3 + 2) * stride..(prev * 3 + 3) * stride];
+                let in_tangent = &self.values[(next * 3) * stride..(next * 3 + 1) * stride];
+                let (t2, t3) = (t * t, t * t * t);
+                (0..stride)
+                    .map(|i| {
+                        (2.0 * t3 - 3.0 * t2 + 1.0) * a[i]
+                            + (t3 - 2.0 * t2 + t) * out_tangent[i] * delta
+                            + (-2.0 * t3 + 3.0 * t2) * b[i]
+                            + (t3 - t2) * in_tangent[i] * delta
+                    })
+                    .collect()
+            }
+        };
+        if self.property == AnimationProperty::Rotation {
+            result = Vec4::from_slice(&result).normalize().to_array().to_vec();
+        }
+        result
+    }
+
+    fn value(&self, key: usize, stride: usize) -> &[f32] {
+        let key = match self.interpolation {
+            Interpolation::CubicSpline => key * 3 + 1,
+            _ => key,
+        };
+        &self.values[key * stride..(key + 1) * stride]
+    }
+}
+
+pub struct AnimationClip {
+    pub name: String,
+    pub channels: Vec<AnimationChannel>,
+}
+
+impl AnimationClip {
+    pub fn duration(&self) -> f32 {
+        self.channels
+            .iter()
+            .map(|channel| channel.duration())
+            .fold(0.0, f32::max)
+    }
+}
+
+/// a clip being played by an AnimationPlayer
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationState {
+    pub clip: usize,
+    pub time: f32,
+    pub speed: f32,
+    /// relative to the other playing clips
+    pub weight: f32,
+    pub looping: bool,
+}
+
+struct CrossFade {
+    duration: f32,
+    elapsed: f32,
+    // weights of the states fading out, the faded in state is the last one
+    from_weights: Vec<f32>,
+}
+
+/// plays and blends the clips of a model, sampled values are written to the targets' Transform by AnimationSystem
+pub struct AnimationPlayer {
+    pub clips: Vec<AnimationClip>,
+    pub states: Vec<AnimationState>,
+    paused: bool,
+    fade: Option<CrossFade>,
+}
+
+impl AnimationPlayer {
+    pub fn new(clips: Vec<AnimationClip>) -> AnimationPlayer {
+        AnimationPlayer {
+            clips,
+            states: vec![],
+            paused: false,
+            fade: None,
+        }
+    }
+
+    pub fn clip_index(&self, name: &str) -> Option<usize> {
+        self.clips.iter().position(|clip| clip.name == name)
+    }
+
+    /// play a clip from the start on its own, looping
+    pub fn play(&mut self, clip: usize) -> &mut AnimationState {
+        self.states.clear();
+        self.fade = None;
+        self.paused = false;
+        self.states.push(Self::new_state(clip, 1.0));
+        self.states.last_mut().unwrap()
+    }
+
+    /// play a clip together with the current ones, or change its weight if it is already playing
+    pub fn blend(&mut self, clip: usize, weight: f32) -> &mut AnimationState {
+        self.paused = false;
+        let index = match self.states.iter().position(|state| state.clip == clip) {
+            Some(index) => index,
+            None => {
+                self.states.push(Self::new_state(clip, weight));
+                self.states.len() - 1
+            }
+        };
+        self.states[index].weight = weight;
+        &mut self.states[index]
+    }
+
+    /// fade the playing clips out and this one in over duration seconds
+    pub fn cross_fade(&mut self, clip: usize, duration: f32) {
+        if self.states.is_empty() || duration <= 0.0 {
+            self.play(clip);
+            return;
+        }
+        self.paused = false;
+        self.states.retain(|state| state.clip != clip);
+        let from_weights = self.states.iter().map(|state| state.weight).collect();
+        self.states.push(Self::new_state(clip, 0.0));
+        self.fade = Some(CrossFade {
+            duration,
+            elapsed: 0.0,
+            from_weights,
+        });
+    }
+
+    pub fn stop(&mut self) {
+        self.states.clear();
+        self.fade = None;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn is_playing(&self) -> bool {
+        !self.paused && !self.states.is_empty()
+    }
+
+    pub fn set_looping(&mut self, looping: bool) {
+        self.states
+            .iter_mut()
+            .for_each(|state| state.looping = looping);
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.states.iter_mut().for_each(|state| state.speed = speed);
+    }
+
+    /// move every playing clip forward, looping clips wrap, the others stop at their end
+    pub fn advance(&mut self, delta_time: f32) {
+        if self.paused {
+            return;
+        }
+        for state in self.states.iter_mut() {
+            let duration = self
+                .clips
+                .get(state.clip)
+                .map_or(0.0, |clip| clip.duration());
+            state.time += delta_time * state.speed;
+            if state.looping && duration > 0.0 {
+                state.time = state.time.rem_euclid(duration);
+            } else {
+                state.time = state.time.clamp(0.0, duration);
+            }
+        }
+
+        if let Some(fade) = &mut self.fade {
+            fade.elapsed += delta_time;
+            let t = (fade.elapsed / fade.duration).min(1.0);
+            let last = self.states.len() - 1;
+            for (state, from) in self.states[..last].iter_mut().zip(&fade.from_weights) {
+                state.weight = from * (1.0 - t);
+            }
+            self.states[last].weight = t;
+            if t >= 1.0 {
+                self.states.drain(..last);
+                self.fade = None;
+            }
+        }
+    }
+
+    /// blended value of every animated property, weights are normalized per property
+    pub fn sample(&self) -> Vec<(EntityId, AnimationProperty, Vec<f32>)> {
+        let mut blended: HashMap<(EntityId, AnimationProperty), (Vec<f32>, f32)> = HashMap::new();
+        let mut order = vec![];
+        for state in &self.states {
+            let Some(clip) = self.clips.get(state.clip) else {
+                continue;
+            };
+            if state.weight <= 0.0 {
+                continue;
+            }
+            for channel in &clip.channels {
+                let value = channel.sample(state.time);
+                let key = (channel.target, channel.property);
+                let Some((sum, total)) = blended.get_mut(&key) else {
+                    let weighted = value.iter().map(|v| v * state.weight).collect();
+                    blended.insert(key, (weighted, state.weight));
+                    order.push(key);
+                    continue;
+                };
+                // q and -q are the same rotation, keep them in one hemisphere before summing
+                let sign = if channel.property == AnimationProperty::Rotation
+                    && sum.iter().zip(&value).map(|(a, b)| a * b).sum::<f32>() < 0.0
+                {
+                    -1.0
+                } else {
+                    1.0
+                };
+                sum.iter_mut()
+                    .zip(&value)
+                    .for_each(|(sum, v)| *sum += v * state.weight * sign);
+                *total += state.weight;
+            }
+        }
+
+        order
+            .into_iter()
+            .map(|key| {
+                let (sum, total) = blended.remove(&key).unwrap();
+                let mut value: Vec<f32> = sum.into_iter().map(|v| v / total).collect();
+                if key.1 == AnimationProperty::Rotation {
+                    value = Vec4::from_slice(&value).normalize().to_array().to_vec();
+                }
+                (key.0, key.1, value)
+            })
+            .collect()
+    }
+
+    fn new_state(clip: usize, weight: f32) -> AnimationState {
+        AnimationState {
+            clip,
+            time: 0.0,
+            speed: 1.0,
+            weight,
+            looping: true,
+        }
+    }
+}
@@ -3,13 +3,235 @@ use std::mem;
 use glam::Mat4;
 use wgpu::{util::DeviceExt, VertexBufferLayout};
 
-use crate::renderer::Renderer;
+use crate::{
+    components::materials::shader::ShaderParser, renderer::Renderer, utils::buffer::write_or_grow,
+};
+
+// 0-4 are mesh attributes, 9 is the mesh's vertex color
+const FIRST_INSTANCE_LOCATION: u32 = 5;
+const VERTEX_COLOR_LOCATION: u32 = 9;
+// webgl2 only has 16 vertex attributes
+const MAX_INSTANCE_LOCATION: u32 = 15;
+
+/// name of the matrix of the default layout, the transform of every instance
+pub const MODEL_MATRIX: &str = "model_matrix";
+
+/// one named per instance attribute, a matrix takes `columns` consecutive locations
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstanceAttribute {
+    pub name: String,
+    pub format: wgpu::VertexFormat,
+    pub columns: u32,
+    pub offset: wgpu::BufferAddress,
+    pub location: u32,
+}
+
+/// the per instance attributes of an Instance buffer, packed in the order they're added,
+/// so a #[repr(C)] struct of f32 arrays with the same fields matches it.
+/// vertex buffers need 4 byte aligned offsets, so 2 byte formats like Unorm8x2 take 4 bytes,
+/// the struct needs a [u8; 2] of padding after them
+/// locations start at 5 and skip 9, the default layout is the mat4 "model_matrix" at 5-8
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstanceLayout {
+    attributes: Vec<InstanceAttribute>,
+}
+
+impl Default for InstanceLayout {
+    fn default() -> Self {
+        InstanceLayout::new().with_matrix(MODEL_MATRIX)
+    }
+}
+
+impl InstanceLayout {
+    /// a layout without attributes
+    pub fn new() -> InstanceLayout {
+        InstanceLayout { attributes: vec![] }
+    }
+
+    /// add a scalar or vector attribute, read as `instance.<name>` in the shader
+    pub fn with_attribute(self, name: &str, format: wgpu::VertexFormat) -> InstanceLayout {
+        self.push(name, format, 1)
+    }
+
+    /// add a mat4, stored as 4 vec4 columns `<name>_0..<name>_3`, read with `instance_<name>(instance)`
+    pub fn with_matrix(self, name: &str) -> InstanceLayout {
+        self.push(name, wgpu::VertexFormat::Float32x4, 4)
+    }
+
+    fn push(mut self, name: &str, format: wgpu::VertexFormat, columns: u32) -> InstanceLayout {
+        assert!(
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+            "instance attribute name {:?} is not a shader identifier",
+            name
+        );
+        assert!(
+            self.attribute(name).is_none(),
+            "instance attribute {} is added twice",
+            name
+        );
+        assert!(
+            !matches!(
+                format,
+                wgpu::VertexFormat::Float64
+                    | wgpu::VertexFormat::Float64x2
+                    | wgpu::VertexFormat::Float64x3
+                    | wgpu::VertexFormat::Float64x4
+            ),
+            "instance attribute {} is {:?}, f64 attributes aren't supported",
+            name,
+            format
+        );
+        let mut location = self
+            .attributes
+            .last()
+            .map_or(FIRST_INSTANCE_LOCATION, |last| last.location + last.columns);
+        if (location..location + columns).contains(&VERTEX_COLOR_LOCATION) {
+            location = VERTEX_COLOR_LOCATION + 1;
+        }
+        assert!(
+            location + columns - 1 <= MAX_INSTANCE_LOCATION,
+            "instance attribute {} needs location {}, the limit is {}",
+            name,
+            location + columns - 1,
+            MAX_INSTANCE_LOCATION
+        );
+        self.attributes.push(InstanceAttribute {
+            name: name.to_string(),
+            format,
+            columns,
+            offset: self.stride(),
+            location,
+        });
+        self
+    }
+
+    pub fn attributes(&self) -> &[InstanceAttribute] {
+        &self.attributes
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&InstanceAttribute> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+    }
+
+    /// bytes per instance, a multiple of 4
+    pub fn stride(&self) -> wgpu::BufferAddress {
+        self.attributes.last().map_or(0, |last| {
+            (last.offset + last.format.size() * last.columns as u64)
+                .next_multiple_of(wgpu::VERTEX_STRIDE_ALIGNMENT)
+        })
+    }
+
+    pub fn vertex_attributes(&self) -> Vec<wgpu::VertexAttribute> {
+        self.attributes
+            .iter()
+            .flat_map(|attribute| {
+                (0..attribute.columns).map(|column| wgpu::VertexAttribute {
+                    offset: attribute.offset + attribute.format.size() * column as u64,
+                    shader_location: attribute.location + column,
+                    format: attribute.format,
+                })
+            })
+            .collect()
+    }
+
+    /// the InstanceInput struct, and a function building each matrix
+    pub fn shader_chunk(&self) -> String {
+        let mut fields = vec![];
+        let mut functions = vec![];
+        for attribute in &self.attributes {
+            let ty = wgsl_type(attribute.format);
+            if attribute.columns == 1 {
+                fields.push(format!(
+                    "    @location({}) {}: {},",
+                    attribute.location, attribute.name, ty
+                ));
+                continue;
+            }
+            let columns: Vec<String> = (0..attribute.columns)
+                .map(|column| format!("instance.{}_{}", attribute.name, column))
+                .collect();
+            for column in 0..attribute.columns {
+                fields.push(format!(
+                    "    @location({}) {}_{}: {},",
+                    attribute.location + column,
+                    attribute.name,
+                    column,
+                    ty
+                ));
+            }
+            functions.push(format!(
+                "fn instance_{}(instance: InstanceInput) -> mat4x4<f32> {{\n    return mat4x4<f32>({});\n}}",
+                attribute.name,
+                columns.join(", ")
+            ));
+        }
+        let mut chunk = format!("struct InstanceInput {{\n{}\n}};\n", fields.join("\n"));
+        for function in functions {
+            chunk.push_str(&function);
+            chunk.push('\n');
+        }
+        chunk
+    }
+
+    /// define HAS_INSTANCE and INSTANCE_<NAME> for every attribute, and make `#include <InstanceInput>` the shader_chunk
+    pub fn configure_parser(&self, parser: &mut ShaderParser) {
+        parser
+            .defines
+            .insert("HAS_INSTANCE".to_string(), "true".to_string());
+        for attribute in &self.attributes {
+            parser.defines.insert(
+                format!("INSTANCE_{}", attribute.name.to_uppercase()),
+                "true".to_string(),
+            );
+        }
+        parser
+            .includes
+            .insert("InstanceInput".to_string(), self.shader_chunk());
+    }
+
+    /// resolve the instance defines and include of a shader before it goes to a material's `shader` config
+    pub fn parse_shader(&self, shader: &str) -> String {
+        let mut parser = ShaderParser::new();
+        self.configure_parser(&mut parser);
+        parser.parse_shader(shader)
+    }
+}
+
+fn wgsl_type(format: wgpu::VertexFormat) -> &'static str {
+    use wgpu::VertexFormat::*;
+    match format {
+        Float32 => "f32",
+        Uint32 => "u32",
+        Sint32 => "i32",
+        Float32x2 | Float16x2 | Unorm8x2 | Snorm8x2 | Unorm16x2 | Snorm16x2 => "vec2<f32>",
+        Float32x3 => "vec3<f32>",
+        Float32x4 | Float16x4 | Unorm8x4 | Snorm8x4 | Unorm16x4 | Snorm16x4 | Unorm10_10_10_2 => {
+            "vec4<f32>"
+        }
+        Uint32x2 | Uint8x2 | Uint16x2 => "vec2<u32>",
+        Uint32x3 => "vec3<u32>",
+        Uint32x4 | Uint8x4 | Uint16x4 => "vec4<u32>",
+        Sint32x2 | Sint8x2 | Sint16x2 => "vec2<i32>",
+        Sint32x3 => "vec3<i32>",
+        Sint32x4 | Sint8x4 | Sint16x4 => "vec4<i32>",
+        Float64 | Float64x2 | Float64x3 | Float64x4 => {
+            panic!("f64 instance attributes aren't supported")
+        }
+    }
+}
 
 pub struct Instance {
     pub buffer: wgpu::Buffer,
     pub buffer_index: u32,
-    pub data: Vec<InstanceData>,
+    pub layout: InstanceLayout,
+    /// cpu copy of the buffer, layout.stride() bytes per instance
+    pub contents: Vec<u8>,
     pub start_location: u32,
+    vertex_attributes: Vec<wgpu::VertexAttribute>,
 }
 
 #[repr(C)]
@@ -19,12 +241,46 @@ pub struct InstanceData {
 }
 
 impl Instance {
+    /// instances with the default layout, a model matrix each
     pub fn new(data: Vec<InstanceData>, renderer: &Renderer) -> Self {
+        Self::with_layout(InstanceLayout::default(), &data, renderer)
+    }
+
+    /// data is any Pod slice packed like the layout, e.g. a #[repr(C)] struct per instance
+    pub fn with_layout<T: bytemuck::Pod>(
+        layout: InstanceLayout,
+        data: &[T],
+        renderer: &Renderer,
+    ) -> Self {
+        let contents = bytemuck::cast_slice(data).to_vec();
+        check_contents(&layout, &contents);
+        let vertex_attributes = layout.vertex_attributes();
         Instance {
-            buffer: Self::get_buffer(&data, &renderer.device),
+            buffer: renderer
+                .device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Instance Buffer"),
+                    contents: &contents,
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                }),
             buffer_index: Self::get_buffer_index(),
-            data,
-            start_location: 5,
+            start_location: vertex_attributes
+                .first()
+                .map_or(FIRST_INSTANCE_LOCATION, |attribute| {
+                    attribute.shader_location
+                }),
+            vertex_attributes,
+            layout,
+            contents,
+        }
+    }
+
+    /// the vertex buffer layout of the instance buffer, borrowing its attributes
+    pub fn vertex_buffer_layout(&self) -> VertexBufferLayout<'_> {
+        VertexBufferLayout {
+            array_stride: self.layout.stride(),
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &self.vertex_attributes,
         }
     }
 
@@ -32,14 +288,77 @@ impl Instance {
         1
     }
 
-    pub fn get_buffer(instance_data: &Vec<InstanceData>, device: &wgpu::Device) -> wgpu::Buffer {
-        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Instance Buffer"),
-            contents: bytemuck::cast_slice(&instance_data),
-            usage: wgpu::BufferUsages::VERTEX,
-        })
+    /// number of instances to draw
+    pub fn count(&self) -> u32 {
+        match self.layout.stride() {
+            0 => 0,
+            stride => (self.contents.len() as u64 / stride) as u32,
+        }
+    }
+
+    /// the model matrix of every instance, None when the layout has no "model_matrix"
+    pub fn matrices(&self) -> Option<Vec<Mat4>> {
+        let attribute = self.layout.attribute(MODEL_MATRIX)?;
+        let stride = self.layout.stride() as usize;
+        let offset = attribute.offset as usize;
+        Some(
+            self.contents
+                .chunks_exact(stride)
+                .map(|instance| {
+                    Mat4::from_cols_slice(bytemuck::cast_slice(&instance[offset..offset + 64]))
+                })
+                .collect(),
+        )
+    }
+
+    /// replace all instances, the buffer is written in place and grows when there are more instances than it holds
+    pub fn set_data<T: bytemuck::Pod>(&mut self, data: &[T], renderer: &Renderer) {
+        let contents: &[u8] = bytemuck::cast_slice(data);
+        check_contents(&self.layout, contents);
+        write_or_grow(renderer, &mut self.buffer, contents, "Instance Buffer");
+        self.contents = contents.to_vec();
     }
 
+    /// overwrite the instances from start, a range past the end appends to them
+    pub fn update_range<T: bytemuck::Pod>(
+        &mut self,
+        start: usize,
+        data: &[T],
+        renderer: &Renderer,
+    ) {
+        let count = self.count() as usize;
+        assert!(
+            start <= count,
+            "instance range starts at {} but there are {} instances",
+            start,
+            count
+        );
+        let contents: &[u8] = bytemuck::cast_slice(data);
+        check_contents(&self.layout, contents);
+        if contents.is_empty() {
+            return;
+        }
+        let offset = start * self.layout.stride() as usize;
+        let end = offset + contents.len();
+        if end > self.contents.len() {
+            self.contents.resize(end, 0);
+        }
+        self.contents[offset..end].copy_from_slice(contents);
+        if end as u64 <= self.buffer.size() {
+            renderer
+                .queue
+                .write_buffer(&self.buffer, offset as wgpu::BufferAddress, contents);
+        } else {
+            write_or_grow(
+                renderer,
+                &mut self.buffer,
+                &self.contents,
+                "Instance Buffer",
+            );
+        }
+    }
+
+    /// the default layout, a model matrix at locations 5-8
     pub fn get_buffer_layout() -> VertexBufferLayout<'static> {
         VertexBufferLayout {
             array_stride: std::mem::size_of::<Mat4>() as wgpu::BufferAddress,
@@ -69,3 +388,13 @@ impl Instance {
         }
     }
 }
+
+fn check_contents(layout: &InstanceLayout, contents: &[u8]) {
+    let stride = layout.stride() as usize;
+    assert!(
+        stride > 0 && contents.len().is_multiple_of(stride),
+        "instance data of {} bytes doesn't match the layout stride {}",
+        contents.len(),
+        stride
+    );
+}
@@ -1,13 +1,7 @@
-use wgpu::util::DeviceExt;
-
-use crate::renderer::Renderer;
-
-use super::light::LightTrait;
+use super::light::{LightData, LightTrait, LIGHT_KIND_AMBIENT};
 
 pub struct AmbientLight {
     pub uniform: AmbientLightUniform,
-    pub buffer: wgpu::Buffer,
-    pub bind_index: u32,
 }
 
 #[repr(C)]
@@ -18,35 +12,21 @@ pub struct AmbientLightUniform {
 }
 
 impl AmbientLight {
-    pub fn new(renderer: &Renderer, index: u32, uniform: AmbientLightUniform) -> Self {
-        let device = &renderer.device;
-        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Ambient Light Buffer"),
-            contents: bytemuck::cast_slice(&[uniform]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-        Self {
-            uniform,
-            buffer,
-            bind_index: index,
-        }
-    }
-
-    pub fn update_buffer(&mut self, renderer: &Renderer) {
-        renderer
-            .queue
-            .write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]))
+    pub fn new(uniform: AmbientLightUniform) -> Self {
+        Self { uniform }
     }
 }
 
 impl LightTrait for AmbientLight {
-    fn get_bind_index(&self) -> u32 {
-        self.bind_index
+    fn get_light_data(&self) -> LightData {
+        LightData {
+            kind: LIGHT_KIND_AMBIENT,
+            color: self.uniform.color,
+            intensity: self.uniform.intensity,
+            ..Default::default()
+        }
     }
 
-    fn get_buffer(&self) -> &wgpu::Buffer {
-        &self.buffer
-    }
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -1,52 +1,62 @@
-use wgpu::util::DeviceExt;
+use glam::{Mat4, Vec3};
 
-use crate::renderer::Renderer;
-
-use super::light::LightTrait;
+use super::light::{LightData, LightTrait, LIGHT_KIND_DIRECTIONAL};
+use crate::components::shadow::{directional_shadow_views, ShadowConfig, ShadowView};
 
 pub struct DirectionalLight {
     pub uniform: DirectionalLightUniform,
-    pub buffer: wgpu::Buffer,
-    pub bind_index: u32,
+    /// None for no shadows
+    pub shadow: Option<ShadowConfig>,
 }
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct DirectionalLightUniform {
+    /// the direction the light travels in
     pub direction: [f32; 3],
-    pub color: [f32; 4],
+    pub color: [f32; 3],
     pub intensity: f32,
 }
 
 impl DirectionalLight {
-    pub fn new(renderer: &Renderer, bind_index: u32, uniform: DirectionalLightUniform) -> Self {
-        let device = &renderer.device;
-        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Directional Light Buffer"),
-            contents: bytemuck::cast_slice(&[uniform]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+    pub fn new(uniform: DirectionalLightUniform) -> Self {
         Self {
             uniform,
-            buffer,
-            bind_index,
+            shadow: None,
         }
     }
 
-    pub fn update_buffer(&mut self, renderer: &Renderer) {
-        renderer
-            .queue
-            .write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.uniform]))
+    pub fn with_shadow(mut self, config: ShadowConfig) -> Self {
+        self.shadow = Some(config);
+        self
     }
 }
 
 impl LightTrait for DirectionalLight {
-    fn get_buffer(&self) -> &wgpu::Buffer {
-        &self.buffer
+    fn get_light_data(&self) -> LightData {
+        LightData {
+            kind: LIGHT_KIND_DIRECTIONAL,
+            direction: self.uniform.direction,
+            color: self.uniform.color,
+            intensity: self.uniform.intensity,
+            ..Default::default()
+        }
     }
 
-    fn get_bind_index(&self) -> u32 {
-        self.bind_index
+    fn get_shadow_config(&self) -> Option<&ShadowConfig> {
+        self.shadow.as_ref()
+    }
+
+    fn get_shadow_views(&self, camera_view: Mat4, camera_projection: Mat4) -> Vec<ShadowView> {
+        match &self.shadow {
+            Some(config) => directional_shadow_views(
+                Vec3::from(self.uniform.direction),
+                config,
+                camera_view,
+                camera_projection,
+            ),
+            None => vec![],
+        }
     }
 
     fn as_any(&self) -> &dyn std::any::Any {
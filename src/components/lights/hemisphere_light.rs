@@ -0,0 +1,54 @@
+use super::light::{LightData, LightTrait, LIGHT_KIND_HEMISPHERE};
+
+/// ambient light fading from the ground color below to the sky color above
+pub struct HemisphereLight {
+    pub uniform: HemisphereLightUniform,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct HemisphereLightUniform {
+    pub sky_color: [f32; 3],
+    pub intensity: f32,
+    pub ground_color: [f32; 3],
+    /// the direction of the sky
+    pub up: [f32; 3],
+}
+
+impl Default for HemisphereLightUniform {
+    fn default() -> Self {
+        Self {
+            sky_color: [1.0; 3],
+            intensity: 1.0,
+            ground_color: [0.0; 3],
+            up: [0.0, 1.0, 0.0],
+        }
+    }
+}
+
+impl HemisphereLight {
+    pub fn new(uniform: HemisphereLightUniform) -> Self {
+        Self { uniform }
+    }
+}
+
+impl LightTrait for HemisphereLight {
+    fn get_light_data(&self) -> LightData {
+        LightData {
+            kind: LIGHT_KIND_HEMISPHERE,
+            color: self.uniform.sky_color,
+            intensity: self.uniform.intensity,
+            direction: self.uniform.up,
+            ground_color: self.uniform.ground_color,
+            ..Default::default()
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
@@ -1,9 +1,83 @@
+use anyhow::anyhow;
+use glam::Mat4;
+
+use crate::components::shadow::{ShadowConfig, ShadowView};
+
+/// the built-in shaders read all lights of the scene from one uniform array at this binding of the env bind group,
+/// `#include <Lights>` to use it
+pub const LIGHTS_BIND_INDEX: u32 = 1;
+/// webgl2 has no storage buffers, so the lights array has a fixed size
+pub const MAX_LIGHTS: usize = 16;
+
+pub const LIGHT_KIND_DIRECTIONAL: u32 = 0;
+pub const LIGHT_KIND_POINT: u32 = 1;
+pub const LIGHT_KIND_SPOT: u32 = 2;
+pub const LIGHT_KIND_AMBIENT: u32 = 3;
+pub const LIGHT_KIND_HEMISPHERE: u32 = 4;
+
 pub trait LightTrait {
-    fn get_buffer(&self) -> &wgpu::Buffer;
+    /// the light packed for the lights array, read every frame
+    fn get_light_data(&self) -> LightData;
+
+    /// Some for lights that cast shadows
+    fn get_shadow_config(&self) -> Option<&ShadowConfig> {
+        None
+    }
 
-    fn get_bind_index(&self) -> u32;
+    /// the shadow maps of the light this frame, fitted to the view camera where needed
+    fn get_shadow_views(&self, _camera_view: Mat4, _camera_projection: Mat4) -> Vec<ShadowView> {
+        vec![]
+    }
 
     fn as_any(&self) -> &dyn std::any::Any;
 
     fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
 }
+
+/// one light of any kind, fields a kind doesn't use are ignored.
+/// the layout follows `struct Light` of the Lights shader chunk
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightData {
+    pub position: [f32; 3],
+    pub kind: u32,
+    /// sky color for hemisphere lights
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// the direction the light travels in, up for hemisphere lights
+    pub direction: [f32; 3],
+    pub range: f32,
+    pub ground_color: [f32; 3],
+    pub decay: f32,
+    pub inner_cone_cos: f32,
+    pub outer_cone_cos: f32,
+    pub _padding: [f32; 2],
+}
+
+/// `struct Lights` of the Lights shader chunk
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightsUniform {
+    pub count: u32,
+    pub _padding: [u32; 3],
+    pub lights: [LightData; MAX_LIGHTS],
+}
+
+impl LightsUniform {
+    pub fn new(lights: &[LightData]) -> anyhow::Result<Self> {
+        if lights.len() > MAX_LIGHTS {
+            return Err(anyhow!(
+                "the scene has {} lights, at most {} are supported",
+                lights.len(),
+                MAX_LIGHTS
+            ));
+        }
+        let mut uniform = LightsUniform {
+            count: lights.len() as u32,
+            _padding: [0; 3],
+            lights: [LightData::default(); MAX_LIGHTS],
+        };
+        uniform.lights[..lights.len()].copy_from_slice(lights);
+        Ok(uniform)
+    }
+}
@@ -1,3 +1,6 @@
 pub mod ambient_light;
 pub mod directional_light;
+pub mod hemisphere_light;
 pub mod light;
+pub mod point_light;
+pub mod spot_light;
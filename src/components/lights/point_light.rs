@@ -0,0 +1,57 @@
+use super::light::{LightData, LightTrait, LIGHT_KIND_POINT};
+
+pub struct PointLight {
+    pub uniform: PointLightUniform,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointLightUniform {
+    pub position: [f32; 3],
+    pub intensity: f32,
+    pub color: [f32; 3],
+    /// the light fades out to 0 at range, 0 for no limit
+    pub range: f32,
+    /// exponent of the distance falloff, 2 is physically based
+    pub decay: f32,
+}
+
+impl Default for PointLightUniform {
+    fn default() -> Self {
+        Self {
+            position: [0.0; 3],
+            intensity: 1.0,
+            color: [1.0; 3],
+            range: 0.0,
+            decay: 2.0,
+        }
+    }
+}
+
+impl PointLight {
+    pub fn new(uniform: PointLightUniform) -> Self {
+        Self { uniform }
+    }
+}
+
+impl LightTrait for PointLight {
+    fn get_light_data(&self) -> LightData {
+        LightData {
+            kind: LIGHT_KIND_POINT,
+            position: self.uniform.position,
+            color: self.uniform.color,
+            intensity: self.uniform.intensity,
+            range: self.uniform.range,
+            decay: self.uniform.decay,
+            ..Default::default()
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
@@ -0,0 +1,106 @@
+use glam::{Mat4, Vec3};
+
+use super::light::{LightData, LightTrait, LIGHT_KIND_SPOT};
+use crate::components::shadow::{spot_shadow_view, ShadowConfig, ShadowView};
+
+pub struct SpotLight {
+    pub uniform: SpotLightUniform,
+    /// None for no shadows
+    pub shadow: Option<ShadowConfig>,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SpotLightUniform {
+    pub position: [f32; 3],
+    pub intensity: f32,
+    pub color: [f32; 3],
+    /// the light fades out to 0 at range, 0 for no limit
+    pub range: f32,
+    /// the direction the light points to
+    pub direction: [f32; 3],
+    /// exponent of the distance falloff, 2 is physically based
+    pub decay: f32,
+    /// cosines of the cone half angles, full light inside inner, none outside outer
+    pub inner_cone_cos: f32,
+    pub outer_cone_cos: f32,
+}
+
+impl Default for SpotLightUniform {
+    fn default() -> Self {
+        Self {
+            position: [0.0; 3],
+            intensity: 1.0,
+            color: [1.0; 3],
+            range: 0.0,
+            direction: [0.0, -1.0, 0.0],
+            decay: 2.0,
+            inner_cone_cos: 1.0,
+            outer_cone_cos: std::f32::consts::FRAC_PI_4.cos(),
+        }
+    }
+}
+
+impl SpotLightUniform {
+    /// half angles in radians from the spot direction, like glTF's innerConeAngle and outerConeAngle
+    pub fn set_cone_angles(&mut self, inner: f32, outer: f32) {
+        self.inner_cone_cos = inner.min(outer).cos();
+        self.outer_cone_cos = outer.cos();
+    }
+}
+
+impl SpotLight {
+    pub fn new(uniform: SpotLightUniform) -> Self {
+        Self {
+            uniform,
+            shadow: None,
+        }
+    }
+
+    pub fn with_shadow(mut self, config: ShadowConfig) -> Self {
+        self.shadow = Some(config);
+        self
+    }
+}
+
+impl LightTrait for SpotLight {
+    fn get_light_data(&self) -> LightData {
+        LightData {
+            kind: LIGHT_KIND_SPOT,
+            position: self.uniform.position,
+            color: self.uniform.color,
+            intensity: self.uniform.intensity,
+            direction: self.uniform.direction,
+            range: self.uniform.range,
+            decay: self.uniform.decay,
+            inner_cone_cos: self.uniform.inner_cone_cos,
+            outer_cone_cos: self.uniform.outer_cone_cos,
+            ..Default::default()
+        }
+    }
+
+    fn get_shadow_config(&self) -> Option<&ShadowConfig> {
+        self.shadow.as_ref()
+    }
+
+    fn get_shadow_views(&self, _camera_view: Mat4, _camera_projection: Mat4) -> Vec<ShadowView> {
+        match &self.shadow {
+            Some(config) => vec![spot_shadow_view(
+                Vec3::from(self.uniform.position),
+                Vec3::from(self.uniform.direction),
+                self.uniform.outer_cone_cos,
+                self.uniform.range,
+                config,
+            )],
+            None => vec![],
+        }
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
@@ -1,7 +1,7 @@
 use std::borrow::Cow;
 use wgpu::{util::DeviceExt, *};
 
-use crate::{renderer::Renderer, utils::depth_texture};
+use crate::{components::materials::render_state::RenderState, renderer::Renderer};
 
 pub struct Material {
     pub pipeline: Option<wgpu::RenderPipeline>,
@@ -15,12 +15,17 @@ pub struct MaterialConfig {
     pub shader: String,
     pub topology: wgpu::PrimitiveTopology,
     pub uniforms: Vec<f32>,
+    /// RenderState::transparent() keeps the alpha blending materials used to have
+    pub render_state: RenderState,
 }
 
 impl MaterialTrait for Material {
     fn as_any(&mut self) -> &mut dyn std::any::Any {
         self
     }
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
     fn get_name(&self) -> &str {
         "Material"
     }
@@ -29,6 +34,9 @@ impl MaterialTrait for Material {
         &self.bind_group
     }
 
+    fn get_pipeline(&self) -> Option<&wgpu::RenderPipeline> {
+        self.pipeline.as_ref()
+    }
     fn get_render_pipeline(
         &mut self,
         renderer: &Renderer,
@@ -60,32 +68,18 @@ impl MaterialTrait for Material {
             fragment: Some(wgpu::FragmentState {
                 module: &self.shader_module,
                 entry_point: "fs_main",
-                targets: &[Some(ColorTargetState {
-                    format: renderer.swapchain_format,
-                    blend: Some(BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
+                targets: &[Some(
+                    self.config
+                        .render_state
+                        .color_target(renderer.swapchain_format),
+                )],
                 compilation_options: Default::default(),
             }),
-            primitive: wgpu::PrimitiveState {
-                topology: self.config.topology, // 1.
-                strip_index_format: { 
-                    if self.config.topology == wgpu::PrimitiveTopology::TriangleStrip {
-                        Some(wgpu::IndexFormat::Uint32)
-                    } else {
-                        None
-                    }
-                },
-                front_face: wgpu::FrontFace::Ccw, // 2.
-                cull_mode: None,
-                // Setting this to anything other than Fill requires Features::NON_FILL_POLYGON_MODE
-                polygon_mode: wgpu::PolygonMode::Fill,
-                // Requires Features::DEPTH_CLIP_CONTROL
-                unclipped_depth: false,
-                // Requires Features::CONSERVATIVE_RASTERIZATION
-                conservative: false,
-            },
-            depth_stencil: Some(depth_texture::get_default_depth_stencil()),
+            primitive: self
+                .config
+                .render_state
+                .primitive_state(renderer, self.config.topology),
+            depth_stencil: Some(self.config.render_state.depth_stencil()),
             multisample: wgpu::MultisampleState{
                 count: 1,
                 mask: !0,
@@ -169,5 +163,9 @@ pub trait MaterialTrait {
         env_pipeline_layout: &Vec<&BindGroupLayout>,
         env_vertex_buffer_layout: Vec<VertexBufferLayout>,
     ) -> &wgpu::RenderPipeline;
+    // pipeline created by get_render_pipeline, used when drawing from a shared scene borrow
+    fn get_pipeline(&self) -> Option<&wgpu::RenderPipeline>;
     fn as_any(&mut self) -> &mut dyn std::any::Any;
+    // read-only downcast, used by exporters
+    fn as_any_ref(&self) -> &dyn std::any::Any;
 }
@@ -2,16 +2,13 @@ use std::borrow::Cow;
 
 use wgpu::{util::DeviceExt, ShaderModuleDescriptor, ShaderSource};
 
-use crate::{
-    components::material::MaterialTrait,
-    renderer::Renderer,
-    utils::{depth_texture, texture::Texture},
-};
+use crate::{components::material::MaterialTrait, renderer::Renderer, utils::texture::Texture};
 
-use super::shader::ShaderParser;
+use super::{render_state::RenderState, shader::ShaderParser};
 #[derive(Hash, Eq, PartialEq, Clone, Copy)]
 pub struct VertexFormatKey {
-    pub has_texture: bool, // 材质是否使用纹理
+    pub has_texture: bool,      // 材质是否使用纹理
+    pub has_vertex_color: bool, // 顶点是否带颜色
 }
 
 pub struct BasicMaterial {
@@ -27,6 +24,11 @@ pub struct BasicMaterialConfig {
     pub name: String,
     pub texture: Option<Texture>,
     pub color: [f32; 4],
+    /// multiply by the mesh's vertex colors, the mesh must have a color format
+    pub vertex_color: bool,
+    /// PointList draws point clouds
+    pub topology: wgpu::PrimitiveTopology,
+    pub render_state: RenderState,
 }
 impl Default for BasicMaterialConfig {
     fn default() -> Self {
@@ -35,6 +37,9 @@ impl Default for BasicMaterialConfig {
             shader: None,
             texture: None,
             color: [1.0, 1.0, 1.0, 1.0],
+            vertex_color: false,
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            render_state: RenderState::default(),
         }
     }
 }
@@ -46,6 +51,9 @@ impl MaterialTrait for BasicMaterial {
     fn as_any(&mut self) -> &mut dyn std::any::Any {
         self
     }
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
     fn get_name(&self) -> &str {
         "image"
     }
@@ -53,6 +61,9 @@ impl MaterialTrait for BasicMaterial {
     fn get_bind_group(&self) -> &wgpu::BindGroup {
         &self.bind_group
     }
+    fn get_pipeline(&self) -> Option<&wgpu::RenderPipeline> {
+        self.pipeline.as_ref()
+    }
     fn get_render_pipeline(
         &mut self,
         renderer: &Renderer,
@@ -85,13 +96,20 @@ impl MaterialTrait for BasicMaterial {
             fragment: Some(wgpu::FragmentState {
                 module: &self.shader_module,
                 entry_point: "fs_main",
-                targets: &[Some(renderer.swapchain_format.into())],
+                targets: &[Some(
+                    self.config
+                        .render_state
+                        .color_target(renderer.swapchain_format),
+                )],
                 compilation_options: Default::default(),
             }),
-            primitive: wgpu::PrimitiveState::default(),
+            primitive: self
+                .config
+                .render_state
+                .primitive_state(renderer, self.config.topology),
             multisample: wgpu::MultisampleState::default(),
             multiview: None,
-            depth_stencil: Some(depth_texture::get_default_depth_stencil()),
+            depth_stencil: Some(self.config.render_state.depth_stencil()),
         });
         self.pipeline = Some(pipeline);
         self.pipeline.as_ref().unwrap()
@@ -99,11 +117,16 @@ impl MaterialTrait for BasicMaterial {
 }
 
 impl BasicMaterial {
-    pub fn new(mut config: BasicMaterialConfig, renderer: &Renderer) -> BasicMaterial {
+    pub fn new(config: BasicMaterialConfig, renderer: &Renderer) -> BasicMaterial {
         let device = &renderer.device;
         let has_texture = config.texture.is_some();
-        let shader_text =
-            Self::generate_shader_text(&config.shader, &VertexFormatKey { has_texture });
+        let shader_text = Self::generate_shader_text(
+            &config.shader,
+            &VertexFormatKey {
+                has_texture,
+                has_vertex_color: config.vertex_color,
+            },
+        );
 
         let shader_module = device.create_shader_module(ShaderModuleDescriptor {
             label: Some("Shader Module"),
@@ -112,10 +135,8 @@ impl BasicMaterial {
 
         let bind_group_layout;
         let bind_group;
-        if has_texture {
-            // 纹理渲染模式
-            let texture = config.texture.unwrap();
-            config.texture = None; // 纹理已移出
+        if let Some(texture) = &config.texture {
+            // 纹理渲染模式, the texture stays in config so it can be exported
 
             bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[
@@ -205,6 +226,11 @@ impl BasicMaterial {
                 .defines
                 .insert("HAS_TEXTURE".to_string(), "true".to_string());
         }
+        if key.has_vertex_color {
+            shader_parser
+                .defines
+                .insert("HAS_VERTEX_COLOR".to_string(), "true".to_string());
+        }
         if let Some(shader) = shader {
             return shader_parser.parse_shader(shader).to_string();
         }
@@ -7,10 +7,10 @@ use wgpu::{util::DeviceExt, BindGroupEntry, ShaderModuleDescriptor, ShaderSource
 use crate::{
     components::{material::MaterialTrait, materials::shader::ShaderParser},
     renderer::Renderer,
-    utils::{depth_texture, texture::Texture},
+    utils::texture::Texture,
 };
 
-use super::basic::VertexFormatKey;
+use super::render_state::RenderState;
 
 pub struct BlinnPhongMaterial {
     pipeline: Option<wgpu::RenderPipeline>,
@@ -28,10 +28,11 @@ pub struct BlinnUniform {
     pub diffuse_strength: f32,
     pub specular_color: [f32; 3],
     pub specular_strength: f32,
+    pub ambient_color: [f32; 3],
     pub shininess: f32,
     pub opacity: f32,
     // 填充数据以满足 16 字节对齐
-    pub _padding: [f32; 2],
+    pub _padding: [f32; 3],
 }
 
 impl BlinnUniform {
@@ -41,15 +42,19 @@ impl BlinnUniform {
             diffuse_strength: config.diffuse_strength,
             specular_color: config.specular_color.to_array(),
             specular_strength: config.specular_strength,
+            ambient_color: config.ambient_color.to_array(),
             shininess: config.shininess,
             opacity: config.opacity,
-            _padding: [0.0; 2],
+            _padding: [0.0; 3],
         }
     }
 }
 
 pub struct BlinnPhongMaterialConfig {
     pub shader: Option<String>,
+    /// 环境光颜色 (MTL Ka), multiplies the ambient and hemisphere lights
+    pub ambient_color: Vec3,
+
     /// 漫反射颜色
     pub diffuse_color: Vec3,
 
@@ -65,7 +70,7 @@ pub struct BlinnPhongMaterialConfig {
     /// 镜面高光指数（Shininess）
     pub shininess: f32,
 
-    /// 环境光纹理（可选）
+    /// 环境光纹理（可选）, the textures are sampled only with use_texture
     pub ambient_texture: Option<Texture>,
 
     /// 漫反射纹理（可选）
@@ -83,15 +88,22 @@ pub struct BlinnPhongMaterialConfig {
     /// 透明度（不透明度）
     pub opacity: f32,
 
-    /// 材质 Uniform Buffer
+    /// 材质 Uniform Buffer, set by BlinnPhongMaterial::new, update_uniforms writes to it
     pub material_uniform_buffer: Option<wgpu::Buffer>,
 
+    /// the mesh has uvs, needed by every texture
     pub use_texture: bool,
+
+    /// 使用顶点颜色, multiplied with the diffuse color, the mesh must have a color format
+    pub vertex_color: bool,
+
+    pub render_state: RenderState,
 }
 
 impl Default for BlinnPhongMaterialConfig {
     fn default() -> Self {
         BlinnPhongMaterialConfig {
+            ambient_color: Vec3::new(1.0, 1.0, 1.0),
             diffuse_color: Vec3::new(0.7, 0.7, 0.7), // gray
             diffuse_strength: 1.0,
             specular_color: Vec3::new(1.0, 1.0, 1.0),
@@ -106,6 +118,8 @@ impl Default for BlinnPhongMaterialConfig {
             shader: None,
             material_uniform_buffer: None,
             use_texture: false,
+            vertex_color: false,
+            render_state: RenderState::default(),
         }
     }
 }
@@ -117,6 +131,9 @@ impl MaterialTrait for BlinnPhongMaterial {
     fn as_any(&mut self) -> &mut dyn std::any::Any {
         self
     }
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
     fn get_name(&self) -> &str {
         "blinn_phong"
     }
@@ -124,6 +141,9 @@ impl MaterialTrait for BlinnPhongMaterial {
     fn get_bind_group(&self) -> &wgpu::BindGroup {
         &self.bind_group
     }
+    fn get_pipeline(&self) -> Option<&wgpu::RenderPipeline> {
+        self.pipeline.as_ref()
+    }
     fn get_render_pipeline(
         &mut self,
         renderer: &Renderer,
@@ -156,13 +176,20 @@ impl MaterialTrait for BlinnPhongMaterial {
             fragment: Some(wgpu::FragmentState {
                 module: &self.shader_module,
                 entry_point: "fs_main",
-                targets: &[Some(renderer.swapchain_format.into())],
+                targets: &[Some(
+                    self.config
+                        .render_state
+                        .color_target(renderer.swapchain_format),
+                )],
                 compilation_options: Default::default(),
             }),
-            primitive: wgpu::PrimitiveState::default(),
+            primitive: self
+                .config
+                .render_state
+                .primitive_state(renderer, wgpu::PrimitiveTopology::TriangleList),
             multisample: wgpu::MultisampleState::default(),
             multiview: None,
-            depth_stencil: Some(depth_texture::get_default_depth_stencil()),
+            depth_stencil: Some(self.config.render_state.depth_stencil()),
         });
         self.pipeline = Some(pipeline);
         self.pipeline.as_ref().unwrap()
@@ -177,14 +204,14 @@ pub const BINDING_SPECULAR_SAMPLER: u32 = 3;
 pub const BINDING_NORMAL_TEXTURE: u32 = 4;
 pub const BINDING_NORMAL_SAMPLER: u32 = 5;
 pub const BINDING_MATERIAL_UNIFORM: u32 = 6;
+pub const BINDING_AMBIENT_TEXTURE: u32 = 7;
+pub const BINDING_AMBIENT_SAMPLER: u32 = 8;
 
 impl BlinnPhongMaterial {
-    pub fn new(config: BlinnPhongMaterialConfig, renderer: &Renderer) -> BlinnPhongMaterial {
+    pub fn new(mut config: BlinnPhongMaterialConfig, renderer: &Renderer) -> BlinnPhongMaterial {
         let device = &renderer.device;
 
-        let shader_text = Self::generate_shader_text(&VertexFormatKey {
-            has_texture: config.use_texture,
-        });
+        let shader_text = Self::generate_shader_text(&config);
         println!("shader_text for blinn: {}", shader_text);
 
         let shader_module = device.create_shader_module(ShaderModuleDescriptor {
@@ -229,6 +256,17 @@ impl BlinnPhongMaterial {
             );
         }
 
+        // 处理环境光纹理
+        if let Some(ambient_texture) = &config.ambient_texture {
+            Self::add_texture_and_sampler(
+                &mut bind_groups,
+                &mut bind_group_layouts,
+                ambient_texture,
+                BINDING_AMBIENT_TEXTURE,
+                BINDING_AMBIENT_SAMPLER,
+            );
+        }
+
         // 创建 Uniform 数据
         let material_uniform = BlinnUniform::new(&config);
         let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -260,6 +298,7 @@ impl BlinnPhongMaterial {
             entries: &bind_groups,
             label: Some("diffuse_bind_group"),
         });
+        config.material_uniform_buffer = Some(uniform_buffer);
 
         BlinnPhongMaterial {
             pipeline: None,
@@ -323,16 +362,30 @@ impl BlinnPhongMaterial {
         });
     }
 
-    fn generate_shader_text(key: &VertexFormatKey) -> String {
+    fn generate_shader_text(config: &BlinnPhongMaterialConfig) -> String {
         let mut shader_parser = ShaderParser::new();
-        if key.has_texture {
-            shader_parser
-                .defines
-                .insert("HAS_TEXTURE".to_string(), "true".to_string());
+        // textures need the uvs of use_texture
+        let textured = |texture: &Option<Texture>| config.use_texture && texture.is_some();
+        let defines = [
+            ("HAS_TEXTURE", config.use_texture),
+            ("HAS_VERTEX_COLOR", config.vertex_color),
+            ("HAS_DIFFUSE_MAP", textured(&config.diffuse_texture)),
+            ("HAS_SPECULAR_MAP", textured(&config.specular_texture)),
+            ("HAS_NORMAL_MAP", textured(&config.normal_texture)),
+            ("HAS_AMBIENT_MAP", textured(&config.ambient_texture)),
+        ];
+        for (name, enabled) in defines {
+            if enabled {
+                shader_parser
+                    .defines
+                    .insert(name.to_string(), "true".to_string());
+            }
         }
 
-        shader_parser
-            .parse_shader(include_str!("shaders/blinn_phong.wgsl"))
-            .to_string()
+        let source = config
+            .shader
+            .as_deref()
+            .unwrap_or(include_str!("shaders/blinn_phong.wgsl"));
+        shader_parser.parse_shader(source)
     }
 }
@@ -1,6 +1,7 @@
 pub mod basic;
 pub mod blinn_phong;
 pub mod pbr;
+pub mod render_state;
 pub mod shader;
 pub mod shaderlib;
 pub mod sprite;
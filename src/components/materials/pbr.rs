@@ -1,67 +1,448 @@
-use crate::{components::material::MaterialTrait, renderer::Renderer, utils::depth_texture};
+use std::borrow::Cow;
 
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat3, Vec2, Vec3, Vec4};
+use wgpu::{util::DeviceExt, BindGroupEntry, ShaderModuleDescriptor, ShaderSource};
+
+use crate::{
+    components::{
+        material::MaterialTrait,
+        materials::{
+            blinn_phong::BlinnPhongMaterial,
+            render_state::{BlendMode, RenderState},
+            shader::ShaderParser,
+        },
+        morph::MorphWeights,
+        skin::Skin,
+    },
+    renderer::Renderer,
+    utils::texture::Texture,
+};
+
+/// metallic-roughness 材质，光照使用 Cook-Torrance BRDF
 pub struct PBRMaterial {
     pub pipeline: Option<wgpu::RenderPipeline>,
     pub shader_module: wgpu::ShaderModule,
     pub bind_group: wgpu::BindGroup,
     pub bind_group_layout: wgpu::BindGroupLayout,
+    pub uniform_buffer: wgpu::Buffer,
     pub config: PBRMaterialConfig,
 }
 
-pub struct PBRMaterialConfig {}
+/// 材质的 Uniform 数据, layout matches PBRUniform in pbr.wgsl
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct PBRUniform {
+    pub base_color: [f32; 4],
+    pub emissive: [f32; 3],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub normal_scale: f32,
+    pub occlusion_strength: f32,
+    pub alpha_cutoff: f32,
+    // mat3x3 columns are padded to vec4 in wgsl
+    pub base_color_uv_transform: [[f32; 4]; 3],
+    pub metallic_roughness_uv_transform: [[f32; 4]; 3],
+    pub normal_uv_transform: [[f32; 4]; 3],
+    pub occlusion_uv_transform: [[f32; 4]; 3],
+    pub emissive_uv_transform: [[f32; 4]; 3],
+}
+
+impl PBRUniform {
+    pub fn new(config: &PBRMaterialConfig) -> Self {
+        Self {
+            base_color: config.base_color_factor.to_array(),
+            emissive: config.emissive_factor.to_array(),
+            metallic: config.metallic_factor,
+            roughness: config.roughness_factor,
+            normal_scale: config.normal_scale,
+            occlusion_strength: config.occlusion_strength,
+            alpha_cutoff: config.alpha_cutoff,
+            base_color_uv_transform: config.base_color_uv_transform.to_padded_mat3(),
+            metallic_roughness_uv_transform: config
+                .metallic_roughness_uv_transform
+                .to_padded_mat3(),
+            normal_uv_transform: config.normal_uv_transform.to_padded_mat3(),
+            occlusion_uv_transform: config.occlusion_uv_transform.to_padded_mat3(),
+            emissive_uv_transform: config.emissive_uv_transform.to_padded_mat3(),
+        }
+    }
+}
+
+/// how the base color alpha is used, same as the glTF alphaMode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AlphaMode {
+    /// alpha is ignored
+    #[default]
+    Opaque,
+    /// fragments below alpha_cutoff are discarded
+    Mask,
+    /// alpha blended, depth is tested but not written.
+    /// meshes are not sorted, blended meshes should be added after opaque ones
+    Blend,
+}
+
+/// uv offset, rotation and scale applied before sampling, like KHR_texture_transform
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureTransform {
+    pub offset: Vec2,
+    /// radians, rotates the uvs counter-clockwise
+    pub rotation: f32,
+    pub scale: Vec2,
+}
+
+impl Default for TextureTransform {
+    fn default() -> Self {
+        TextureTransform {
+            offset: Vec2::ZERO,
+            rotation: 0.0,
+            scale: Vec2::ONE,
+        }
+    }
+}
+
+impl TextureTransform {
+    pub fn to_mat3(&self) -> Mat3 {
+        Mat3::from_translation(self.offset)
+            * Mat3::from_angle(-self.rotation)
+            * Mat3::from_scale(self.scale)
+    }
+
+    fn to_padded_mat3(self) -> [[f32; 4]; 3] {
+        let mat = self.to_mat3();
+        [
+            mat.x_axis.extend(0.0).to_array(),
+            mat.y_axis.extend(0.0).to_array(),
+            mat.z_axis.extend(0.0).to_array(),
+        ]
+    }
+}
+
+/// factors are multiplied with the texture values, like the glTF metallic-roughness model.
+/// base color and emissive textures should be srgb, the others linear (Texture::from_image_linear)
+pub struct PBRMaterialConfig {
+    pub shader: Option<String>,
+    /// 材质名称（用于调试或标识）
+    pub name: String,
+
+    /// 基础颜色，线性空间，alpha 为不透明度
+    pub base_color_factor: Vec4,
+    pub base_color_texture: Option<Texture>,
+
+    /// 金属度 0 ~ 1
+    pub metallic_factor: f32,
+    /// 粗糙度 0 ~ 1
+    pub roughness_factor: f32,
+    /// roughness in the g channel, metallic in the b channel
+    pub metallic_roughness_texture: Option<Texture>,
+
+    /// 法线贴图 xy 的缩放
+    pub normal_scale: f32,
+    pub normal_texture: Option<Texture>,
+
+    /// 环境光遮蔽强度，读取 r 通道
+    pub occlusion_strength: f32,
+    pub occlusion_texture: Option<Texture>,
+
+    /// 自发光颜色
+    pub emissive_factor: Vec3,
+    pub emissive_texture: Option<Texture>,
+
+    pub base_color_uv_transform: TextureTransform,
+    pub metallic_roughness_uv_transform: TextureTransform,
+    pub normal_uv_transform: TextureTransform,
+    pub occlusion_uv_transform: TextureTransform,
+    pub emissive_uv_transform: TextureTransform,
+
+    pub alpha_mode: AlphaMode,
+    /// only used by AlphaMode::Mask
+    pub alpha_cutoff: f32,
+    /// render_state.cull_mode is ignored when double sided
+    pub double_sided: bool,
+    /// vertices are skinned by the entity's Skin (group 3), the mesh must be PositionNormalTextureSkin
+    pub skinned: bool,
+    /// vertices are blended by the mesh's morph targets and the entity's MorphWeights (group 3)
+    pub morph_targets: bool,
+    /// culls back faces by default, AlphaMode::Blend turns an opaque blend into alpha blending without depth write
+    pub render_state: RenderState,
+}
+
+impl Default for PBRMaterialConfig {
+    fn default() -> Self {
+        PBRMaterialConfig {
+            shader: None,
+            name: "pbr".to_string(),
+            base_color_factor: Vec4::ONE,
+            base_color_texture: None,
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            metallic_roughness_texture: None,
+            normal_scale: 1.0,
+            normal_texture: None,
+            occlusion_strength: 1.0,
+            occlusion_texture: None,
+            emissive_factor: Vec3::ZERO,
+            emissive_texture: None,
+            base_color_uv_transform: TextureTransform::default(),
+            metallic_roughness_uv_transform: TextureTransform::default(),
+            normal_uv_transform: TextureTransform::default(),
+            occlusion_uv_transform: TextureTransform::default(),
+            emissive_uv_transform: TextureTransform::default(),
+            alpha_mode: AlphaMode::Opaque,
+            alpha_cutoff: 0.5,
+            double_sided: false,
+            skinned: false,
+            morph_targets: false,
+            render_state: RenderState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl PBRMaterialConfig {
+    /// render_state with alpha_mode and double_sided applied, like glTF defines them
+    pub fn pipeline_render_state(&self) -> RenderState {
+        let mut render_state = self.render_state;
+        if self.alpha_mode == AlphaMode::Blend && render_state.blend == BlendMode::Opaque {
+            render_state.blend = BlendMode::Alpha;
+            render_state.depth_write = false;
+        }
+        if self.double_sided {
+            render_state.cull_mode = None;
+        }
+        render_state
+    }
+
+    pub fn has_texture(&self) -> bool {
+        self.base_color_texture.is_some()
+            || self.metallic_roughness_texture.is_some()
+            || self.normal_texture.is_some()
+            || self.occlusion_texture.is_some()
+            || self.emissive_texture.is_some()
+    }
+}
+
+// 定义绑定索引常量
+pub const BINDING_MATERIAL_UNIFORM: u32 = 0;
+pub const BINDING_BASE_COLOR_TEXTURE: u32 = 1;
+pub const BINDING_BASE_COLOR_SAMPLER: u32 = 2;
+pub const BINDING_METALLIC_ROUGHNESS_TEXTURE: u32 = 3;
+pub const BINDING_METALLIC_ROUGHNESS_SAMPLER: u32 = 4;
+pub const BINDING_NORMAL_TEXTURE: u32 = 5;
+pub const BINDING_NORMAL_SAMPLER: u32 = 6;
+pub const BINDING_OCCLUSION_TEXTURE: u32 = 7;
+pub const BINDING_OCCLUSION_SAMPLER: u32 = 8;
+pub const BINDING_EMISSIVE_TEXTURE: u32 = 9;
+pub const BINDING_EMISSIVE_SAMPLER: u32 = 10;
+
+impl PBRMaterial {
+    pub fn new(config: PBRMaterialConfig, renderer: &Renderer) -> PBRMaterial {
+        let device = &renderer.device;
+
+        let shader_text = Self::generate_shader_text(&config);
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("PBR Shader Module"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(&shader_text)),
+        });
+
+        let material_uniform = PBRUniform::new(&config);
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("PBR Material Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[material_uniform]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let mut bind_groups: Vec<BindGroupEntry> = vec![BindGroupEntry {
+            binding: BINDING_MATERIAL_UNIFORM,
+            resource: uniform_buffer.as_entire_binding(),
+        }];
+        let mut bind_group_layouts: Vec<wgpu::BindGroupLayoutEntry> =
+            vec![wgpu::BindGroupLayoutEntry {
+                binding: BINDING_MATERIAL_UNIFORM,
+                visibility: wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }];
+
+        let textures = [
+            (
+                &config.base_color_texture,
+                BINDING_BASE_COLOR_TEXTURE,
+                BINDING_BASE_COLOR_SAMPLER,
+            ),
+            (
+                &config.metallic_roughness_texture,
+                BINDING_METALLIC_ROUGHNESS_TEXTURE,
+                BINDING_METALLIC_ROUGHNESS_SAMPLER,
+            ),
+            (
+                &config.normal_texture,
+                BINDING_NORMAL_TEXTURE,
+                BINDING_NORMAL_SAMPLER,
+            ),
+            (
+                &config.occlusion_texture,
+                BINDING_OCCLUSION_TEXTURE,
+                BINDING_OCCLUSION_SAMPLER,
+            ),
+            (
+                &config.emissive_texture,
+                BINDING_EMISSIVE_TEXTURE,
+                BINDING_EMISSIVE_SAMPLER,
+            ),
+        ];
+        for (texture, texture_binding, sampler_binding) in textures {
+            if let Some(texture) = texture {
+                BlinnPhongMaterial::add_texture_and_sampler(
+                    &mut bind_groups,
+                    &mut bind_group_layouts,
+                    texture,
+                    texture_binding,
+                    sampler_binding,
+                );
+            }
+        }
+
+        let bind_group_layout =
+            BlinnPhongMaterial::create_material_bind_group_layout(device, &bind_group_layouts);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &bind_groups,
+            label: Some("pbr_bind_group"),
+        });
+
+        PBRMaterial {
+            pipeline: None,
+            shader_module,
+            bind_group,
+            bind_group_layout,
+            uniform_buffer,
+            config,
+        }
+    }
+
+    /// upload the factors after changing config, textures can't be swapped here
+    pub fn update_uniforms(&self, renderer: &Renderer) {
+        let material_uniform = PBRUniform::new(&self.config);
+        renderer.queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[material_uniform]),
+        );
+    }
+
+    fn generate_shader_text(config: &PBRMaterialConfig) -> String {
+        let mut shader_parser = ShaderParser::new();
+        let defines = [
+            ("HAS_TEXTURE", config.has_texture() || config.skinned),
+            ("HAS_SKIN", config.skinned),
+            ("HAS_MORPH", config.morph_targets),
+            ("HAS_BASE_COLOR_MAP", config.base_color_texture.is_some()),
+            (
+                "HAS_METALLIC_ROUGHNESS_MAP",
+                config.metallic_roughness_texture.is_some(),
+            ),
+            ("HAS_NORMAL_MAP", config.normal_texture.is_some()),
+            ("HAS_OCCLUSION_MAP", config.occlusion_texture.is_some()),
+            ("HAS_EMISSIVE_MAP", config.emissive_texture.is_some()),
+            ("ALPHA_MODE_OPAQUE", config.alpha_mode == AlphaMode::Opaque),
+            ("ALPHA_MODE_MASK", config.alpha_mode == AlphaMode::Mask),
+        ];
+        for (name, enabled) in defines {
+            if enabled {
+                shader_parser
+                    .defines
+                    .insert(name.to_string(), "true".to_string());
+            }
+        }
+
+        let source = config
+            .shader
+            .as_deref()
+            .unwrap_or(include_str!("shaders/pbr.wgsl"));
+        shader_parser.parse_shader(source)
+    }
+}
 
 impl MaterialTrait for PBRMaterial {
     fn as_any(&mut self) -> &mut dyn std::any::Any {
         self
     }
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
     fn get_name(&self) -> &str {
-        "image"
+        "pbr"
     }
 
     fn get_bind_group(&self) -> &wgpu::BindGroup {
         &self.bind_group
     }
+    fn get_pipeline(&self) -> Option<&wgpu::RenderPipeline> {
+        self.pipeline.as_ref()
+    }
     fn get_render_pipeline(
         &mut self,
         renderer: &Renderer,
         env_pipeline_layout: &Vec<&wgpu::BindGroupLayout>,
         env_vertex_buffer_layout: Vec<wgpu::VertexBufferLayout>,
     ) -> &wgpu::RenderPipeline {
-        if self.pipeline.is_some() {
-            return self.pipeline.as_ref().unwrap();
-        }
-        let device = &renderer.device;
-        let mut layouts = vec![&self.bind_group_layout];
-        for layout in env_pipeline_layout {
-            layouts.push(layout);
-        }
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Pipeline Layout"),
-            bind_group_layouts: &layouts.as_slice(), //[env_pipeline_layout, layouts].concat().as_slice(),
-            push_constant_ranges: &[],
-        });
+        if self.pipeline.is_none() {
+            let device = &renderer.device;
+            let mut layouts = vec![&self.bind_group_layout];
+            for layout in env_pipeline_layout {
+                layouts.push(layout);
+            }
+            // a morphed mesh binds the joint matrices together with its weights
+            let deform_layout = if self.config.morph_targets {
+                Some(MorphWeights::create_bind_group_layout(
+                    device,
+                    self.config.skinned,
+                ))
+            } else if self.config.skinned {
+                Some(Skin::create_bind_group_layout(device))
+            } else {
+                None
+            };
+            if let Some(layout) = &deform_layout {
+                layouts.push(layout);
+            }
+            let render_state = self.config.pipeline_render_state();
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("PBR Pipeline Layout"),
+                bind_group_layouts: layouts.as_slice(),
+                push_constant_ranges: &[],
+            });
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &self.shader_module,
-                entry_point: "vs_main",
-                buffers: &env_vertex_buffer_layout,
-                compilation_options: Default::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &self.shader_module,
-                entry_point: "fs_main",
-                targets: &[Some(renderer.swapchain_format.into())],
-                compilation_options: Default::default(),
-            }),
-            primitive: wgpu::PrimitiveState::default(),
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-            depth_stencil: Some(depth_texture::get_default_depth_stencil()),
-        });
-        self.pipeline = Some(pipeline);
+            let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("PBR Render Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &self.shader_module,
+                    entry_point: "vs_main",
+                    buffers: &env_vertex_buffer_layout,
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &self.shader_module,
+                    entry_point: "fs_main",
+                    targets: &[Some(render_state.color_target(renderer.swapchain_format))],
+                    compilation_options: Default::default(),
+                }),
+                primitive: render_state
+                    .primitive_state(renderer, wgpu::PrimitiveTopology::TriangleList),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                depth_stencil: Some(render_state.depth_stencil()),
+            });
+            self.pipeline = Some(pipeline);
+        }
         self.pipeline.as_ref().unwrap()
     }
 }
@@ -0,0 +1,138 @@
+use crate::{renderer::Renderer, utils::depth_texture};
+
+/// how a material's color is combined with what's already drawn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlendMode {
+    /// overwrite the target
+    Opaque,
+    /// src * src_alpha + dst * (1 - src_alpha)
+    Alpha,
+    /// src * src_alpha + dst, for glows and particles
+    Additive,
+    /// the shader outputs color already multiplied by alpha, src + dst * (1 - src_alpha)
+    Premultiplied,
+    Custom(wgpu::BlendState),
+}
+
+impl BlendMode {
+    pub fn blend_state(&self) -> Option<wgpu::BlendState> {
+        match self {
+            BlendMode::Opaque => None,
+            BlendMode::Alpha => Some(wgpu::BlendState::ALPHA_BLENDING),
+            BlendMode::Additive => Some(wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            }),
+            BlendMode::Premultiplied => Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+            BlendMode::Custom(state) => Some(*state),
+        }
+    }
+}
+
+/// the fixed function state of a material's pipeline, shared by all material configs.
+/// it is read once when the pipeline is created
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderState {
+    pub blend: BlendMode,
+    /// None draws both faces
+    pub cull_mode: Option<wgpu::Face>,
+    pub front_face: wgpu::FrontFace,
+    /// off draws over everything, depth_compare is ignored
+    pub depth_test: bool,
+    pub depth_write: bool,
+    pub depth_compare: wgpu::CompareFunction,
+    /// pushes the depth back, e.g. against z fighting of decals
+    pub depth_bias: wgpu::DepthBiasState,
+    /// Line and Point need the adapter's POLYGON_MODE_LINE and POLYGON_MODE_POINT features,
+    /// they fall back to Fill without
+    pub polygon_mode: wgpu::PolygonMode,
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        let depth_stencil = depth_texture::get_default_depth_stencil();
+        RenderState {
+            blend: BlendMode::Opaque,
+            cull_mode: None,
+            front_face: wgpu::FrontFace::Ccw,
+            depth_test: true,
+            depth_write: depth_stencil.depth_write_enabled,
+            depth_compare: depth_stencil.depth_compare,
+            depth_bias: depth_stencil.bias,
+            polygon_mode: wgpu::PolygonMode::Fill,
+        }
+    }
+}
+
+impl RenderState {
+    /// alpha blended, the default of Material and SpriteMaterial
+    pub fn transparent() -> Self {
+        RenderState {
+            blend: BlendMode::Alpha,
+            ..Default::default()
+        }
+    }
+
+    pub fn color_target(&self, format: wgpu::TextureFormat) -> wgpu::ColorTargetState {
+        wgpu::ColorTargetState {
+            format,
+            blend: self.blend.blend_state(),
+            write_mask: wgpu::ColorWrites::ALL,
+        }
+    }
+
+    pub fn primitive_state(
+        &self,
+        renderer: &Renderer,
+        topology: wgpu::PrimitiveTopology,
+    ) -> wgpu::PrimitiveState {
+        wgpu::PrimitiveState {
+            topology,
+            // meshes have u32 indices
+            strip_index_format: topology.is_strip().then_some(wgpu::IndexFormat::Uint32),
+            front_face: self.front_face,
+            cull_mode: self.cull_mode,
+            polygon_mode: self.supported_polygon_mode(&renderer.device),
+            ..Default::default()
+        }
+    }
+
+    pub fn depth_stencil(&self) -> wgpu::DepthStencilState {
+        wgpu::DepthStencilState {
+            depth_write_enabled: self.depth_write,
+            depth_compare: if self.depth_test {
+                self.depth_compare
+            } else {
+                wgpu::CompareFunction::Always
+            },
+            bias: self.depth_bias,
+            ..depth_texture::get_default_depth_stencil()
+        }
+    }
+
+    /// the polygon mode if the device has its feature, else Fill
+    pub fn supported_polygon_mode(&self, device: &wgpu::Device) -> wgpu::PolygonMode {
+        let feature = match self.polygon_mode {
+            wgpu::PolygonMode::Fill => return wgpu::PolygonMode::Fill,
+            wgpu::PolygonMode::Line => wgpu::Features::POLYGON_MODE_LINE,
+            wgpu::PolygonMode::Point => wgpu::Features::POLYGON_MODE_POINT,
+        };
+        if device.features().contains(feature) {
+            self.polygon_mode
+        } else {
+            log::warn!(
+                "{:?} polygon mode isn't supported by the adapter, Fill is used",
+                self.polygon_mode
+            );
+            wgpu::PolygonMode::Fill
+        }
+    }
+}
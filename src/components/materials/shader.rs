@@ -5,6 +5,8 @@ use super::shaderlib;
 
 pub struct ShaderParser {
     pub defines: HashMap<String, String>,
+    /// chunks for #include that aren't in SHADER_LIB, e.g. the InstanceInput of an InstanceLayout
+    pub includes: HashMap<String, String>,
     ifdef_stack: Vec<IfdefState>,
     define_regex: Regex,
     define_val_regex: Regex,
@@ -25,6 +27,7 @@ impl ShaderParser {
     pub fn new() -> ShaderParser {
         ShaderParser {
             defines: HashMap::new(),
+            includes: HashMap::new(),
             ifdef_stack: Vec::new(),
             define_regex: Regex::new(r"^#define\s+(\w+)").unwrap(),
             define_val_regex: Regex::new(r"^#define\s+(\w+)\s+(\w+)").unwrap(),
@@ -133,11 +136,19 @@ impl ShaderParser {
         // check for #include
         let include_vals = self.include_regex.captures(line.trim());
         if include_vals.is_some() {
+            // includes in inactive blocks are skipped, they may not exist without the define
+            if self.ifdef_stack.iter().any(|state| !state.is_active) {
+                return None;
+            }
             let include_val = include_vals.unwrap().get(1).unwrap().as_str();
-            if shaderlib::SHADER_LIB.contains_key(include_val) {
-                let shader = shaderlib::SHADER_LIB.get(include_val).unwrap();
+            let shader = self
+                .includes
+                .get(include_val)
+                .or_else(|| shaderlib::SHADER_LIB.get(include_val));
+            if let Some(shader) = shader {
                 let mut parser = ShaderParser::new();
                 parser.defines = self.defines.clone();
+                parser.includes = self.includes.clone();
                 let res = parser.parse_shader(shader);
                 return Some(res);
             } else {
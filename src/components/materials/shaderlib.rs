@@ -1,6 +1,8 @@
 use lazy_static::lazy_static;
 use std::collections::HashMap;
 
+use crate::components::{lights::light::MAX_LIGHTS, shadow::MAX_SHADOW_MAPS};
+
 lazy_static! {
     pub static ref SHADER_LIB: HashMap<String, String> = {
         let mut map = HashMap::new();
@@ -12,6 +14,34 @@ lazy_static! {
             "VertexStruct".to_string(),
             include_str!("shaderlibs/vertexstruct.wgsl").to_string(),
         );
+        map.insert(
+            "TransformUniform".to_string(),
+            include_str!("shaderlibs/transform.wgsl").to_string(),
+        );
+        map.insert(
+            "SkinUniform".to_string(),
+            include_str!("shaderlibs/skin.wgsl").to_string(),
+        );
+        map.insert(
+            "MorphTargets".to_string(),
+            include_str!("shaderlibs/morph.wgsl").to_string(),
+        );
+        map.insert(
+            "Lights".to_string(),
+            format!(
+                "const MAX_LIGHTS: u32 = {}u;\n{}",
+                MAX_LIGHTS,
+                include_str!("shaderlibs/lights.wgsl")
+            ),
+        );
+        map.insert(
+            "Shadows".to_string(),
+            format!(
+                "const MAX_SHADOW_MAPS: u32 = {}u;\n{}",
+                MAX_SHADOW_MAPS,
+                include_str!("shaderlibs/shadows.wgsl")
+            ),
+        );
         map
     };
 }
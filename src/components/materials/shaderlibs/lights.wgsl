@@ -0,0 +1,87 @@
+const LIGHT_KIND_DIRECTIONAL: u32 = 0u;
+const LIGHT_KIND_POINT: u32 = 1u;
+const LIGHT_KIND_SPOT: u32 = 2u;
+const LIGHT_KIND_AMBIENT: u32 = 3u;
+const LIGHT_KIND_HEMISPHERE: u32 = 4u;
+
+// LightData in rust, fields a kind doesn't use are ignored
+struct Light {
+    position: vec3<f32>,
+    kind: u32,
+    color: vec3<f32>,
+    intensity: f32,
+    direction: vec3<f32>,
+    range: f32,
+    ground_color: vec3<f32>,
+    decay: f32,
+    inner_cone_cos: f32,
+    outer_cone_cos: f32,
+}
+
+struct Lights {
+    count: u32,
+    lights: array<Light, MAX_LIGHTS>,
+}
+
+// direction to the light and the light arriving at the surface
+struct LightSample {
+    direction: vec3<f32>,
+    radiance: vec3<f32>,
+}
+
+@group(1) @binding(1) var<uniform> lights: Lights;
+
+fn light_count() -> u32 {
+  return min(lights.count, MAX_LIGHTS);
+}
+
+// inverse power falloff, smoothly windowed to 0 at range. range 0 has no limit
+fn distance_attenuation(distance: f32, range: f32, decay: f32) -> f32 {
+  var attenuation = 1. / pow(max(distance, 0.01), decay);
+  if (range > 0.) {
+    let ratio = distance / range;
+    let window = clamp(1. - ratio * ratio * ratio * ratio, 0., 1.);
+    attenuation = attenuation * window * window;
+  }
+  return attenuation;
+}
+
+// ambient and hemisphere lights reach every surface, other kinds give nothing
+fn ambient_light(light: Light, normal: vec3<f32>) -> vec3<f32> {
+  if (light.kind == LIGHT_KIND_AMBIENT) {
+    return light.color * light.intensity;
+  }
+  if (light.kind == LIGHT_KIND_HEMISPHERE) {
+    let weight = dot(normal, normalize(light.direction)) * 0.5 + 0.5;
+    return mix(light.ground_color, light.color, weight) * light.intensity;
+  }
+  return vec3<f32>(0.);
+}
+
+// direct light from a directional, point or spot light, other kinds give no radiance
+fn sample_light(light: Light, position: vec3<f32>) -> LightSample {
+  var out: LightSample;
+  if (light.kind == LIGHT_KIND_DIRECTIONAL) {
+    out.direction = normalize(-light.direction);
+    out.radiance = light.color * light.intensity;
+    return out;
+  }
+  if (light.kind != LIGHT_KIND_POINT && light.kind != LIGHT_KIND_SPOT) {
+    out.direction = vec3<f32>(0., 1., 0.);
+    out.radiance = vec3<f32>(0.);
+    return out;
+  }
+  let to_light = light.position - position;
+  let distance = length(to_light);
+  out.direction = to_light / max(distance, 0.0001);
+  var attenuation = distance_attenuation(distance, light.range, light.decay);
+  if (light.kind == LIGHT_KIND_SPOT) {
+    let cos_angle = dot(normalize(light.direction), -out.direction);
+    let cone = clamp((cos_angle - light.outer_cone_cos) / max(light.inner_cone_cos - light.outer_cone_cos, 0.0001), 0., 1.);
+    attenuation = attenuation * cone * cone;
+  }
+  out.radiance = light.color * light.intensity * attenuation;
+  return out;
+}
+
+#include <Shadows>
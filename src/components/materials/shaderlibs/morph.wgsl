@@ -0,0 +1,45 @@
+#ifdef HAS_MORPH
+const MAX_MORPH_TARGETS: u32 = 64u;
+
+struct MorphUniform {
+    // 4 weights per vec4 to keep the uniform array stride
+    weights: array<vec4<f32>, 16>,
+    count: u32,
+    vertex_count: u32,
+}
+
+@group(3) @binding(1) var<uniform> morph: MorphUniform;
+// position offset then normal offset of every vertex, target after target
+@group(3) @binding(2) var morph_texture: texture_2d<f32>;
+
+fn morph_texel(index: u32) -> vec3<f32> {
+    let width = textureDimensions(morph_texture).x;
+    return textureLoad(morph_texture, vec2<u32>(index % width, index / width), 0).xyz;
+}
+
+fn morph_weight(target_index: u32) -> f32 {
+    return morph.weights[target_index / 4u][target_index % 4u];
+}
+
+fn morph_position(vertex_index: u32, position: vec3<f32>) -> vec3<f32> {
+    var result = position;
+    for (var i = 0u; i < morph.count; i++) {
+        let weight = morph_weight(i);
+        if (weight != 0.) {
+            result += morph_texel((i * morph.vertex_count + vertex_index) * 2u) * weight;
+        }
+    }
+    return result;
+}
+
+fn morph_normal(vertex_index: u32, normal: vec3<f32>) -> vec3<f32> {
+    var result = normal;
+    for (var i = 0u; i < morph.count; i++) {
+        let weight = morph_weight(i);
+        if (weight != 0.) {
+            result += morph_texel((i * morph.vertex_count + vertex_index) * 2u + 1u) * weight;
+        }
+    }
+    return result;
+}
+#endif
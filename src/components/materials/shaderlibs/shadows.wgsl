@@ -0,0 +1,60 @@
+// layer i of shadow_texture belongs to shadows.maps[i], cascades of a light are ordered near to far
+struct ShadowMap {
+    view_projection: mat4x4<f32>,
+    light_index: u32,
+    split_depth: f32,
+    bias: f32,
+    normal_offset: f32,
+    pcf_radius: u32,
+    perspective: u32,
+    _padding: vec2<u32>,
+}
+
+struct Shadows {
+    count: u32,
+    texel_size: f32,
+    _padding: vec2<u32>,
+    maps: array<ShadowMap, MAX_SHADOW_MAPS>,
+}
+
+@group(1) @binding(2) var<uniform> shadows: Shadows;
+@group(1) @binding(3) var shadow_texture: texture_depth_2d_array;
+@group(1) @binding(4) var shadow_sampler: sampler_comparison;
+
+fn sample_shadow_map(index: u32, position: vec3<f32>, normal: vec3<f32>) -> f32 {
+  let shadow_map = shadows.maps[index];
+  var offset = shadow_map.normal_offset;
+  if (shadow_map.perspective != 0u) {
+    // texels of a spot light grow with the distance
+    offset = offset * distance(lights.lights[shadow_map.light_index].position, position);
+  }
+  let clip = shadow_map.view_projection * vec4<f32>(position + normal * offset, 1.);
+  let ndc = clip.xyz / clip.w;
+  // nothing is known about casters outside of the map
+  if (clip.w <= 0. || abs(ndc.x) > 1. || abs(ndc.y) > 1. || ndc.z > 1.) {
+    return 1.;
+  }
+  let uv = ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+  let depth = ndc.z - shadow_map.bias;
+  let radius = i32(shadow_map.pcf_radius);
+  var lit = 0.;
+  for (var y = -radius; y <= radius; y = y + 1) {
+    for (var x = -radius; x <= radius; x = x + 1) {
+      let texel = vec2<f32>(f32(x), f32(y)) * shadows.texel_size;
+      lit = lit + textureSampleCompareLevel(shadow_texture, shadow_sampler, uv + texel, index, depth);
+    }
+  }
+  let side = f32(radius * 2 + 1);
+  return lit / (side * side);
+}
+
+// 1 for lit, 0 for fully shadowed, view_depth picks the cascade
+fn light_shadow(light_index: u32, position: vec3<f32>, normal: vec3<f32>, view_depth: f32) -> f32 {
+  for (var i = 0u; i < shadows.count; i = i + 1u) {
+    let shadow_map = shadows.maps[i];
+    if (shadow_map.light_index == light_index && view_depth <= shadow_map.split_depth) {
+      return sample_shadow_map(i, position, normal);
+    }
+  }
+  return 1.;
+}
@@ -0,0 +1,17 @@
+#ifdef HAS_SKIN
+const MAX_JOINTS: u32 = 128u;
+
+struct SkinUniform {
+    // inverse(mesh global) * joint global * inverse bind matrix
+    joint_matrices: array<mat4x4<f32>, MAX_JOINTS>,
+}
+
+@group(3) @binding(0) var<uniform> skin: SkinUniform;
+
+fn skin_matrix(joints: vec4<u32>, weights: vec4<f32>) -> mat4x4<f32> {
+    return skin.joint_matrices[joints.x] * weights.x
+        + skin.joint_matrices[joints.y] * weights.y
+        + skin.joint_matrices[joints.z] * weights.z
+        + skin.joint_matrices[joints.w] * weights.w;
+}
+#endif
@@ -1 +1,6 @@
-@group(1) @binding(1) var<uniform> transform: mat4x4<f32>;
\ No newline at end of file
+struct TransformUniform {
+    model_matrix: mat4x4<f32>,
+    normal_matrix: mat4x4<f32>,
+}
+
+@group(2) @binding(0) var<uniform> transform: TransformUniform;
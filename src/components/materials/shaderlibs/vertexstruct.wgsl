@@ -5,6 +5,9 @@ struct VertexOutput {
     #ifdef HAS_TEXTURE
     @location(2) tex_coord: vec2<f32>,
     #endif
+    #ifdef HAS_VERTEX_COLOR
+    @location(3) color: vec4<f32>,
+    #endif
 }
 
 struct VertexInput {
@@ -13,4 +16,14 @@ struct VertexInput {
     #ifdef HAS_TEXTURE
     @location(2) tex_coord: vec2<f32>,
     #endif
+    #ifdef HAS_SKIN
+    @location(3) joints: vec4<u32>,
+    @location(4) weights: vec4<f32>,
+    #endif
+    #ifdef HAS_VERTEX_COLOR
+    @location(9) color: vec4<f32>,
+    #endif
+    #ifdef HAS_MORPH
+    @builtin(vertex_index) vertex_index: u32,
+    #endif
 }
\ No newline at end of file
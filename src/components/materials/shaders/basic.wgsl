@@ -6,6 +6,8 @@ struct CameraUniform {
 };
 @group(1) @binding(0) var<uniform> camera: CameraUniform;
 
+#include <TransformUniform>
+
 // 顶点输入/输出
 struct VertexInput {
     @builtin(vertex_index) vertex_index: u32,
@@ -13,18 +15,24 @@ struct VertexInput {
     #ifdef HAS_TEXTURE
     @location(2) tex_coord: vec2<f32>,
     #endif
+    #ifdef HAS_VERTEX_COLOR
+    @location(9) color: vec4<f32>,
+    #endif
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coord: vec2<f32>,
+    #ifdef HAS_VERTEX_COLOR
+    @location(1) color: vec4<f32>,
+    #endif
 };
 
 // 顶点着色器
 @vertex
 fn vs_main(in: VertexInput) -> VertexOutput {
     var out: VertexOutput;
-    out.clip_position = camera.view_projection * vec4<f32>(in.position, 1.0);
+    out.clip_position = camera.view_projection * transform.model_matrix * vec4<f32>(in.position, 1.0);
     
     #ifdef HAS_TEXTURE
         out.tex_coord = in.tex_coord;
@@ -34,6 +42,9 @@ fn vs_main(in: VertexInput) -> VertexOutput {
         let y = f32((in.vertex_index / 2u) % 2u);
         out.tex_coord = vec2<f32>(x, y);
     #endif
+    #ifdef HAS_VERTEX_COLOR
+        out.color = in.color;
+    #endif
     
     return out;
 }
@@ -52,8 +63,12 @@ fn vs_main(in: VertexInput) -> VertexOutput {
 fn fs_main(in: VertexOutput) -> @location(0) vec4f {
 #ifdef HAS_TEXTURE
     // 纹理采样
-    return textureSample(t_diffuse, s_diffuse, in.tex_coord);
+    var result = textureSample(t_diffuse, s_diffuse, in.tex_coord);
 #else
-    return vec4f(color);
+    var result = vec4f(color);
+#endif
+#ifdef HAS_VERTEX_COLOR
+    result = result * in.color;
 #endif
+    return result;
 }
\ No newline at end of file
@@ -1,70 +1,119 @@
 #include <CameraUniform>
 #include <VertexStruct>
-
-struct DirectionLight{
-    direction: vec3<f32>,
-    color: vec3<f32>,
-    intensity: f32,
-}
+#include <TransformUniform>
+#include <Lights>
 
 struct BlinnUniform {
     diffuse_color: vec3<f32>,
     diffuse_strength: f32,
     specular_color: vec3<f32>,
     specular_strength: f32,
+    ambient_color: vec3<f32>,
     shininess: f32,
     opacity: f32,
-    _padding: vec2<f32>, // 保持 16 字节对齐
+    // 结构体按 16 字节对齐，末尾的填充和 Rust 端的 _padding 一样大
 };
 
 @group(0) @binding(0) var<uniform> color: vec4f;
-@group(1) @binding(1) var<uniform> direction_light: DirectionLight;
 @group(1) @binding(0) var<uniform> camera: CameraUniform;
 @vertex
 fn vs_main(vertex:VertexInput) -> VertexOutput  {
   var out: VertexOutput;
-  var clip_position = camera.projection_matrix * camera.view_matrix * vec4<f32>(vertex.position, 1.);
+  let world_position = transform.model_matrix * vec4<f32>(vertex.position, 1.);
+  var clip_position = camera.projection_matrix * camera.view_matrix * world_position;
   out.clip_position = clip_position;
-  out.position = vertex.position;
-  out.normal = vertex.normal;
+  out.position = world_position.xyz;
+  out.normal = normalize((transform.normal_matrix * vec4<f32>(vertex.normal, 0.)).xyz);
   #ifdef HAS_TEXTURE
   out.tex_coord = vertex.tex_coord; 
   #endif
+  #ifdef HAS_VERTEX_COLOR
+  out.color = vertex.color;
+  #endif
   return out;
 }
 
 
-@group(0) @binding(0)
-var diffuse_texture: texture_2d<f32>;
-@group(0) @binding(1)
-var diffuse_sampler: sampler;
+#ifdef HAS_DIFFUSE_MAP
+@group(0) @binding(0) var diffuse_texture: texture_2d<f32>;
+@group(0) @binding(1) var diffuse_sampler: sampler;
+#endif
+#ifdef HAS_SPECULAR_MAP
+@group(0) @binding(2) var specular_texture: texture_2d<f32>;
+@group(0) @binding(3) var specular_sampler: sampler;
+#endif
+#ifdef HAS_NORMAL_MAP
+@group(0) @binding(4) var normal_texture: texture_2d<f32>;
+@group(0) @binding(5) var normal_sampler: sampler;
+#endif
 @group(0) @binding(6) var<uniform> material: BlinnUniform;
+#ifdef HAS_AMBIENT_MAP
+@group(0) @binding(7) var ambient_texture: texture_2d<f32>;
+@group(0) @binding(8) var ambient_sampler: sampler;
+#endif
+
+#ifdef HAS_NORMAL_MAP
+// tangent frame from screen space derivatives, like the pbr shader
+fn perturb_normal(n: vec3<f32>, position: vec3<f32>, uv: vec2<f32>, sampled: vec3<f32>) -> vec3<f32> {
+    let dp1 = dpdx(position);
+    let dp2 = dpdy(position);
+    let duv1 = dpdx(uv);
+    let duv2 = dpdy(uv);
+    let dp2perp = cross(dp2, n);
+    let dp1perp = cross(n, dp1);
+    let t = dp2perp * duv1.x + dp1perp * duv2.x;
+    let b = dp2perp * duv1.y + dp1perp * duv2.y;
+    let invmax = inverseSqrt(max(dot(t, t), dot(b, b)));
+    let tbn = mat3x3<f32>(t * invmax, b * invmax, n);
+    return normalize(tbn * (sampled * 2. - 1.));
+}
+#endif
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4f {
-    #ifdef HAS_TEXTURE
-    let color =  textureSample(diffuse_texture, diffuse_sampler, in.tex_coord);
+    #ifdef HAS_DIFFUSE_MAP
+    var color =  textureSample(diffuse_texture, diffuse_sampler, in.tex_coord);
     #else
-    let color = vec4<f32>(material.diffuse_color, material.opacity);
+    var color = vec4<f32>(material.diffuse_color, material.opacity);
+    #endif
+    #ifdef HAS_VERTEX_COLOR
+    color = color * in.color;
     #endif
 
-    //todo: not use hard code ambient_strength
-    let ambient_strength = 0.1;
-    let ambient_color = direction_light.color * ambient_strength;
+    var normal = normalize(in.normal);
+    #ifdef HAS_NORMAL_MAP
+    normal = perturb_normal(normal, in.position, in.tex_coord, textureSample(normal_texture, normal_sampler, in.tex_coord).xyz);
+    #endif
+    var specular_color = material.specular_color * material.specular_strength;
+    #ifdef HAS_SPECULAR_MAP
+    specular_color = specular_color * textureSample(specular_texture, specular_sampler, in.tex_coord).rgb;
+    #endif
+    var ambient_color = material.ambient_color;
+    #ifdef HAS_AMBIENT_MAP
+    ambient_color = ambient_color * textureSample(ambient_texture, ambient_sampler, in.tex_coord).rgb;
+    #endif
+    // pow(0, 0) is undefined
+    let shininess = max(material.shininess, 1.);
 
-    let light_dir = normalize(-direction_light.direction);
     let view_dir = normalize(camera.position.xyz - in.position);
-    let half_dir = normalize(view_dir + light_dir);
-
-    let diffuse_strength = max(dot(in.normal, light_dir), 0.0);
-    let diffuse_color = direction_light.color * diffuse_strength;
+    let view_depth = -(camera.view_matrix * vec4<f32>(in.position, 1.)).z;
+    var ambient = vec3<f32>(0.);
+    var diffuse = vec3<f32>(0.);
+    var specular = vec3<f32>(0.);
+    for (var i = 0u; i < light_count(); i = i + 1u) {
+      let light = lights.lights[i];
+      ambient = ambient + ambient_light(light, normal);
+      let light_sample = sample_light(light, in.position);
+      let radiance = light_sample.radiance * light_shadow(i, in.position, in.normal, view_depth);
+      let n_dot_l = dot(normal, light_sample.direction);
+      if (n_dot_l > 0.) {
+        let half_dir = normalize(view_dir + light_sample.direction);
+        diffuse = diffuse + n_dot_l * radiance;
+        specular = specular + pow(max(dot(normal, half_dir), 0.), shininess) * radiance;
+      }
+    }
 
-    let specular_strength = pow(max(dot(in.normal, half_dir), 0.0), 32.0);
-    let specular_color = specular_strength * direction_light.color;
-
-    // specular_strength = pow(max(dot(in.normal, half_dir), 0.0), material.shininess) * material.specular_strength;
-    // let specular_color = specular_strength * direction_light.color * material.specular_color;
-
-    let result = (ambient_color + diffuse_color + specular_color) * color.rgb;
+    let result = (ambient * ambient_color + diffuse * material.diffuse_strength) * color.rgb + specular * specular_color;
 
     return  vec4f(result, color.a);
-}
\ No newline at end of file
+}
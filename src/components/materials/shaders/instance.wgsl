@@ -1,42 +1,41 @@
 // Vertex shader
 struct VertexInput {
     @location(0) position: vec3<f32>,
-    @location(1) tex_coord: vec2<f32>,
+    @location(2) tex_coord: vec2<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coord: vec2<f32>,
-    @location(1) z: f32,
+    @location(1) color: vec4<f32>,
 }
 struct CameraUniform {
     view_projection: mat4x4<f32>
 }
-struct InstanceInput {
-    @location(5) model_matrix_0: vec4<f32>,
-    @location(6) model_matrix_1: vec4<f32>,
-    @location(7) model_matrix_2: vec4<f32>,
-    @location(8) model_matrix_3: vec4<f32>,
-};
+// InstanceLayout::parse_shader fills it in, the default layout only has model_matrix
+#include <InstanceInput>
 
 @group(1) @binding(0) // 1.
 var<uniform> camera: CameraUniform;
 
+#include <TransformUniform>
+
 @vertex
 fn vs_main(
     model: VertexInput,
     instance: InstanceInput,
 ) -> VertexOutput {
-    let instance_matrix = mat4x4<f32>(
-        instance.model_matrix_0,
-        instance.model_matrix_1,
-        instance.model_matrix_2,
-        instance.model_matrix_3,
-    );
     var out: VertexOutput;
     out.tex_coord = model.tex_coord;
-    out.clip_position = camera.view_projection * instance_matrix * vec4<f32>(model.position, 1.0);
-    out.z = instance.model_matrix_3.x/10.0;
+#ifdef INSTANCE_UV_OFFSET_SCALE
+    // xy offset, zw scale of the instance's cell in an atlas
+    out.tex_coord = instance.uv_offset_scale.xy + model.tex_coord * instance.uv_offset_scale.zw;
+#endif
+    out.color = vec4<f32>(1.0);
+#ifdef INSTANCE_COLOR
+    out.color = instance.color;
+#endif
+    out.clip_position = camera.view_projection * transform.model_matrix * instance_model_matrix(instance) * vec4<f32>(model.position, 1.0);
     return out;
 }
 
@@ -49,7 +48,5 @@ var s_diffuse: sampler;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    var color =  textureSample(t_diffuse, s_diffuse, in.tex_coord);
-    color.r = in.z;
-    return color;
-}
\ No newline at end of file
+    return textureSample(t_diffuse, s_diffuse, in.tex_coord) * in.color;
+}
@@ -0,0 +1,200 @@
+#include <CameraUniform>
+#include <VertexStruct>
+#include <TransformUniform>
+#include <SkinUniform>
+#include <MorphTargets>
+#include <Lights>
+
+struct PBRUniform {
+    base_color: vec4<f32>,
+    emissive: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    alpha_cutoff: f32,
+    base_color_uv_transform: mat3x3<f32>,
+    metallic_roughness_uv_transform: mat3x3<f32>,
+    normal_uv_transform: mat3x3<f32>,
+    occlusion_uv_transform: mat3x3<f32>,
+    emissive_uv_transform: mat3x3<f32>,
+};
+
+@group(1) @binding(0) var<uniform> camera: CameraUniform;
+@group(0) @binding(0) var<uniform> material: PBRUniform;
+
+#ifdef HAS_BASE_COLOR_MAP
+@group(0) @binding(1) var base_color_texture: texture_2d<f32>;
+@group(0) @binding(2) var base_color_sampler: sampler;
+#endif
+#ifdef HAS_METALLIC_ROUGHNESS_MAP
+@group(0) @binding(3) var metallic_roughness_texture: texture_2d<f32>;
+@group(0) @binding(4) var metallic_roughness_sampler: sampler;
+#endif
+#ifdef HAS_NORMAL_MAP
+@group(0) @binding(5) var normal_texture: texture_2d<f32>;
+@group(0) @binding(6) var normal_sampler: sampler;
+#endif
+#ifdef HAS_OCCLUSION_MAP
+@group(0) @binding(7) var occlusion_texture: texture_2d<f32>;
+@group(0) @binding(8) var occlusion_sampler: sampler;
+#endif
+#ifdef HAS_EMISSIVE_MAP
+@group(0) @binding(9) var emissive_texture: texture_2d<f32>;
+@group(0) @binding(10) var emissive_sampler: sampler;
+#endif
+
+const PI: f32 = 3.14159265359;
+
+fn transform_uv(transform: mat3x3<f32>, uv: vec2<f32>) -> vec2<f32> {
+  return (transform * vec3<f32>(uv, 1.)).xy;
+}
+
+@vertex
+fn vs_main(vertex: VertexInput) -> VertexOutput {
+  var out: VertexOutput;
+  var position = vec4<f32>(vertex.position, 1.);
+  var normal = vec4<f32>(vertex.normal, 0.);
+  // morph targets blend in bind pose, before skinning
+  #ifdef HAS_MORPH
+  position = vec4<f32>(morph_position(vertex.vertex_index, position.xyz), 1.);
+  normal = vec4<f32>(morph_normal(vertex.vertex_index, normal.xyz), 0.);
+  #endif
+  #ifdef HAS_SKIN
+  let skinning = skin_matrix(vertex.joints, vertex.weights);
+  position = skinning * position;
+  normal = skinning * normal;
+  #endif
+  let world_position = transform.model_matrix * position;
+  out.clip_position = camera.projection_matrix * camera.view_matrix * world_position;
+  out.position = world_position.xyz;
+  out.normal = normalize((transform.normal_matrix * normal).xyz);
+  #ifdef HAS_TEXTURE
+  out.tex_coord = vertex.tex_coord;
+  #endif
+  return out;
+}
+
+// GGX / Trowbridge-Reitz normal distribution
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+  let a = roughness * roughness;
+  let a2 = a * a;
+  let d = n_dot_h * n_dot_h * (a2 - 1.) + 1.;
+  return a2 / (PI * d * d);
+}
+
+// Smith's method with Schlick-GGX for both view and light direction
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+  let r = roughness + 1.;
+  let k = r * r / 8.;
+  let ggx_v = n_dot_v / (n_dot_v * (1. - k) + k);
+  let ggx_l = n_dot_l / (n_dot_l * (1. - k) + k);
+  return ggx_v * ggx_l;
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+  return f0 + (1. - f0) * pow(clamp(1. - cos_theta, 0., 1.), 5.);
+}
+
+// cook-torrance reflectance of one light, times n dot l
+fn direct_light(n: vec3<f32>, v: vec3<f32>, l: vec3<f32>, radiance: vec3<f32>, albedo: vec3<f32>, metallic: f32, roughness: f32) -> vec3<f32> {
+  let h = normalize(v + l);
+  let n_dot_v = max(dot(n, v), 0.0001);
+  let n_dot_l = max(dot(n, l), 0.);
+  let n_dot_h = max(dot(n, h), 0.);
+  let h_dot_v = max(dot(h, v), 0.);
+
+  // dielectrics reflect 4% at normal incidence, metals tint the reflection by base color
+  let f0 = mix(vec3<f32>(0.04), albedo, metallic);
+  let f = fresnel_schlick(h_dot_v, f0);
+  let d = distribution_ggx(n_dot_h, roughness);
+  let g = geometry_smith(n_dot_v, n_dot_l, roughness);
+  let specular = d * g * f / (4. * n_dot_v * max(n_dot_l, 0.0001));
+  let k_d = (vec3<f32>(1.) - f) * (1. - metallic);
+  return (k_d * albedo / PI + specular) * radiance * n_dot_l;
+}
+
+#ifdef HAS_NORMAL_MAP
+// tangent frame from screen space derivatives, meshes don't carry tangents
+fn perturb_normal(n: vec3<f32>, position: vec3<f32>, uv: vec2<f32>, sampled: vec3<f32>) -> vec3<f32> {
+  let dp1 = dpdx(position);
+  let dp2 = dpdy(position);
+  let duv1 = dpdx(uv);
+  let duv2 = dpdy(uv);
+  let dp2perp = cross(dp2, n);
+  let dp1perp = cross(n, dp1);
+  let t = dp2perp * duv1.x + dp1perp * duv2.x;
+  let b = dp2perp * duv1.y + dp1perp * duv2.y;
+  let invmax = inverseSqrt(max(dot(t, t), dot(b, b)));
+  let tbn = mat3x3<f32>(t * invmax, b * invmax, n);
+  let tangent_normal = (sampled * 2. - 1.) * vec3<f32>(material.normal_scale, material.normal_scale, 1.);
+  return normalize(tbn * tangent_normal);
+}
+#endif
+
+@fragment
+fn fs_main(in: VertexOutput, @builtin(front_facing) front_facing: bool) -> @location(0) vec4f {
+  var base_color = material.base_color;
+  #ifdef HAS_BASE_COLOR_MAP
+  base_color = base_color * textureSample(base_color_texture, base_color_sampler, transform_uv(material.base_color_uv_transform, in.tex_coord));
+  #endif
+  #ifdef ALPHA_MODE_MASK
+  if (base_color.a < material.alpha_cutoff) {
+    discard;
+  }
+  #endif
+
+  var metallic = material.metallic;
+  var roughness = material.roughness;
+  #ifdef HAS_METALLIC_ROUGHNESS_MAP
+  // glTF packs roughness in g and metallic in b
+  let metallic_roughness = textureSample(metallic_roughness_texture, metallic_roughness_sampler, transform_uv(material.metallic_roughness_uv_transform, in.tex_coord));
+  roughness = roughness * metallic_roughness.g;
+  metallic = metallic * metallic_roughness.b;
+  #endif
+  roughness = clamp(roughness, 0.04, 1.);
+  metallic = clamp(metallic, 0., 1.);
+
+  var n = normalize(in.normal);
+  if (!front_facing) {
+    n = -n;
+  }
+  #ifdef HAS_NORMAL_MAP
+  let normal_uv = transform_uv(material.normal_uv_transform, in.tex_coord);
+  let sampled_normal = textureSample(normal_texture, normal_sampler, normal_uv).xyz;
+  n = perturb_normal(n, in.position, normal_uv, sampled_normal);
+  #endif
+
+  var occlusion = 1.;
+  #ifdef HAS_OCCLUSION_MAP
+  let occlusion_sample = textureSample(occlusion_texture, occlusion_sampler, transform_uv(material.occlusion_uv_transform, in.tex_coord)).r;
+  occlusion = 1. + material.occlusion_strength * (occlusion_sample - 1.);
+  #endif
+
+  var emissive = material.emissive;
+  #ifdef HAS_EMISSIVE_MAP
+  emissive = emissive * textureSample(emissive_texture, emissive_sampler, transform_uv(material.emissive_uv_transform, in.tex_coord)).rgb;
+  #endif
+
+  let albedo = base_color.rgb;
+  let v = normalize(camera.position.xyz - in.position);
+  let view_depth = -(camera.view_matrix * vec4<f32>(in.position, 1.)).z;
+  var direct = vec3<f32>(0.);
+  var irradiance = vec3<f32>(0.);
+  for (var i = 0u; i < light_count(); i = i + 1u) {
+    let light = lights.lights[i];
+    irradiance = irradiance + ambient_light(light, n);
+    let light_sample = sample_light(light, in.position);
+    let radiance = light_sample.radiance * light_shadow(i, in.position, n, view_depth);
+    direct = direct + direct_light(n, v, light_sample.direction, radiance, albedo, metallic, roughness);
+  }
+
+  //todo: environment map
+  let ambient = irradiance * albedo * occlusion;
+  let color = ambient + direct + emissive;
+  #ifdef ALPHA_MODE_OPAQUE
+  return vec4f(color, 1.);
+  #else
+  return vec4f(color, base_color.a);
+  #endif
+}
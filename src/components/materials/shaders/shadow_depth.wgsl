@@ -0,0 +1,41 @@
+#include <TransformUniform>
+#include <SkinUniform>
+#include <MorphTargets>
+#ifdef HAS_INSTANCE
+#include <InstanceInput>
+#endif
+
+// the shadow map being drawn
+@group(0) @binding(0) var<uniform> light_view_projection: mat4x4<f32>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    #ifdef HAS_SKIN
+    @location(3) joints: vec4<u32>,
+    @location(4) weights: vec4<f32>,
+    #endif
+    #ifdef HAS_MORPH
+    @builtin(vertex_index) vertex_index: u32,
+    #endif
+}
+
+// depth only, no fragment stage
+@vertex
+fn vs_main(
+  vertex: VertexInput,
+  #ifdef HAS_INSTANCE
+  instance: InstanceInput,
+  #endif
+) -> @builtin(position) vec4<f32> {
+  var position = vec4<f32>(vertex.position, 1.);
+  #ifdef HAS_MORPH
+  position = vec4<f32>(morph_position(vertex.vertex_index, position.xyz), 1.);
+  #endif
+  #ifdef HAS_SKIN
+  position = skin_matrix(vertex.joints, vertex.weights) * position;
+  #endif
+  #ifdef INSTANCE_MODEL_MATRIX
+  position = instance_model_matrix(instance) * position;
+  #endif
+  return light_view_projection * transform.model_matrix * position;
+}
@@ -12,6 +12,9 @@ struct VertexOutput {
 }
 
 #include <CameraUniform>
+#include <TransformUniform>
+
+@group(1) @binding(0) var<uniform> camera: CameraUniform;
 
 @group(0) @binding(2)
 var<uniform> size: vec3<f32>;
@@ -22,7 +25,7 @@ fn vs_main(
     input: VertexInput,
 ) -> VertexOutput {
     var out: VertexOutput;
-    var mvPosition = camera.view_matrix * vec4<f32>(input.position, 1.0);
+    var mvPosition = camera.view_matrix * transform.model_matrix * vec4<f32>(input.position, 1.0);
 
     let m = f32(input.vertex_index%2u);
     let h = floor(f32(input.vertex_index) / 2.);
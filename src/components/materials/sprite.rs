@@ -1,15 +1,12 @@
-use wgpu::{util::DeviceExt, BlendState, ColorTargetState, ShaderModuleDescriptor, ShaderSource};
+use wgpu::{util::DeviceExt, ShaderModuleDescriptor, ShaderSource};
 
 use crate::{
     components::material::MaterialTrait,
     renderer::Renderer,
-    utils::{
-        depth_texture,
-        texture::{self, Texture},
-    },
+    utils::texture::{self, Texture},
 };
 
-use super::shader::ShaderParser;
+use super::{render_state::RenderState, shader::ShaderParser};
 
 pub struct SpriteMaterial {
     pipeline: Option<wgpu::RenderPipeline>,
@@ -29,6 +26,7 @@ pub struct SpriteMaterialConfig {
     pub shader: Option<String>,
     pub name: String,
     pub texture: Option<Texture>,
+    pub render_state: RenderState,
 }
 impl Default for SpriteMaterialConfig {
     fn default() -> Self {
@@ -40,6 +38,7 @@ impl Default for SpriteMaterialConfig {
             shader: None,
             texture: None,
             size_attenuation: true,
+            render_state: RenderState::transparent(),
         }
     }
 }
@@ -51,6 +50,9 @@ impl MaterialTrait for SpriteMaterial {
     fn as_any(&mut self) -> &mut dyn std::any::Any {
         self
     }
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
 
     fn get_name(&self) -> &str {
         "Sprite"
@@ -59,6 +61,9 @@ impl MaterialTrait for SpriteMaterial {
     fn get_bind_group(&self) -> &wgpu::BindGroup {
         &self.bind_group
     }
+    fn get_pipeline(&self) -> Option<&wgpu::RenderPipeline> {
+        self.pipeline.as_ref()
+    }
     fn get_render_pipeline(
         &mut self,
         renderer: &Renderer,
@@ -91,21 +96,24 @@ impl MaterialTrait for SpriteMaterial {
             fragment: Some(wgpu::FragmentState {
                 module: &self.shader_module,
                 entry_point: "fs_main",
-                targets: &[Some(ColorTargetState {
-                    format: renderer.swapchain_format,
-                    blend: Some(BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
+                targets: &[Some(
+                    self.config
+                        .render_state
+                        .color_target(renderer.swapchain_format),
+                )],
                 compilation_options: Default::default(),
             }),
-            primitive: wgpu::PrimitiveState::default(),
+            primitive: self
+                .config
+                .render_state
+                .primitive_state(renderer, wgpu::PrimitiveTopology::TriangleList),
             multisample: wgpu::MultisampleState {
                 count: 1,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
             multiview: None,
-            depth_stencil: Some(depth_texture::get_default_depth_stencil()),
+            depth_stencil: Some(self.config.render_state.depth_stencil()),
         });
         self.pipeline = Some(pipeline);
         self.pipeline.as_ref().unwrap()
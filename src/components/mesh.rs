@@ -1,14 +1,20 @@
+use crate::components::morph::MorphTargets;
+use crate::geometry::mesh_data::MeshData;
 use crate::renderer::Renderer;
+use crate::utils::buffer::write_or_grow;
 use bytemuck::{Pod, Zeroable};
 use wgpu::util::DeviceExt;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum VertexFormat {
-    PositionOnly,          // 仅位置 (xyz)
-    PositionTexture,       // 位置 + 纹理坐标 (xyz, uv)
-    PositionNormal,        // 位置 + 法线 (xyz, xyz)
-    PositionNormalTexture, // 位置 + 法线 + 纹理坐标 (xyz, xyz, uv)
-    Custom,                // 自定义格式
+    PositionOnly,              // 仅位置 (xyz)
+    PositionTexture,           // 位置 + 纹理坐标 (xyz, uv)
+    PositionNormal,            // 位置 + 法线 (xyz, xyz)
+    PositionNormalTexture,     // 位置 + 法线 + 纹理坐标 (xyz, xyz, uv)
+    PositionNormalTextureSkin, // 位置 + 法线 + 纹理坐标 + 骨骼索引 + 骨骼权重 (xyz, xyz, uv, u32x4, f32x4)
+    PositionColor,             // 位置 + 顶点颜色 (xyz, rgba)
+    PositionNormalColor,       // 位置 + 法线 + 顶点颜色 (xyz, xyz, rgba)
+    Custom,                    // 自定义格式
 }
 
 #[repr(C)]
@@ -25,6 +31,31 @@ pub struct VertexPositionNormalTexture {
     pub normal: [f32; 3],
     pub tex_coords: [f32; 2],
 }
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct VertexPositionNormalTextureSkin {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub tex_coords: [f32; 2],
+    pub joints: [u32; 4],
+    pub weights: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct VertexPositionColor {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct VertexPositionNormalColor {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub color: [f32; 4],
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
 pub struct VertexPositionTexture {
@@ -39,6 +70,12 @@ pub struct Mesh {
     pub vertex_format: VertexFormat,
     pub vertex_buffer_layout: wgpu::VertexBufferLayout<'static>,
     pub vertex_attributes: Vec<wgpu::VertexAttribute>,
+    /// blend shapes, drawn with a material that has morph targets enabled and a MorphWeights component
+    pub morph_targets: Option<MorphTargets>,
+    /// cpu copy of the vertex buffer, laid out by vertex_attributes, kept for exporters
+    pub vertices: Vec<u8>,
+    /// cpu copy of the index buffer
+    pub indices: Vec<u32>,
 }
 
 impl Mesh {
@@ -53,7 +90,7 @@ impl Mesh {
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Vertex Buffer"),
                 contents: vertices_contents,
-                usage: wgpu::BufferUsages::VERTEX,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             });
 
         let index_buffer = renderer
@@ -61,7 +98,7 @@ impl Mesh {
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Index Buffer"),
                 contents: bytemuck::cast_slice(&indices),
-                usage: wgpu::BufferUsages::INDEX,
+                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
             });
 
         // 根据格式创建顶点属性
@@ -81,6 +118,9 @@ impl Mesh {
             vertex_buffer_layout,
             vertex_attributes,
             num_indices: indices.len() as u32,
+            morph_targets: None,
+            vertices: vertices_contents.to_vec(),
+            indices,
         };
 
         // 更新布局中的 attributes 引用
@@ -89,13 +129,48 @@ impl Mesh {
         instance
     }
 
+    /// upload MeshData, the format follows its streams: normals and uvs win over colors,
+    /// tangents aren't uploaded since no built-in shader reads them
+    pub fn from_data(data: &MeshData, renderer: &Renderer) -> Mesh {
+        let uvs = data.uvs.as_ref();
+        let colors = data.colors.as_ref().filter(|_| uvs.is_none());
+        let format = match (data.normals.is_some(), uvs.is_some(), colors.is_some()) {
+            (true, true, _) => VertexFormat::PositionNormalTexture,
+            (true, false, true) => VertexFormat::PositionNormalColor,
+            (true, false, false) => VertexFormat::PositionNormal,
+            (false, true, _) => VertexFormat::PositionTexture,
+            (false, false, true) => VertexFormat::PositionColor,
+            (false, false, false) => VertexFormat::PositionOnly,
+        };
+        // 按 create_vertex_attributes 的顺序交错写入
+        let mut vertices: Vec<f32> = Vec::with_capacity(data.positions.len() * 12);
+        for (i, position) in data.positions.iter().enumerate() {
+            vertices.extend_from_slice(position);
+            if let Some(normals) = &data.normals {
+                vertices.extend_from_slice(&normals[i]);
+            }
+            if let Some(uvs) = uvs {
+                vertices.extend_from_slice(&uvs[i]);
+            }
+            if let Some(colors) = colors {
+                vertices.extend_from_slice(&colors[i]);
+            }
+        }
+        Mesh::new(
+            bytemuck::cast_slice(&vertices),
+            data.indices.clone(),
+            format,
+            renderer,
+        )
+    }
+
     pub fn new_position_only(vertices: Vec<f32>, indices: Vec<u32>, renderer: &Renderer) -> Mesh {
         let vertex_buffer = renderer
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Vertex Buffer"),
                 contents: bytemuck::cast_slice(&vertices),
-                usage: wgpu::BufferUsages::VERTEX,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             });
 
         let index_buffer = renderer
@@ -103,7 +178,7 @@ impl Mesh {
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Index Buffer"),
                 contents: bytemuck::cast_slice(&indices),
-                usage: wgpu::BufferUsages::INDEX,
+                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
             });
 
         // 根据格式创建顶点属性
@@ -124,6 +199,9 @@ impl Mesh {
             vertex_buffer_layout,
             vertex_attributes,
             num_indices: indices.len() as u32,
+            morph_targets: None,
+            vertices: bytemuck::cast_slice(&vertices).to_vec(),
+            indices,
         };
 
         // 更新布局中的 attributes 引用
@@ -132,6 +210,36 @@ impl Mesh {
         instance
     }
 
+    /// replace the vertex data in place, laid out like the mesh's format. the buffer grows when it's too small.
+    /// morph targets aren't touched, keep the vertex count when the mesh has them
+    pub fn update_vertices(&mut self, vertices_contents: &[u8], renderer: &Renderer) {
+        let stride = self.vertex_buffer_layout.array_stride as usize;
+        assert!(
+            stride == 0 || vertices_contents.len().is_multiple_of(stride),
+            "vertex data is not a multiple of the vertex stride {}",
+            stride
+        );
+        write_or_grow(
+            renderer,
+            &mut self.vertex_buffer,
+            vertices_contents,
+            "Vertex Buffer",
+        );
+        self.vertices = vertices_contents.to_vec();
+    }
+
+    /// replace the indices in place and update num_indices, the buffer grows when it's too small
+    pub fn update_indices(&mut self, indices: Vec<u32>, renderer: &Renderer) {
+        write_or_grow(
+            renderer,
+            &mut self.index_buffer,
+            bytemuck::cast_slice(&indices),
+            "Index Buffer",
+        );
+        self.num_indices = indices.len() as u32;
+        self.indices = indices;
+    }
+
     fn create_vertex_attributes(format: VertexFormat) -> (Vec<wgpu::VertexAttribute>, usize) {
         let mut attributes = Vec::new();
         let mut offset = 0;
@@ -206,6 +314,59 @@ impl Mesh {
                 });
                 total_size = 32; // 3 * 4 + 3 * 4 + 2 * 4 bytes
             }
+            VertexFormat::PositionNormalTextureSkin => {
+                let (mut skin_attributes, size) =
+                    Self::create_vertex_attributes(VertexFormat::PositionNormalTexture);
+                offset = size as u64;
+
+                // 骨骼索引: uint4
+                skin_attributes.push(wgpu::VertexAttribute {
+                    offset,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Uint32x4,
+                });
+                offset += 16;
+
+                // 骨骼权重: float4
+                skin_attributes.push(wgpu::VertexAttribute {
+                    offset,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x4,
+                });
+                attributes = skin_attributes;
+                total_size = 64; // 32 + 4 * 4 + 4 * 4 bytes
+            }
+            VertexFormat::PositionColor => {
+                // 位置: float3 (x, y, z)
+                attributes.push(wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                });
+                offset += 12;
+
+                // 顶点颜色: float4 (r, g, b, a), location 9 comes after the instance matrix
+                attributes.push(wgpu::VertexAttribute {
+                    offset,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Float32x4,
+                });
+                total_size = 28; // 3 * 4 + 4 * 4 bytes
+            }
+            VertexFormat::PositionNormalColor => {
+                let (mut color_attributes, size) =
+                    Self::create_vertex_attributes(VertexFormat::PositionNormal);
+                offset = size as u64;
+
+                // 顶点颜色: float4 (r, g, b, a)
+                color_attributes.push(wgpu::VertexAttribute {
+                    offset,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Float32x4,
+                });
+                attributes = color_attributes;
+                total_size = 40; // 3 * 4 + 3 * 4 + 4 * 4 bytes
+            }
             VertexFormat::Custom => {
                 // 自定义格式，需要调用 set_custom_attributes 方法设置
                 total_size = 0;
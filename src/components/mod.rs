@@ -1,10 +1,14 @@
+pub mod animation;
 pub mod controller;
 pub mod instance;
 pub mod lights;
 pub mod material;
 pub mod materials;
 pub mod mesh;
+pub mod morph;
 pub mod orthographic_camera;
 pub mod perspective_camera;
+pub mod shadow;
+pub mod skin;
 pub mod transform;
 pub mod viewport;
@@ -0,0 +1,242 @@
+use wgpu::util::DeviceExt;
+
+use crate::{
+    components::{mesh::Mesh, skin::Skin},
+    renderer::Renderer,
+};
+
+/// same as MAX_MORPH_TARGETS in shaderlibs/morph.wgsl
+pub const MAX_MORPH_TARGETS: usize = 64;
+// webgl2 guarantees 2048 texels per texture side
+const MORPH_TEXTURE_WIDTH: usize = 2048;
+
+/// position and normal offsets of one blend shape, one per vertex of the base mesh
+#[derive(Debug, Clone, Default)]
+pub struct MorphTarget {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Option<Vec<[f32; 3]>>,
+}
+
+/// morph targets of a mesh on the gpu, texel (target * vertex_count + vertex) * 2 holds the position offset
+/// and the next texel the normal offset. the vertex stage reads them with textureLoad,
+/// so the number of targets isn't limited by vertex attribute slots
+pub struct MorphTargets {
+    pub count: u32,
+    pub vertex_count: u32,
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+impl MorphTargets {
+    /// panics if there are more than MAX_MORPH_TARGETS targets, or a target doesn't match vertex_count.
+    /// fails if the texture would be higher than the device allows
+    pub fn new(
+        renderer: &Renderer,
+        vertex_count: usize,
+        targets: &[MorphTarget],
+    ) -> anyhow::Result<MorphTargets> {
+        assert!(
+            targets.len() <= MAX_MORPH_TARGETS,
+            "mesh has {} morph targets, at most {} are supported",
+            targets.len(),
+            MAX_MORPH_TARGETS
+        );
+        let texel_count = targets.len() * vertex_count * 2;
+        let width = texel_count.clamp(1, MORPH_TEXTURE_WIDTH);
+        let height = texel_count.div_ceil(width).max(1);
+        let max_height = renderer.device.limits().max_texture_dimension_2d as usize;
+        if height > max_height {
+            return Err(anyhow::anyhow!(
+                "{} morph targets of {} vertices need a {}x{} texture, at most {} rows are supported",
+                targets.len(),
+                vertex_count,
+                width,
+                height,
+                max_height
+            ));
+        }
+        let mut texels: Vec<[f32; 4]> = Vec::with_capacity(texel_count);
+        for target in targets {
+            assert_eq!(
+                target.positions.len(),
+                vertex_count,
+                "morph target positions don't match the vertex count"
+            );
+            for (i, position) in target.positions.iter().enumerate() {
+                let normal = target
+                    .normals
+                    .as_ref()
+                    .and_then(|normals| normals.get(i))
+                    .copied()
+                    .unwrap_or([0.; 3]);
+                texels.push([position[0], position[1], position[2], 0.]);
+                texels.push([normal[0], normal[1], normal[2], 0.]);
+            }
+        }
+        texels.resize(width * height, [0.; 4]);
+
+        let size = wgpu::Extent3d {
+            width: width as u32,
+            height: height as u32,
+            depth_or_array_layers: 1,
+        };
+        let texture = renderer.device.create_texture_with_data(
+            &renderer.queue,
+            &wgpu::TextureDescriptor {
+                label: Some("Morph Target Texture"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba32Float,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            bytemuck::cast_slice(&texels),
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Ok(MorphTargets {
+            count: targets.len() as u32,
+            vertex_count: vertex_count as u32,
+            texture,
+            view,
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct MorphUniform {
+    weights: [[f32; 4]; MAX_MORPH_TARGETS / 4],
+    count: u32,
+    vertex_count: u32,
+    _padding: [u32; 2],
+}
+
+/// blend weights of a morphed mesh, edit `weights` and the MorphSystem uploads them every frame.
+/// it owns the entity's group(3) bind group, which also carries the joint matrices when the entity is skinned,
+/// the material must be built with morph targets (and skinning) enabled to match
+pub struct MorphWeights {
+    pub weights: Vec<f32>,
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    count: u32,
+    vertex_count: u32,
+}
+
+impl MorphWeights {
+    /// panics if the mesh has no morph targets
+    pub fn new(
+        renderer: &Renderer,
+        mesh: &Mesh,
+        skin: Option<&Skin>,
+        weights: Vec<f32>,
+    ) -> MorphWeights {
+        let targets = mesh
+            .morph_targets
+            .as_ref()
+            .expect("MorphWeights needs a mesh with morph targets");
+        let device = &renderer.device;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Morph Weights Buffer"),
+            size: std::mem::size_of::<MorphUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&targets.view),
+            },
+        ];
+        if let Some(skin) = skin {
+            entries.insert(
+                0,
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: skin.buffer.as_entire_binding(),
+                },
+            );
+        }
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Morph Bind Group"),
+            layout: &Self::create_bind_group_layout(device, skin.is_some()),
+            entries: &entries,
+        });
+        let morph_weights = MorphWeights {
+            weights,
+            buffer,
+            bind_group,
+            count: targets.count,
+            vertex_count: targets.vertex_count,
+        };
+        morph_weights.update_buffer(renderer);
+        morph_weights
+    }
+
+    /// group(3) layout of a morphed mesh, binding 0 is the Skin's joint matrices when skinned
+    pub fn create_bind_group_layout(device: &wgpu::Device, skinned: bool) -> wgpu::BindGroupLayout {
+        let mut entries = vec![
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+        ];
+        if skinned {
+            entries.insert(
+                0,
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            );
+        }
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Morph Bind Group Layout"),
+            entries: &entries,
+        })
+    }
+
+    /// weights past the mesh's target count are ignored
+    pub fn update_buffer(&self, renderer: &Renderer) {
+        let mut uniform = MorphUniform {
+            weights: [[0.; 4]; MAX_MORPH_TARGETS / 4],
+            count: self.count,
+            vertex_count: self.vertex_count,
+            _padding: [0; 2],
+        };
+        for (i, weight) in self.weights.iter().take(self.count as usize).enumerate() {
+            uniform.weights[i / 4][i % 4] = *weight;
+        }
+        renderer
+            .queue
+            .write_buffer(&self.buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+}
@@ -74,19 +74,9 @@ impl OrthographicCamera {
 
 impl CameraTrait for OrthographicCamera {
     fn update_bind_group(&mut self, renderer: &Renderer) {
-        let zoom = self.config.zoom;
-        let width = self.config.width * zoom;
-        let height = width / self.config.aspect;
         let uniform = CameraUniform::new(
-            Mat4::look_at_rh(self.config.position, self.config.target, self.config.up),
-            Mat4::orthographic_rh(
-                -width / 2.0,
-                width / 2.0,
-                -height / 2.0,
-                height / 2.0,
-                self.config.near,
-                self.config.far,
-            ),
+            self.get_view_matrix(),
+            self.get_projection_matrix(),
             self.config.position.clone(),
         );
         renderer
@@ -114,4 +104,22 @@ impl CameraTrait for OrthographicCamera {
     fn get_type(&self) -> String {
         "orthographic".to_string()
     }
+
+    fn get_view_matrix(&self) -> Mat4 {
+        Mat4::look_at_rh(self.config.position, self.config.target, self.config.up)
+    }
+
+    fn get_projection_matrix(&self) -> Mat4 {
+        let zoom = self.config.zoom;
+        let width = self.config.width * zoom;
+        let height = width / self.config.aspect;
+        Mat4::orthographic_rh(
+            -width / 2.0,
+            width / 2.0,
+            -height / 2.0,
+            height / 2.0,
+            self.config.near,
+            self.config.far,
+        )
+    }
 }
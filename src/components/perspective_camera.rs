@@ -87,18 +87,15 @@ pub trait CameraTrait {
     fn as_any(&mut self) -> &mut dyn Any;
     fn get_type(&self) -> String;
     fn set_aspect(&mut self, aspect: f32, renderer: &Renderer);
+    fn get_view_matrix(&self) -> Mat4;
+    fn get_projection_matrix(&self) -> Mat4;
 }
 
 impl CameraTrait for PerspectiveCamera {
     fn update_bind_group(&mut self, renderer: &Renderer) {
         let uniform = CameraUniform::new(
-            Mat4::look_at_rh(self.config.position, self.config.target, self.config.up),
-            Mat4::perspective_rh(
-                self.config.fov.to_radians(),
-                self.config.aspect,
-                self.config.near,
-                self.config.far,
-            ),
+            self.get_view_matrix(),
+            self.get_projection_matrix(),
             self.config.position.clone(),
         );
         renderer
@@ -125,4 +122,17 @@ impl CameraTrait for PerspectiveCamera {
     fn get_type(&self) -> String {
         "perspective".to_string()
     }
+
+    fn get_view_matrix(&self) -> Mat4 {
+        Mat4::look_at_rh(self.config.position, self.config.target, self.config.up)
+    }
+
+    fn get_projection_matrix(&self) -> Mat4 {
+        Mat4::perspective_rh(
+            self.config.fov.to_radians(),
+            self.config.aspect,
+            self.config.near,
+            self.config.far,
+        )
+    }
 }
@@ -0,0 +1,305 @@
+use glam::{Mat4, Vec3};
+
+/// shadow maps shared by all shadow casting lights, a directional light takes one per cascade
+pub const MAX_SHADOW_MAPS: usize = 4;
+
+/// how the orthographic shadow camera of a directional light covers the scene
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShadowFit {
+    /// a fixed box around center, half_size x and y across the light and z along it
+    Fixed { center: Vec3, half_size: Vec3 },
+    /// follow the view camera up to max_distance, split into cascades (1 to MAX_SHADOW_MAPS).
+    /// split_lambda blends uniform (0) and logarithmic (1) splits,
+    /// casters up to caster_distance toward the light outside of the view still cast
+    Camera {
+        max_distance: f32,
+        cascades: u32,
+        split_lambda: f32,
+        caster_distance: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowConfig {
+    /// texels per side, all maps use the biggest size of the scene
+    pub map_size: u32,
+    /// subtracted from the receiver's depth in the shadow map, against shadow acne
+    pub bias: f32,
+    /// receivers are pushed along their normal by this many texels
+    pub normal_bias: f32,
+    /// percentage closer filtering over (2 * pcf_radius + 1)^2 texels, 0 for hard shadows
+    pub pcf_radius: u32,
+    /// directional lights only
+    pub fit: ShadowFit,
+    /// spot lights only, the near and far planes of the shadow camera. the light's range is used when it is set
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for ShadowConfig {
+    fn default() -> Self {
+        Self {
+            map_size: 2048,
+            bias: 0.0005,
+            normal_bias: 1.0,
+            pcf_radius: 1,
+            fit: ShadowFit::Camera {
+                max_distance: 50.0,
+                cascades: 1,
+                split_lambda: 0.5,
+                caster_distance: 50.0,
+            },
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+}
+
+/// the light space of one shadow map
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowView {
+    pub view_projection: Mat4,
+    /// the view depth a cascade covers up to, f32::MAX for single maps
+    pub split_depth: f32,
+    /// world size of a texel, per unit of distance to the light for perspective views
+    pub texel_size: f32,
+    pub perspective: bool,
+}
+
+/// the far depth of every cascade between near and far
+pub fn cascade_splits(near: f32, far: f32, cascades: u32, lambda: f32) -> Vec<f32> {
+    (1..=cascades)
+        .map(|i| {
+            let t = i as f32 / cascades as f32;
+            let uniform = near + (far - near) * t;
+            let logarithmic = near * (far / near).powf(t);
+            uniform + (logarithmic - uniform) * lambda
+        })
+        .collect()
+}
+
+/// one orthographic view per cascade, direction is where the light travels to
+pub fn directional_shadow_views(
+    direction: Vec3,
+    config: &ShadowConfig,
+    camera_view: Mat4,
+    camera_projection: Mat4,
+) -> Vec<ShadowView> {
+    let direction = direction.normalize();
+    match config.fit {
+        ShadowFit::Fixed { center, half_size } => {
+            let view = Mat4::look_at_rh(
+                center - direction * half_size.z,
+                center,
+                up_vector(direction),
+            );
+            let projection = Mat4::orthographic_rh(
+                -half_size.x,
+                half_size.x,
+                -half_size.y,
+                half_size.y,
+                0.,
+                half_size.z * 2.,
+            );
+            vec![ShadowView {
+                view_projection: projection * view,
+                split_depth: f32::MAX,
+                texel_size: half_size.x.max(half_size.y) * 2. / config.map_size as f32,
+                perspective: false,
+            }]
+        }
+        ShadowFit::Camera {
+            max_distance,
+            cascades,
+            split_lambda,
+            caster_distance,
+        } => {
+            let frustum = Frustum::new(camera_view, camera_projection);
+            let near = frustum.near_depth.max(1e-4);
+            let far = frustum.far_depth.min(max_distance).max(near);
+            let cascades = cascades.clamp(1, MAX_SHADOW_MAPS as u32);
+            let mut start = near;
+            cascade_splits(near, far, cascades, split_lambda)
+                .into_iter()
+                .map(|end| {
+                    let corners = frustum.slice(start, end);
+                    start = end;
+                    let mut view =
+                        fit_sphere(&corners, direction, caster_distance, config.map_size);
+                    view.split_depth = if cascades == 1 { f32::MAX } else { end };
+                    view
+                })
+                .collect()
+        }
+    }
+}
+
+/// a perspective view covering the outer cone
+pub fn spot_shadow_view(
+    position: Vec3,
+    direction: Vec3,
+    outer_cone_cos: f32,
+    range: f32,
+    config: &ShadowConfig,
+) -> ShadowView {
+    let direction = direction.normalize();
+    // a little wider than the cone so the pcf kernel stays inside the map
+    let fov = (outer_cone_cos.clamp(-1., 1.).acos() * 2. * 1.05).clamp(0.01, 3.);
+    let far = if range > 0. { range } else { config.far };
+    let view = Mat4::look_at_rh(position, position + direction, up_vector(direction));
+    let projection = Mat4::perspective_rh(fov, 1., config.near, far.max(config.near * 2.));
+    ShadowView {
+        view_projection: projection * view,
+        split_depth: f32::MAX,
+        texel_size: (fov / 2.).tan() * 2. / config.map_size as f32,
+        perspective: true,
+    }
+}
+
+fn up_vector(direction: Vec3) -> Vec3 {
+    if direction.y.abs() > 0.99 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    }
+}
+
+/// the bounding sphere of the corners keeps the map size constant while the camera turns,
+/// and the origin snapped to whole texels keeps the edges from swimming while it moves
+fn fit_sphere(
+    corners: &[Vec3; 8],
+    direction: Vec3,
+    caster_distance: f32,
+    map_size: u32,
+) -> ShadowView {
+    let center = corners.iter().copied().sum::<Vec3>() / 8.;
+    let radius = corners
+        .iter()
+        .map(|corner| corner.distance(center))
+        .fold(0., f32::max);
+    let radius = (radius * 16.).ceil() / 16.;
+    let view = Mat4::look_at_rh(
+        center - direction * (radius + caster_distance),
+        center,
+        up_vector(direction),
+    );
+    let mut projection = Mat4::orthographic_rh(
+        -radius,
+        radius,
+        -radius,
+        radius,
+        0.,
+        radius * 2. + caster_distance,
+    );
+    let half_size = map_size as f32 / 2.;
+    let origin = (projection * view).project_point3(Vec3::ZERO) * half_size;
+    let offset = (origin.round() - origin) / half_size;
+    projection.w_axis.x += offset.x;
+    projection.w_axis.y += offset.y;
+    ShadowView {
+        view_projection: projection * view,
+        split_depth: f32::MAX,
+        texel_size: radius * 2. / map_size as f32,
+        perspective: false,
+    }
+}
+
+/// the corner rays of a camera frustum in world space
+struct Frustum {
+    near_corners: [Vec3; 4],
+    far_corners: [Vec3; 4],
+    near_depth: f32,
+    far_depth: f32,
+}
+
+impl Frustum {
+    fn new(view: Mat4, projection: Mat4) -> Frustum {
+        let inverse = (projection * view).inverse();
+        let corner = |x: f32, y: f32, z: f32| inverse.project_point3(Vec3::new(x, y, z));
+        let xy = [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)];
+        let near_corners = xy.map(|(x, y)| corner(x, y, 0.));
+        let far_corners = xy.map(|(x, y)| corner(x, y, 1.));
+        let depth = |point: Vec3| -(view * point.extend(1.)).z;
+        Frustum {
+            near_corners,
+            far_corners,
+            near_depth: depth(near_corners[0]),
+            far_depth: depth(far_corners[0]),
+        }
+    }
+
+    /// the 8 corners between two view depths, view depth is linear along the corner rays
+    fn slice(&self, start: f32, end: f32) -> [Vec3; 8] {
+        let range = self.far_depth - self.near_depth;
+        let at = |depth: f32, i: usize| {
+            let t = (depth - self.near_depth) / range;
+            self.near_corners[i] + (self.far_corners[i] - self.near_corners[i]) * t
+        };
+        [
+            at(start, 0),
+            at(start, 1),
+            at(start, 2),
+            at(start, 3),
+            at(end, 0),
+            at(end, 1),
+            at(end, 2),
+            at(end, 3),
+        ]
+    }
+}
+
+/// `struct ShadowMap` of the Shadows shader chunk
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShadowMapData {
+    pub view_projection: [[f32; 4]; 4],
+    /// index of the light in the lights array
+    pub light_index: u32,
+    pub split_depth: f32,
+    pub bias: f32,
+    /// world distance receivers are pushed along their normal, per unit of distance to the light for perspective maps
+    pub normal_offset: f32,
+    pub pcf_radius: u32,
+    pub perspective: u32,
+    pub _padding: [u32; 2],
+}
+
+impl ShadowMapData {
+    pub fn new(light_index: u32, view: &ShadowView, config: &ShadowConfig) -> Self {
+        Self {
+            view_projection: view.view_projection.to_cols_array_2d(),
+            light_index,
+            split_depth: view.split_depth,
+            bias: config.bias,
+            normal_offset: config.normal_bias * view.texel_size,
+            pcf_radius: config.pcf_radius,
+            perspective: view.perspective as u32,
+            _padding: [0; 2],
+        }
+    }
+}
+
+/// `struct Shadows` of the Shadows shader chunk, layer i of the shadow texture belongs to maps[i]
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShadowsUniform {
+    pub count: u32,
+    /// 1 / map size
+    pub texel_size: f32,
+    pub _padding: [u32; 2],
+    pub maps: [ShadowMapData; MAX_SHADOW_MAPS],
+}
+
+impl ShadowsUniform {
+    pub fn new(maps: &[ShadowMapData], map_size: u32) -> Self {
+        let count = maps.len().min(MAX_SHADOW_MAPS);
+        let mut uniform = ShadowsUniform {
+            count: count as u32,
+            texel_size: 1. / map_size as f32,
+            _padding: [0; 2],
+            maps: [ShadowMapData::default(); MAX_SHADOW_MAPS],
+        };
+        uniform.maps[..count].copy_from_slice(&maps[..count]);
+        uniform
+    }
+}
@@ -0,0 +1,108 @@
+use glam::Mat4;
+use wgpu::util::DeviceExt;
+
+use crate::{components::transform::Transform, entity::EntityId, renderer::Renderer, scene::Scene};
+
+// joint matrices use group 3, after material(0), env(1) and transform(2)
+pub const SKIN_BIND_GROUP_INDEX: u32 = 3;
+/// same as MAX_JOINTS in shaderlibs/skin.wgsl
+pub const MAX_JOINTS: usize = 128;
+
+/// joints of a skinned mesh, the entity also needs a mesh with joints and weights
+/// and a material built with skinning enabled
+pub struct Skin {
+    /// joint entities, indexed by the mesh's joint attribute
+    pub joints: Vec<EntityId>,
+    pub inverse_bind_matrices: Vec<Mat4>,
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Skin {
+    /// panics if there are more than MAX_JOINTS joints
+    pub fn new(
+        renderer: &Renderer,
+        joints: Vec<EntityId>,
+        inverse_bind_matrices: Vec<Mat4>,
+    ) -> Skin {
+        assert!(
+            joints.len() <= MAX_JOINTS,
+            "skin has {} joints, at most {} are supported",
+            joints.len(),
+            MAX_JOINTS
+        );
+        let device = &renderer.device;
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Skin Buffer"),
+            contents: bytemuck::cast_slice(&[Mat4::IDENTITY.to_cols_array(); MAX_JOINTS]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Skin Bind Group"),
+            layout: &Self::create_bind_group_layout(device),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        Skin {
+            joints,
+            inverse_bind_matrices,
+            buffer,
+            bind_group,
+        }
+    }
+
+    pub fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Skin Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        })
+    }
+
+    /// joint matrices in the mesh's space, the mesh's model matrix is applied after skinning.
+    /// joints without transform stay at their bind pose
+    pub fn joint_matrices(&self, scene: &Scene, mesh_global_matrix: Mat4) -> Vec<Mat4> {
+        let inverse_mesh = mesh_global_matrix.inverse();
+        self.joints
+            .iter()
+            .enumerate()
+            .map(|(i, joint)| {
+                let inverse_bind = self
+                    .inverse_bind_matrices
+                    .get(i)
+                    .copied()
+                    .unwrap_or(Mat4::IDENTITY);
+                let joint_global = scene
+                    .get_entity(*joint)
+                    .and_then(|entity| entity.get_component_id::<Transform>())
+                    .and_then(|id| scene.get_component::<Transform>(id).ok())
+                    .map(|transform| transform.global_matrix);
+                match joint_global {
+                    Some(joint_global) => inverse_mesh * joint_global * inverse_bind,
+                    None => Mat4::IDENTITY,
+                }
+            })
+            .collect()
+    }
+
+    pub fn update_buffer(&self, renderer: &Renderer, joint_matrices: &[Mat4]) {
+        let data: Vec<[f32; 16]> = joint_matrices
+            .iter()
+            .take(MAX_JOINTS)
+            .map(|matrix| matrix.to_cols_array())
+            .collect();
+        renderer
+            .queue
+            .write_buffer(&self.buffer, 0, bytemuck::cast_slice(&data));
+    }
+}
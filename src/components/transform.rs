@@ -3,6 +3,9 @@ use wgpu::util::DeviceExt;
 
 use crate::renderer::Renderer;
 
+// model matrix use group 2, after material(0) and env(1)
+pub const TRANSFORM_BIND_GROUP_INDEX: u32 = 2;
+
 pub struct Transform {
     pub position: glam::Vec3,
     pub rotation: glam::Quat,
@@ -10,21 +13,57 @@ pub struct Transform {
     pub matrix: glam::Mat4,
     pub global_matrix: glam::Mat4,
     pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TransformUniform {
+    pub model_matrix: [[f32; 4]; 4],
+    // inverse transpose of model matrix, for normals under non-uniform scale
+    pub normal_matrix: [[f32; 4]; 4],
+}
+
+impl TransformUniform {
+    pub fn new(global_matrix: Mat4) -> Self {
+        Self {
+            model_matrix: global_matrix.to_cols_array_2d(),
+            normal_matrix: global_matrix.inverse().transpose().to_cols_array_2d(),
+        }
+    }
 }
 
 impl Transform {
     pub fn new(renderer: &Renderer, position: Vec3, rotation: Quat, scale: Vec3) -> Transform {
-        let buffer = Self::make_buffer(Mat4::default(), renderer);
+        Self::new_with_device(&renderer.device, position, rotation, scale)
+    }
+
+    /// for transforms the renderer owns, before it exists
+    pub fn new_with_device(
+        device: &wgpu::Device,
+        position: Vec3,
+        rotation: Quat,
+        scale: Vec3,
+    ) -> Transform {
+        let matrix = Mat4::from_scale_rotation_translation(scale, rotation, position);
+        let buffer = Self::make_buffer(matrix, device);
+        let bind_group = Self::make_bind_group(&buffer, device);
         Transform {
             position,
             rotation,
             scale,
             buffer,
-            matrix: Mat4::from_scale_rotation_translation(scale, rotation, position),
-            global_matrix: Mat4::default(),
+            bind_group,
+            matrix,
+            global_matrix: matrix,
         }
     }
 
+    pub fn from_matrix(renderer: &Renderer, matrix: Mat4) -> Transform {
+        let (scale, rotation, position) = matrix.to_scale_rotation_translation();
+        Transform::new(renderer, position, rotation, scale)
+    }
+
     pub fn set_position(&mut self, position: Vec3) {
         self.position = position;
         self.update_matrix();
@@ -40,25 +79,60 @@ impl Transform {
         self.update_matrix();
     }
 
-    pub fn update_matrix(&self) -> glam::Mat4 {
-        glam::Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.position)
+    pub fn update_matrix(&mut self) -> glam::Mat4 {
+        self.matrix =
+            glam::Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.position);
+        self.matrix
     }
 
-    pub fn update_global_matrix(&mut self, parent_global_matrix: glam::Mat4) {
+    pub fn update_global_matrix(&mut self, parent_global_matrix: glam::Mat4) -> glam::Mat4 {
         self.global_matrix = parent_global_matrix * self.matrix;
+        self.global_matrix
     }
 
-    fn make_buffer(mat: Mat4, renderer: &Renderer) -> wgpu::Buffer {
-        let device = &renderer.device;
-        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Camera Buffer"),
-            contents: bytemuck::cast_slice(&mat.to_cols_array()),
+    fn make_buffer(mat: Mat4, device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Transform Buffer"),
+            contents: bytemuck::cast_slice(&[TransformUniform::new(mat)]),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-        buffer
+        })
+    }
+
+    fn make_bind_group(buffer: &wgpu::Buffer, device: &wgpu::Device) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Transform Bind Group"),
+            layout: &Self::create_bind_group_layout(device),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        })
+    }
+
+    pub fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Transform Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        })
     }
 
-    pub fn update_bind_group(&mut self, renderer: &Renderer) {}
+    // upload global matrix to gpu
+    pub fn update_bind_group(&mut self, renderer: &Renderer) {
+        renderer.queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::cast_slice(&[TransformUniform::new(self.global_matrix)]),
+        );
+    }
 
     pub fn default(renderer: &Renderer) -> Transform {
         Transform::new(
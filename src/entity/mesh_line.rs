@@ -3,8 +3,10 @@ use glam::Vec3;
 use crate::{
     components::{
         material::{Material, MaterialConfig, MaterialTrait},
+        materials::render_state::RenderState,
         mesh::Mesh,
     },
+    entity::EntityId,
     renderer::Renderer,
     scene::Scene,
 };
@@ -14,7 +16,7 @@ pub fn make_mesh(
     width: f32,
     renderer: &Renderer,
     scene: &mut Scene,
-    entity_id: usize,
+    entity_id: EntityId,
 ) {
     let (vertices, indices) = make_width_line_vertexes(path, width);
     println!("vertices: {:?}", vertices);
@@ -50,7 +52,7 @@ pub fn make_width_line_vertexes(path: &Vec<Vec3>, width: f32) -> (Vec<f32>, Vec<
     (vertexes, indices)
 }
 
-pub fn make_material(renderer: &Renderer, scene: &mut Scene, color: Vec<f32>, entity_id: usize) {
+pub fn make_material(renderer: &Renderer, scene: &mut Scene, color: Vec<f32>, entity_id: EntityId) {
     let shader = include_str!("../components/materials/shaders/default.wgsl");
     let mut shader_parser = crate::components::materials::shader::ShaderParser::new();
     let shader = shader_parser.parse_shader(shader);
@@ -59,6 +61,7 @@ pub fn make_material(renderer: &Renderer, scene: &mut Scene, color: Vec<f32>, en
             shader,
             topology: wgpu::PrimitiveTopology::TriangleStrip,
             uniforms: color,
+            render_state: RenderState::transparent(),
         },
         renderer,
     );
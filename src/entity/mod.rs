@@ -1,54 +1,75 @@
 pub mod mesh_line;
 pub mod sprite_entity;
 
-use std::collections::HashMap;
+use std::{any::Any, collections::HashMap};
 
-static mut ID: usize = 0;
+use crate::component_store::ComponentId;
+
+/// stable handle to an entity in a scene, a removed entity's handle never matches the slot's new owner
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EntityId {
+    pub index: u32,
+    pub generation: u32,
+}
+
+impl std::fmt::Display for EntityId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
+
+#[derive(Default)]
 pub struct Entity {
     pub name: String,
-    pub id: usize,
-    pub is_child: bool,
-    pub children: Vec<usize>,
+    // set by scene when the entity is added as a child
+    pub parent: Option<EntityId>,
+    pub children: Vec<EntityId>,
     // map to scene's components
-    pub components_map: HashMap<String, usize>,
+    pub components_map: HashMap<String, ComponentId>,
+    // drawn into the shadow maps of shadow casting lights
+    pub cast_shadow: bool,
+    // darkened by the shadow maps when drawn
+    pub receive_shadow: bool,
 }
 
 impl Entity {
     pub fn new() -> Entity {
-        let instance = Entity {
-            children: Vec::new(),
-            name: String::from(""),
-            id: unsafe {
-                ID += 1;
-                ID
-            },
-            is_child: false,
-            components_map: HashMap::new(),
-        };
-        instance
-    }
-
-    pub fn add_child(&mut self, child: usize) -> usize {
+        Entity::default()
+    }
+
+    pub fn is_child(&self) -> bool {
+        self.parent.is_some()
+    }
+
+    pub fn add_child(&mut self, child: EntityId) -> usize {
         self.children.push(child);
         self.children.len() - 1
     }
 
-    pub fn remove_child(&mut self, index: usize) {
-        self.children.remove(index);
+    pub fn remove_child(&mut self, child: EntityId) -> bool {
+        let len = self.children.len();
+        self.children.retain(|id| *id != child);
+        self.children.len() != len
     }
 
-    pub fn get_component_index(&self, component: &str) -> usize {
-        *self.components_map.get(component).unwrap()
+    pub fn get_component_index(&self, component: &str) -> Option<ComponentId> {
+        self.components_map.get(component).copied()
     }
 
-    // make component manually memory management
-    pub fn set_component_index(&mut self, name: &str, component_index: usize) {
+    /// find a component by its type, if several share the type any one of them is returned
+    pub fn get_component_id<T: Any>(&self) -> Option<ComponentId> {
         self.components_map
-            .insert(name.to_string(), component_index);
+            .values()
+            .find(|component_id| component_id.is::<T>())
+            .copied()
+    }
+
+    pub fn set_component_index(&mut self, name: &str, component_id: ComponentId) {
+        self.components_map.insert(name.to_string(), component_id);
     }
 
-    pub fn remove_component_index(&mut self, name: &str) {
-        self.components_map.remove(name).unwrap();
+    pub fn remove_component_index(&mut self, name: &str) -> Option<ComponentId> {
+        self.components_map.remove(name)
     }
 
     pub fn has_component(&self, component: &str) -> bool {
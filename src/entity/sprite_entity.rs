@@ -6,11 +6,12 @@ use crate::{
         materials::sprite::{SpriteMaterial, SpriteMaterialConfig},
         mesh::Mesh,
     },
+    entity::EntityId,
     renderer::Renderer,
     scene::Scene,
 };
 
-pub fn make_mesh(position: Vec3, renderer: &Renderer, scene: &mut Scene, entity_id: usize) {
+pub fn make_mesh(position: Vec3, renderer: &Renderer, scene: &mut Scene, entity_id: EntityId) {
     let mesh = Mesh::new_position_only(
         vec![
             position.x, position.y, position.z, position.x, position.y, position.z, position.x,
@@ -26,7 +27,7 @@ pub fn make_material(
     renderer: &Renderer,
     scene: &mut Scene,
     material: SpriteMaterialConfig,
-    entity_id: usize,
+    entity_id: EntityId,
 ) {
     let material = SpriteMaterial::new(material, renderer);
     scene.set_entity_component::<Box<dyn MaterialTrait>>(entity_id, Box::new(material), "material");
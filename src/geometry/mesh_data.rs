@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use glam::{Mat3, Mat4, Vec2, Vec3, Vec4, Vec4Swizzles};
+
+use super::normals::{flat_normals, smooth_normals};
+
+/// triangle list geometry on the cpu, every stream that is Some has one value per position.
+/// build it, edit it, then upload it with Mesh::from_data
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshData {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Option<Vec<[f32; 3]>>,
+    pub uvs: Option<Vec<[f32; 2]>>,
+    /// rgba in 0..1
+    pub colors: Option<Vec<[f32; 4]>>,
+    /// xyz along +u, w is the handedness of the bitangent (cross(normal, tangent) * w)
+    pub tangents: Option<Vec<[f32; 4]>>,
+    pub indices: Vec<u32>,
+}
+
+/// axis aligned bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Bounds {
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// radius of the sphere around center that holds the box
+    pub fn radius(&self) -> f32 {
+        self.size().length() * 0.5
+    }
+}
+
+impl MeshData {
+    pub fn new(positions: Vec<[f32; 3]>, indices: Vec<u32>) -> MeshData {
+        MeshData {
+            positions,
+            indices,
+            ..Default::default()
+        }
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// area weighted normals shared by the triangles around each vertex
+    pub fn compute_smooth_normals(&mut self) {
+        self.normals = Some(smooth_normals(&self.positions, &self.indices));
+    }
+
+    /// every triangle gets its own vertices with the face normal, the other streams are copied
+    pub fn compute_flat_normals(&mut self) {
+        *self = self.gather(&self.indices);
+        self.indices = (0..self.positions.len() as u32).collect();
+        self.normals = Some(flat_normals(&self.positions));
+    }
+
+    /// per vertex tangents from the uvs, like mikktspace the triangle tangents are weighted by the corner angle
+    /// and orthogonalized against the normal. smooth normals are computed when there are none
+    pub fn compute_tangents(&mut self) -> anyhow::Result<()> {
+        if self.uvs.is_none() {
+            return Err(anyhow!("tangents need uvs"));
+        }
+        if self.normals.is_none() {
+            self.compute_smooth_normals();
+        }
+        let uvs = self.uvs.as_ref().unwrap();
+        let normals = self.normals.as_ref().unwrap();
+
+        let mut tangents = vec![Vec3::ZERO; self.positions.len()];
+        let mut bitangents = vec![Vec3::ZERO; self.positions.len()];
+        for triangle in self.indices.chunks_exact(3) {
+            let corners = [triangle[0], triangle[1], triangle[2]].map(|i| i as usize);
+            let [p0, p1, p2] = corners.map(|i| Vec3::from(self.positions[i]));
+            let [uv0, uv1, uv2] = corners.map(|i| Vec2::from(uvs[i]));
+            let (edge1, edge2) = (p1 - p0, p2 - p0);
+            let (duv1, duv2) = (uv1 - uv0, uv2 - uv0);
+            let r = duv1.x * duv2.y - duv2.x * duv1.y;
+            if r.abs() < f32::EPSILON {
+                continue;
+            }
+            let tangent = ((edge1 * duv2.y - edge2 * duv1.y) / r).normalize_or_zero();
+            let bitangent = ((edge2 * duv1.x - edge1 * duv2.x) / r).normalize_or_zero();
+            for (corner, i) in corners.iter().enumerate() {
+                let [a, b, c] = [corner, (corner + 1) % 3, (corner + 2) % 3]
+                    .map(|k| Vec3::from(self.positions[corners[k]]));
+                let angle = (b - a).angle_between(c - a);
+                if angle.is_finite() {
+                    tangents[*i] += tangent * angle;
+                    bitangents[*i] += bitangent * angle;
+                }
+            }
+        }
+
+        self.tangents = Some(
+            tangents
+                .into_iter()
+                .zip(bitangents)
+                .zip(normals)
+                .map(|((tangent, bitangent), normal)| {
+                    let normal = Vec3::from(*normal);
+                    let tangent = (tangent - normal * normal.dot(tangent))
+                        .try_normalize()
+                        .unwrap_or_else(|| normal.any_orthonormal_vector());
+                    let handedness = if normal.cross(tangent).dot(bitangent) < 0. {
+                        -1.
+                    } else {
+                        1.
+                    };
+                    tangent.extend(handedness).to_array()
+                })
+                .collect(),
+        );
+        Ok(())
+    }
+
+    /// append the vertices and triangles of other, a stream missing on one side is filled with
+    /// smooth normals, zero uvs, white colors or +x tangents so it isn't lost
+    pub fn merge(&mut self, other: &MeshData) {
+        let offset = self.positions.len() as u32;
+        let (self_count, other_count) = (self.positions.len(), other.positions.len());
+
+        let mut other_normals = other.normals.clone();
+        if self.normals.is_some() && other_normals.is_none() {
+            other_normals = Some(smooth_normals(&other.positions, &other.indices));
+        }
+        if other_normals.is_some() && self.normals.is_none() {
+            self.compute_smooth_normals();
+        }
+        merge_stream(
+            &mut self.normals,
+            other_normals.as_ref(),
+            self_count,
+            other_count,
+            [0.; 3],
+        );
+        merge_stream(
+            &mut self.uvs,
+            other.uvs.as_ref(),
+            self_count,
+            other_count,
+            [0.; 2],
+        );
+        merge_stream(
+            &mut self.colors,
+            other.colors.as_ref(),
+            self_count,
+            other_count,
+            [1.; 4],
+        );
+        merge_stream(
+            &mut self.tangents,
+            other.tangents.as_ref(),
+            self_count,
+            other_count,
+            [1., 0., 0., 1.],
+        );
+        self.positions.extend_from_slice(&other.positions);
+        self.indices
+            .extend(other.indices.iter().map(|index| index + offset));
+    }
+
+    /// transform positions and tangents by matrix, normals by its inverse transpose.
+    /// a mirroring matrix also flips the triangle winding so the front faces stay outside
+    pub fn transform(&mut self, matrix: Mat4) {
+        for position in self.positions.iter_mut() {
+            *position = matrix.transform_point3(Vec3::from(*position)).to_array();
+        }
+        let normal_matrix = Mat3::from_mat4(matrix).inverse().transpose();
+        if let Some(normals) = self.normals.as_mut() {
+            for normal in normals.iter_mut() {
+                *normal = (normal_matrix * Vec3::from(*normal))
+                    .normalize_or_zero()
+                    .to_array();
+            }
+        }
+        let mirrored = matrix.determinant() < 0.;
+        if let Some(tangents) = self.tangents.as_mut() {
+            for tangent in tangents.iter_mut() {
+                let xyz = matrix.transform_vector3(Vec4::from(*tangent).xyz());
+                let w = if mirrored { -tangent[3] } else { tangent[3] };
+                *tangent = xyz.normalize_or_zero().extend(w).to_array();
+            }
+        }
+        if mirrored {
+            for triangle in self.indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+    }
+
+    /// merge vertices whose streams are all equal after snapping to a grid of epsilon (0 for exact matches).
+    /// the first vertex is kept, triangles that collapse are removed
+    pub fn weld(&mut self, epsilon: f32) {
+        let quantize = |value: f32| {
+            if epsilon > 0. {
+                (value / epsilon).round() as i64
+            } else {
+                // -0 and 0 are the same vertex
+                (value + 0.).to_bits() as i64
+            }
+        };
+        let mut keys: HashMap<Vec<i64>, u32> = HashMap::new();
+        let mut sources = vec![];
+        let remap: Vec<u32> = (0..self.positions.len())
+            .map(|i| {
+                let mut key: Vec<i64> = self.positions[i].map(quantize).to_vec();
+                if let Some(normals) = &self.normals {
+                    key.extend(normals[i].map(quantize));
+                }
+                if let Some(uvs) = &self.uvs {
+                    key.extend(uvs[i].map(quantize));
+                }
+                if let Some(colors) = &self.colors {
+                    key.extend(colors[i].map(quantize));
+                }
+                if let Some(tangents) = &self.tangents {
+                    key.extend(tangents[i].map(quantize));
+                }
+                *keys.entry(key).or_insert_with(|| {
+                    sources.push(i as u32);
+                    sources.len() as u32 - 1
+                })
+            })
+            .collect();
+
+        let indices = self
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]].map(|i| remap[i as usize]))
+            .filter(|[a, b, c]| a != b && b != c && a != c)
+            .flatten()
+            .collect();
+        *self = self.gather(&sources);
+        self.indices = indices;
+    }
+
+    /// None for an empty mesh
+    pub fn bounds(&self) -> Option<Bounds> {
+        let first = Vec3::from(*self.positions.first()?);
+        Some(self.positions.iter().fold(
+            Bounds {
+                min: first,
+                max: first,
+            },
+            |bounds, position| Bounds {
+                min: bounds.min.min(Vec3::from(*position)),
+                max: bounds.max.max(Vec3::from(*position)),
+            },
+        ))
+    }
+
+    /// a copy with one vertex per source index, indices are left empty
+    fn gather(&self, sources: &[u32]) -> MeshData {
+        fn pick<T: Copy>(stream: &[T], sources: &[u32]) -> Vec<T> {
+            sources.iter().map(|i| stream[*i as usize]).collect()
+        }
+        MeshData {
+            positions: pick(&self.positions, sources),
+            normals: self.normals.as_deref().map(|s| pick(s, sources)),
+            uvs: self.uvs.as_deref().map(|s| pick(s, sources)),
+            colors: self.colors.as_deref().map(|s| pick(s, sources)),
+            tangents: self.tangents.as_deref().map(|s| pick(s, sources)),
+            indices: vec![],
+        }
+    }
+}
+
+fn merge_stream<T: Copy>(
+    stream: &mut Option<Vec<T>>,
+    other: Option<&Vec<T>>,
+    count: usize,
+    other_count: usize,
+    default: T,
+) {
+    match (stream.as_mut(), other) {
+        (Some(stream), Some(other)) => stream.extend_from_slice(other),
+        (Some(stream), None) => stream.extend(std::iter::repeat_n(default, other_count)),
+        (None, Some(other)) => {
+            let mut merged = vec![default; count];
+            merged.extend_from_slice(other);
+            *stream = Some(merged);
+        }
+        (None, None) => {}
+    }
+}
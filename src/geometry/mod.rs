@@ -1,2 +1,4 @@
+pub mod mesh_data;
+pub mod normals;
 pub mod plane;
 pub mod sphere;
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use glam::Vec3;
+
+/// normal of a counter-clockwise triangle, zero for degenerate triangles
+pub fn face_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    let (a, b, c) = (Vec3::from(a), Vec3::from(b), Vec3::from(c));
+    (b - a).cross(c - a).normalize_or_zero().to_array()
+}
+
+/// one normal per vertex, averaged over the triangles sharing it and weighted by their area
+pub fn smooth_normals(positions: &[[f32; 3]], indices: &[u32]) -> Vec<[f32; 3]> {
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|i| i as usize);
+        if a >= positions.len() || b >= positions.len() || c >= positions.len() {
+            continue;
+        }
+        let (pa, pb, pc) = (
+            Vec3::from(positions[a]),
+            Vec3::from(positions[b]),
+            Vec3::from(positions[c]),
+        );
+        // 叉积长度是面积的两倍，不归一化即按面积加权
+        let normal = (pb - pa).cross(pc - pa);
+        normals[a] += normal;
+        normals[b] += normal;
+        normals[c] += normal;
+    }
+    normals
+        .into_iter()
+        .map(|normal| normal.normalize_or_zero().to_array())
+        .collect()
+}
+
+/// one normal per vertex of a non-indexed triangle list, every vertex takes its face's normal
+pub fn flat_normals(positions: &[[f32; 3]]) -> Vec<[f32; 3]> {
+    positions
+        .chunks_exact(3)
+        .flat_map(|triangle| {
+            let normal = face_normal(triangle[0], triangle[1], triangle[2]);
+            [normal; 3]
+        })
+        .collect()
+}
+
+/// smoothing group of a triangle, FLAT_GROUP triangles aren't smoothed with any other
+pub const FLAT_GROUP: u32 = 0;
+
+/// smooth normals where only triangles of the same group are averaged, vertices at the same position are
+/// averaged even if the index buffer splits them (e.g. at uv seams). vertices shared by several groups are
+/// duplicated, so this returns the source vertex of every new vertex, the new indices and the new normals
+pub fn grouped_smooth_normals(
+    positions: &[[f32; 3]],
+    indices: &[u32],
+    groups: &[u32],
+) -> (Vec<u32>, Vec<u32>, Vec<[f32; 3]>) {
+    // flat triangles get a key of their own
+    let group_key = |triangle: usize| match groups.get(triangle).copied().unwrap_or(1) {
+        FLAT_GROUP => (true, triangle as u32),
+        group => (false, group),
+    };
+    let position_key = |i: u32| positions[i as usize].map(f32::to_bits);
+
+    let mut sums: HashMap<([u32; 3], (bool, u32)), Vec3> = HashMap::new();
+    for (t, triangle) in indices.chunks_exact(3).enumerate() {
+        if triangle.iter().any(|i| *i as usize >= positions.len()) {
+            continue;
+        }
+        let [a, b, c] =
+            [triangle[0], triangle[1], triangle[2]].map(|i| Vec3::from(positions[i as usize]));
+        // 叉积长度是面积的两倍，不归一化即按面积加权
+        let normal = (b - a).cross(c - a);
+        for i in triangle {
+            *sums.entry((position_key(*i), group_key(t))).or_default() += normal;
+        }
+    }
+
+    let mut remap: HashMap<(u32, (bool, u32)), u32> = HashMap::new();
+    let mut sources = vec![];
+    let mut normals = vec![];
+    let mut new_indices = Vec::with_capacity(indices.len());
+    for (t, triangle) in indices.chunks_exact(3).enumerate() {
+        if triangle.iter().any(|i| *i as usize >= positions.len()) {
+            continue;
+        }
+        for i in triangle {
+            let key = group_key(t);
+            let index = *remap.entry((*i, key)).or_insert_with(|| {
+                sources.push(*i);
+                normals.push(
+                    sums[&(position_key(*i), key)]
+                        .normalize_or_zero()
+                        .to_array(),
+                );
+                sources.len() as u32 - 1
+            });
+            new_indices.push(index);
+        }
+    }
+    (sources, new_indices, normals)
+}
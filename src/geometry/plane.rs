@@ -1,7 +1,6 @@
-use crate::{
-    components::mesh::{Mesh, VertexFormat, VertexPositionNormalTexture, VertexPositionTexture},
-    renderer::Renderer,
-};
+use crate::{components::mesh::Mesh, renderer::Renderer};
+
+use super::mesh_data::MeshData;
 
 pub struct MakePlaneConfig {
     pub width: f32,
@@ -21,7 +20,7 @@ impl Default for MakePlaneConfig {
     }
 }
 
-pub fn make_plane_data(config: MakePlaneConfig) -> (Vec<f32>, Vec<u32>, Vec<f32>, Vec<f32>) {
+pub fn make_plane_data(config: MakePlaneConfig) -> MeshData {
     let mut vertices = Vec::new();
     let mut indices = Vec::new();
     let mut normals = Vec::new();
@@ -40,9 +39,9 @@ pub fn make_plane_data(config: MakePlaneConfig) -> (Vec<f32>, Vec<u32>, Vec<f32>
     let vertex_count_y = config.height_segments + 1;
 
     // 预分配内存以提高性能
-    vertices.reserve(vertex_count_x * vertex_count_y * 3);
-    normals.reserve(vertex_count_x * vertex_count_y * 3);
-    uvs.reserve(vertex_count_x * vertex_count_y * 2);
+    vertices.reserve(vertex_count_x * vertex_count_y);
+    normals.reserve(vertex_count_x * vertex_count_y);
+    uvs.reserve(vertex_count_x * vertex_count_y);
 
     // 生成顶点、法线和UV坐标
     for iy in 0..vertex_count_y {
@@ -52,18 +51,16 @@ pub fn make_plane_data(config: MakePlaneConfig) -> (Vec<f32>, Vec<u32>, Vec<f32>
             let x = (ix as f32 * segment_width) - width_half;
 
             // 顶点位置 - XY平面
-            vertices.push(x);
-            vertices.push(y);
-            vertices.push(0.0);
+            vertices.push([x, y, 0.0]);
 
             // 法线方向 - Z轴正向
-            normals.push(0.0);
-            normals.push(0.0);
-            normals.push(1.0);
+            normals.push([0.0, 0.0, 1.0]);
 
             // UV坐标 - 标准化并翻转Y轴
-            uvs.push(ix as f32 / config.width_segments as f32);
-            uvs.push(1.0 - (iy as f32 / config.height_segments as f32));
+            uvs.push([
+                ix as f32 / config.width_segments as f32,
+                1.0 - (iy as f32 / config.height_segments as f32),
+            ]);
         }
     }
 
@@ -87,26 +84,22 @@ pub fn make_plane_data(config: MakePlaneConfig) -> (Vec<f32>, Vec<u32>, Vec<f32>
         }
     }
 
-    (vertices, indices, normals, uvs)
+    MeshData {
+        positions: vertices,
+        normals: Some(normals),
+        uvs: Some(uvs),
+        indices,
+        ..Default::default()
+    }
 }
 
 pub fn make_plane_mesh(config: MakePlaneConfig, renderer: &Renderer) -> Mesh {
-    let (vertices, indices, normals, uvs) = make_plane_data(config);
-    let mut vertexes = Vec::new();
-
-    for i in 0..(vertices.len() / 3) {
-        vertexes.push(VertexPositionTexture {
-            position: [vertices[i * 3], vertices[i * 3 + 1], vertices[i * 3 + 2]],
-            tex_coords: [uvs[i * 2], uvs[i * 2 + 1]],
-        });
-    }
-
-    Mesh::new(
-        bytemuck::cast_slice(&vertexes),
-        indices,
-        VertexFormat::PositionTexture,
-        renderer,
-    )
+    // position + uv as before, the image and instance examples are drawn with that layout
+    let data = MeshData {
+        normals: None,
+        ..make_plane_data(config)
+    };
+    Mesh::from_data(&data, renderer)
 }
 
 // pub fn make_image_mesh(
@@ -1,9 +1,8 @@
 use glam::Vec3;
 
-use crate::{
-    components::mesh::{Mesh, VertexFormat, VertexPositionNormalTexture},
-    renderer::Renderer,
-};
+use crate::{components::mesh::Mesh, renderer::Renderer};
+
+use super::mesh_data::MeshData;
 
 pub struct MakeSphereConfig {
     pub radius: f32,
@@ -29,7 +28,7 @@ impl Default for MakeSphereConfig {
     }
 }
 
-pub fn make_sphere_data(config: MakeSphereConfig) -> (Vec<f32>, Vec<u32>, Vec<f32>, Vec<f32>) {
+pub fn make_sphere_data(config: MakeSphereConfig) -> MeshData {
     let mut vertices = Vec::new();
     let mut indices = Vec::new();
     let mut normals = Vec::new();
@@ -63,19 +62,14 @@ pub fn make_sphere_data(config: MakeSphereConfig) -> (Vec<f32>, Vec<u32>, Vec<f3
                 * (config.phi_start + u * config.phi_length).sin()
                 * (config.theta_start + v * config.theta_length).sin();
 
-            vertices.push(vertex.x);
-            vertices.push(vertex.y);
-            vertices.push(vertex.z);
+            vertices.push(vertex.to_array());
 
             // normal
             normal = vertex.normalize();
-            normals.push(normal.x);
-            normals.push(normal.y);
-            normals.push(normal.z);
+            normals.push(normal.to_array());
 
             // uv
-            uvs.push(u + u_offset);
-            uvs.push(1.0 - v);
+            uvs.push([u + u_offset, 1.0 - v]);
 
             vertices_row.push(index);
             index += 1;
@@ -106,24 +100,15 @@ pub fn make_sphere_data(config: MakeSphereConfig) -> (Vec<f32>, Vec<u32>, Vec<f3
         }
     }
 
-    (vertices, indices, normals, uvs)
+    MeshData {
+        positions: vertices,
+        normals: Some(normals),
+        uvs: Some(uvs),
+        indices,
+        ..Default::default()
+    }
 }
 
 pub fn make_sphere_mesh(config: MakeSphereConfig, renderer: &Renderer) -> Mesh {
-    let (vertices, indices, normals, uvs) = make_sphere_data(config);
-    let mut vertexes = Vec::new();
-    for i in 0..(vertices.len() / 3) {
-        vertexes.push(VertexPositionNormalTexture {
-            position: [vertices[i * 3], vertices[i * 3 + 1], vertices[i * 3 + 2]],
-            normal: [normals[i * 3], normals[i * 3 + 1], normals[i * 3 + 2]],
-            tex_coords: [uvs[i * 2], uvs[i * 2 + 1]],
-        });
-    }
-
-    Mesh::new(
-        bytemuck::cast_slice(&vertexes),
-        indices,
-        VertexFormat::PositionNormalTexture,
-        renderer,
-    )
+    Mesh::from_data(&make_sphere_data(config), renderer)
 }
@@ -1,8 +1,11 @@
 // a principal: that the user own the user-created struct otherwise it's owned by the creater
+pub mod app;
+pub mod component_store;
 pub mod components;
 pub mod entity;
 pub mod geometry;
 pub mod mini_gpu;
+pub mod query;
 pub mod renderer;
 pub mod scene;
 pub mod system;
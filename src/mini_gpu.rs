@@ -1,10 +1,18 @@
 use std::sync::Arc;
 
-use winit::window::Window;
+use winit::{
+    dpi::LogicalSize,
+    error::EventLoopError,
+    event::{Event, WindowEvent},
+    event_loop::{EventLoop, EventLoopWindowTarget},
+    window::{Window, WindowBuilder},
+};
 
 use crate::{
-    renderer::{Renderer, RendererConfig},
+    app::{App, AppConfig},
+    renderer::{HeadlessConfig, Renderer, RendererConfig},
     scene,
+    system::{camera_controller_system::CameraControllerSystem, mesh_render::MeshRender},
 };
 
 pub struct MiniGPU {
@@ -31,7 +39,8 @@ impl MiniGPU {
         .await;
         let mut scene = scene::Scene::new();
         scene.add_default_camera(&renderer);
-        scene.add_default_directional_light(&renderer);
+        scene.add_default_directional_light();
+        scene.add_default_ambient_light();
         MiniGPU {
             config,
             renderer,
@@ -39,7 +48,140 @@ impl MiniGPU {
         }
     }
 
+    /// create a MiniGPU without window, render into an offscreen texture and read it back by `renderer.capture_image()`
+    pub async fn new_headless(config: HeadlessConfig) -> MiniGPU {
+        let renderer = Renderer::new_headless(config).await;
+        let mut scene = scene::Scene::new();
+        scene.add_default_camera(&renderer);
+        scene.add_default_directional_light();
+        scene.add_default_ambient_light();
+        MiniGPU {
+            config: MiniGPUConfig {
+                width: renderer.config.width,
+                height: renderer.config.height,
+            },
+            renderer,
+            scene,
+        }
+    }
+
     pub fn get_default_scene(&mut self) -> &scene::Scene {
         &self.scene
     }
+
+    /// resize the surface and keep the default camera's aspect in sync, zero size (minimized) is ignored
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.config.width = width;
+        self.config.height = height;
+        self.renderer.resize(width, height);
+        if let Some(camera) = self.scene.get_default_camera_mut() {
+            camera.set_aspect(width as f32 / height as f32, &self.renderer);
+        }
+    }
+
+    /// create the window and event loop, then drive the app until the window is closed.
+    /// resize, scale factor changes, controller events and redraws are handled here, on native and wasm
+    pub async fn run<A: App + 'static>(config: AppConfig, mut app: A) -> Result<(), EventLoopError> {
+        let event_loop = EventLoop::new()?;
+        let window = WindowBuilder::new()
+            .with_title(&config.title)
+            .with_inner_size(LogicalSize::new(config.width, config.height))
+            .build(&event_loop)
+            .expect("Failed to create window");
+        #[cfg(target_arch = "wasm32")]
+        Self::append_canvas(&window, config.canvas_parent_id.as_deref());
+
+        // the web canvas may not be sized yet, fall back to the requested size
+        let size = window.inner_size();
+        let scale_factor = window.scale_factor();
+        let (width, height) = if size.width == 0 || size.height == 0 {
+            let size = LogicalSize::new(config.width, config.height).to_physical(scale_factor);
+            (size.width, size.height)
+        } else {
+            (size.width, size.height)
+        };
+        let window_id = window.id();
+        let mut mini_gpu = MiniGPU::new(MiniGPUConfig { width, height }, window).await;
+        if config.default_systems {
+            let renderer = &mut mini_gpu.renderer;
+            renderer.add_system(
+                "controller".to_string(),
+                Box::new(CameraControllerSystem::default()),
+            );
+            renderer.add_system("render".to_string(), Box::new(MeshRender {}));
+        }
+        app.setup(&mut mini_gpu);
+
+        let mut last_frame_time = web_time::Instant::now();
+        event_loop.run(move |event, target| match event {
+            Event::WindowEvent { window_id: id, event } if id == window_id => {
+                app.on_event(&mut mini_gpu, &event);
+                mini_gpu.renderer.process_event(&event);
+                mini_gpu.handle_window_event(&mut app, event, target, &mut last_frame_time);
+            }
+            Event::AboutToWait => {
+                mini_gpu.renderer.request_redraw();
+            }
+            _ => {}
+        })
+    }
+
+    fn handle_window_event<A: App>(
+        &mut self,
+        app: &mut A,
+        event: WindowEvent,
+        target: &EventLoopWindowTarget<()>,
+        last_frame_time: &mut web_time::Instant,
+    ) {
+        match event {
+            WindowEvent::Resized(size) => {
+                self.resize(size.width, size.height);
+                self.renderer.request_redraw();
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                // a Resized event with the new physical size follows
+                self.renderer.viewport.scale_factor = scale_factor as f32;
+            }
+            WindowEvent::RedrawRequested => {
+                let now = web_time::Instant::now();
+                let dt = now.duration_since(*last_frame_time).as_secs_f32();
+                *last_frame_time = now;
+                app.update(self, dt);
+                match self.renderer.render(&mut self.scene) {
+                    Ok(_) => {}
+                    // surface needs to be reconfigured
+                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                        self.resize(self.config.width, self.config.height);
+                    }
+                    Err(wgpu::SurfaceError::OutOfMemory) => {
+                        log::error!("Out of memory, exit");
+                        target.exit();
+                    }
+                    Err(e) => log::warn!("Failed to render: {}", e),
+                }
+            }
+            WindowEvent::CloseRequested => target.exit(),
+            _ => {}
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn append_canvas(window: &Window, parent_id: Option<&str>) {
+        use winit::platform::web::WindowExtWebSys;
+        web_sys::window()
+            .and_then(|win| win.document())
+            .and_then(|doc| {
+                let parent = match parent_id {
+                    Some(id) => doc.get_element_by_id(id)?,
+                    None => doc.body()?.into(),
+                };
+                let canvas = web_sys::Element::from(window.canvas()?);
+                parent.append_child(&canvas).ok()?;
+                Some(())
+            })
+            .expect("Failed to append canvas to document");
+    }
 }
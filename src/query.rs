@@ -0,0 +1,60 @@
+use std::any::Any;
+
+use crate::{entity::Entity, scene::Scene};
+
+/// a component request of scene.query, implemented for `&T`, `Option<&T>` and tuples of them.
+/// components are matched by type, the name used to attach them does not matter
+pub trait Query<'a> {
+    type Item;
+    fn fetch(scene: &'a Scene, entity: &'a Entity) -> Option<Self::Item>;
+    /// false if the scene holds no component this query requires, so the entity scan can be skipped
+    fn may_match(scene: &Scene) -> bool;
+}
+
+impl<'a, T: Any> Query<'a> for &'a T {
+    type Item = &'a T;
+
+    fn fetch(scene: &'a Scene, entity: &'a Entity) -> Option<Self::Item> {
+        let component_id = entity.get_component_id::<T>()?;
+        scene.get_component::<T>(component_id).ok()
+    }
+
+    fn may_match(scene: &Scene) -> bool {
+        scene.component_count::<T>() > 0
+    }
+}
+
+impl<'a, T: Any> Query<'a> for Option<&'a T> {
+    type Item = Option<&'a T>;
+
+    fn fetch(scene: &'a Scene, entity: &'a Entity) -> Option<Self::Item> {
+        Some(<&'a T as Query<'a>>::fetch(scene, entity))
+    }
+
+    fn may_match(_scene: &Scene) -> bool {
+        true
+    }
+}
+
+macro_rules! impl_query_tuple {
+    ($($name:ident),+) => {
+        impl<'a, $($name: Query<'a>),+> Query<'a> for ($($name,)+) {
+            type Item = ($($name::Item,)+);
+
+            fn fetch(scene: &'a Scene, entity: &'a Entity) -> Option<Self::Item> {
+                Some(($($name::fetch(scene, entity)?,)+))
+            }
+
+            fn may_match(scene: &Scene) -> bool {
+                $($name::may_match(scene))&&+
+            }
+        }
+    };
+}
+
+impl_query_tuple!(A);
+impl_query_tuple!(A, B);
+impl_query_tuple!(A, B, C);
+impl_query_tuple!(A, B, C, D);
+impl_query_tuple!(A, B, C, D, E);
+impl_query_tuple!(A, B, C, D, E, F);
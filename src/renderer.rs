@@ -1,9 +1,20 @@
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
 
-use winit::window::Window;
+use winit::{event::WindowEvent, window::Window};
 
 use crate::{
-    components::viewport::Viewport, scene::Scene, system::system::System, utils::depth_texture,
+    components::viewport::Viewport,
+    scene::Scene,
+    system::{
+        animation_system::AnimationSystem,
+        morph_system::MorphSystem,
+        mesh_render::MeshRenderResources,
+        schedule::Schedule,
+        skin_system::SkinSystem,
+        system::{FrameContext, Stage, System},
+        transform_system::TransformSystem,
+    },
+    utils::{depth_texture, render_target::RenderTarget, shadow_map::ShadowMaps},
 };
 
 pub struct Renderer {
@@ -13,11 +24,18 @@ pub struct Renderer {
     pub adapter: wgpu::Adapter,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
-    pub surface: wgpu::Surface<'static>,
-    pub window: Arc<Window>,
-    pub systems_map: HashMap<String, Box<dyn System>>,
+    // surface and window are None for headless renderer
+    pub surface: Option<wgpu::Surface<'static>>,
+    pub window: Option<Arc<Window>>,
+    // owned color target for headless renderer
+    pub render_target: Option<RenderTarget>,
+    pub schedule: Schedule,
     pub depth_texture: depth_texture::DepthTexture,
+    pub shadow_maps: ShadowMaps,
+    pub mesh_render_resources: MeshRenderResources,
     pub viewport: Viewport,
+    pub frame_index: u64,
+    last_frame_time: Option<web_time::Instant>,
 }
 
 pub struct RendererConfig {
@@ -25,6 +43,39 @@ pub struct RendererConfig {
     pub height: u32,
 }
 
+pub struct HeadlessConfig {
+    pub width: u32,
+    pub height: u32,
+    /// use the software adapter, so it can run on machines without gpu
+    pub force_fallback_adapter: bool,
+    pub format: wgpu::TextureFormat,
+}
+
+impl Default for HeadlessConfig {
+    fn default() -> Self {
+        HeadlessConfig {
+            width: 800,
+            height: 600,
+            force_fallback_adapter: false,
+            format: RenderTarget::DEFAULT_FORMAT,
+        }
+    }
+}
+
+/// the texture a frame is drawn into, present it after the commands are submitted
+pub struct Frame {
+    pub view: wgpu::TextureView,
+    surface_texture: Option<wgpu::SurfaceTexture>,
+}
+
+impl Frame {
+    pub fn present(self) {
+        if let Some(surface_texture) = self.surface_texture {
+            surface_texture.present();
+        }
+    }
+}
+
 impl Renderer {
     pub async fn new(config: RendererConfig, window: Arc<Window>) -> Renderer {
         let instance = wgpu::Instance::default();
@@ -37,20 +88,7 @@ impl Renderer {
             })
             .await
             .expect("Failed to find an appropriate adapter");
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    required_features: wgpu::Features::empty(),
-                    // Make sure we use the texture resolution limits from the adapter, so we can support images the size of the swapchain.
-                    required_limits: wgpu::Limits::downlevel_webgl2_defaults()
-                        .using_resolution(adapter.limits()),
-                },
-                None,
-            )
-            .await
-            .expect("Failed to create device");
+        let (device, queue) = Self::request_device(&adapter).await;
 
         let swapchain_capabilities = surface.get_capabilities(&adapter);
         let swapchain_format = swapchain_capabilities.formats[0];
@@ -67,48 +105,246 @@ impl Renderer {
         surface.configure(&device, &surface_config);
         let depth_texture =
             depth_texture::DepthTexture::new(&device, &surface_config, "depth_texture");
+        let shadow_maps = ShadowMaps::new(&device);
+        let mesh_render_resources = MeshRenderResources::new(&device);
         let scale_factor = window.scale_factor();
         Renderer {
-            window,
+            window: Some(window),
             viewport: Viewport::new(config.width, config.height, scale_factor as f32),
             config,
             surface_config,
             swapchain_format,
             adapter,
-            surface,
+            surface: Some(surface),
+            render_target: None,
+            device,
+            queue,
+            depth_texture,
+            shadow_maps,
+            mesh_render_resources,
+            schedule: Self::default_schedule(),
+            frame_index: 0,
+            last_frame_time: None,
+        }
+    }
+
+    /// create a renderer without window, it renders into an owned texture which can be read back by `read_pixels`
+    pub async fn new_headless(config: HeadlessConfig) -> Renderer {
+        let instance = wgpu::Instance::default();
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                force_fallback_adapter: config.force_fallback_adapter,
+                compatible_surface: None,
+            })
+            .await
+            .expect("Failed to find an appropriate adapter");
+        let (device, queue) = Self::request_device(&adapter).await;
+
+        // surface config is only used to describe the target size and format
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: config.format,
+            width: config.width,
+            height: config.height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        let render_target = RenderTarget::new(
+            &device,
+            config.width,
+            config.height,
+            config.format,
+            "render_target",
+        );
+        let depth_texture =
+            depth_texture::DepthTexture::new(&device, &surface_config, "depth_texture");
+        let shadow_maps = ShadowMaps::new(&device);
+        let mesh_render_resources = MeshRenderResources::new(&device);
+        Renderer {
+            window: None,
+            viewport: Viewport::new(config.width, config.height, 1.),
+            config: RendererConfig {
+                width: config.width,
+                height: config.height,
+            },
+            surface_config,
+            swapchain_format: config.format,
+            adapter,
+            surface: None,
+            render_target: Some(render_target),
             device,
             queue,
             depth_texture,
-            systems_map: HashMap::new(),
+            shadow_maps,
+            mesh_render_resources,
+            schedule: Self::default_schedule(),
+            frame_index: 0,
+            last_frame_time: None,
         }
     }
 
+    async fn request_device(adapter: &wgpu::Adapter) -> (wgpu::Device, wgpu::Queue) {
+        adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    // wireframe and point materials, see RenderState::polygon_mode
+                    required_features: adapter.features()
+                        & (wgpu::Features::POLYGON_MODE_LINE | wgpu::Features::POLYGON_MODE_POINT),
+                    // Make sure we use the texture resolution limits from the adapter, so we can support images the size of the swapchain.
+                    required_limits: wgpu::Limits::downlevel_webgl2_defaults()
+                        .using_resolution(adapter.limits()),
+                },
+                None,
+            )
+            .await
+            .expect("Failed to create device")
+    }
+
     pub fn resize(&mut self, width: u32, height: u32) {
+        self.config.width = width;
+        self.config.height = height;
         self.viewport.width = width as f32;
         self.viewport.height = height as f32;
+        self.viewport.aspect = width as f32 / height as f32;
         self.surface_config.width = width;
         self.surface_config.height = height;
-        self.surface.configure(&self.device, &self.surface_config);
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.surface_config);
+        }
+        if let Some(render_target) = &self.render_target {
+            self.render_target = Some(RenderTarget::new(
+                &self.device,
+                width,
+                height,
+                render_target.format,
+                "render_target",
+            ));
+        }
         self.depth_texture =
             depth_texture::DepthTexture::new(&self.device, &self.surface_config, "depth_texture");
     }
 
-    pub fn render(&self, scene: &Scene) -> Result<(), wgpu::SurfaceError> {
-        let map = &self.systems_map;
-        for (_, system) in map {
-            {
-                system.update(self, scene);
-            }
+    /// get the texture to draw this frame into, the swapchain texture or the headless target
+    pub fn get_current_frame(&self) -> Result<Frame, wgpu::SurfaceError> {
+        if let Some(surface) = &self.surface {
+            let surface_texture = surface.get_current_texture()?;
+            let view = surface_texture
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default());
+            return Ok(Frame {
+                view,
+                surface_texture: Some(surface_texture),
+            });
         }
+        let render_target = self
+            .render_target
+            .as_ref()
+            .expect("Renderer has neither a surface nor a render target");
+        Ok(Frame {
+            view: render_target
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+            surface_texture: None,
+        })
+    }
+
+    /// read the last rendered frame of a headless renderer as tightly packed RGBA8
+    pub fn read_pixels(&self) -> anyhow::Result<Vec<u8>> {
+        let render_target = self
+            .render_target
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("read_pixels is only supported by headless renderer"))?;
+        render_target.read_pixels(&self.device, &self.queue)
+    }
+
+    pub fn capture_image(&self) -> anyhow::Result<image::RgbaImage> {
+        let pixels = self.read_pixels()?;
+        image::RgbaImage::from_raw(self.config.width, self.config.height, pixels)
+            .ok_or_else(|| anyhow::anyhow!("Failed to create image from pixels"))
+    }
+
+    pub fn request_redraw(&self) {
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+    }
+
+    // transforms are propagated by default, so a renderer only needs MeshRender added to draw
+    fn default_schedule() -> Schedule {
+        let mut schedule = Schedule::new();
+        let defaults: Vec<(&str, Box<dyn System>)> = vec![
+            ("animation", Box::new(AnimationSystem {})),
+            ("transform", Box::new(TransformSystem {})),
+            // joint matrices need this frame's global matrices
+            ("skin", Box::new(SkinSystem {})),
+            ("morph", Box::new(MorphSystem {})),
+        ];
+        for (name, system) in defaults {
+            schedule.add_system(system.stage(), name.to_string(), system);
+        }
+        schedule
+    }
+
+    /// run all systems in schedule order with one shared encoder, then submit and present the frame
+    pub fn render(&mut self, scene: &mut Scene) -> Result<(), wgpu::SurfaceError> {
+        let now = web_time::Instant::now();
+        let delta_time = self
+            .last_frame_time
+            .map_or(0., |last| now.duration_since(last).as_secs_f32());
+        self.last_frame_time = Some(now);
+
+        let frame = self.get_current_frame()?;
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Frame Encoder"),
+            });
+        // the depth pipelines of shadow casters need &mut, build them before the systems run
+        self.shadow_maps.prepare(&self.device, scene);
+        self.mesh_render_resources.prepare(&self.device, scene);
+        // systems borrow the renderer through the context, take the schedule out while it runs
+        let mut schedule = std::mem::take(&mut self.schedule);
+        let mut ctx = FrameContext {
+            renderer: self,
+            encoder: &mut encoder,
+            view: &frame.view,
+            delta_time,
+            frame_index: self.frame_index,
+        };
+        schedule.run(&mut ctx, scene);
+        self.schedule = schedule;
+
+        self.queue.submit(Some(encoder.finish()));
+        frame.present();
+        self.frame_index += 1;
         Ok(())
     }
 
+    /// add a system to the stage it asks for, see `System::stage`
     pub fn add_system(&mut self, name: String, system: Box<dyn System>) -> Option<Box<dyn System>> {
-        self.systems_map.insert(name, system)
+        let stage = system.stage();
+        self.schedule.add_system(stage, name, system)
+    }
+
+    pub fn add_system_to_stage(
+        &mut self,
+        stage: Stage,
+        name: String,
+        system: Box<dyn System>,
+    ) -> Option<Box<dyn System>> {
+        self.schedule.add_system(stage, name, system)
+    }
+
+    pub fn remove_system(&mut self, name: &str) -> Option<Box<dyn System>> {
+        self.schedule.remove_system(name)
     }
 
-    pub fn remove_system(&mut self, name: &str) {
-        let system = self.systems_map.remove(name).unwrap();
-        drop(system);
+    /// forward window events to systems, e.g. camera controllers
+    pub fn process_event(&mut self, event: &WindowEvent) {
+        self.schedule.on_event(event);
     }
 }
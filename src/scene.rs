@@ -3,195 +3,307 @@ use glam::Vec3;
 use crate::{
     components::{
         lights::{
+            ambient_light::{AmbientLight, AmbientLightUniform},
             directional_light::{DirectionalLight, DirectionalLightUniform},
             light::LightTrait,
         },
         perspective_camera::*,
     },
-    entity::Entity,
+    component_store::{ComponentError, ComponentId, ComponentStore},
+    entity::{Entity, EntityId},
+    query::Query,
     renderer,
 };
-use std::any::{Any, TypeId};
-const DEFAULT_CAMERA_BIND_INDEX: u32 = 0;
-const DEFAULT_LIGHT_BIND_INDEX: u32 = 1;
-
-pub struct Component {
-    ptr: *mut dyn Any,       // 指向组件实例的指针
-    type_id: TypeId,         // 存储组件的类型 ID
-    type_name: &'static str, // 存储类型名称，用于错误消息
+use std::{any::Any, collections::HashSet};
+pub(crate) const DEFAULT_CAMERA_BIND_INDEX: u32 = 0;
+
+struct EntitySlot {
+    generation: u32,
+    entity: Option<Entity>,
 }
 
 #[derive(Default)]
 pub struct Scene {
-    pub entities: Vec<Entity>,
+    entities: Vec<EntitySlot>,
+    free_entities: Vec<u32>,
     pub background_color: wgpu::Color,
-    pub default_camera: Option<usize>,
-    pub default_light: Option<usize>,
-    components: Vec<Component>,
+    pub default_camera: Option<EntityId>,
+    pub default_light: Option<EntityId>,
+    pub default_ambient_light: Option<EntityId>,
+    // typed storages, components are dropped together with the scene
+    components: ComponentStore,
 }
 impl Scene {
     pub fn new() -> Scene {
-        let instance = Scene {
+        Scene {
             entities: Vec::new(),
+            free_entities: Vec::new(),
             background_color: wgpu::Color::TRANSPARENT,
-            components: Vec::new(),
+            components: ComponentStore::new(),
             default_camera: None,
             default_light: None,
-        };
-        instance
+            default_ambient_light: None,
+        }
     }
 
-    pub fn add_default_entity(&mut self) -> usize {
+    pub fn add_default_entity(&mut self) -> EntityId {
         self.add_entity(Entity::new())
     }
 
-    pub fn add_entity(&mut self, entity: Entity) -> usize {
-        self.entities.push(entity);
-        self.entities.len() - 1
+    pub fn add_entity(&mut self, entity: Entity) -> EntityId {
+        // reuse a freed slot, its generation was bumped when it was freed
+        if let Some(index) = self.free_entities.pop() {
+            let slot = &mut self.entities[index as usize];
+            slot.entity = Some(entity);
+            return EntityId {
+                index,
+                generation: slot.generation,
+            };
+        }
+        self.entities.push(EntitySlot {
+            generation: 0,
+            entity: Some(entity),
+        });
+        EntityId {
+            index: (self.entities.len() - 1) as u32,
+            generation: 0,
+        }
     }
 
-    pub fn add_entity_child(&mut self, parent_id: usize, mut entity: Entity) -> usize {
-        entity.is_child = true;
+    /// the child isn't added when the parent handle is stale
+    pub fn add_entity_child(
+        &mut self,
+        parent_id: EntityId,
+        mut entity: Entity,
+    ) -> Result<EntityId, ComponentError> {
+        if !self.contains_entity(parent_id) {
+            return Err(ComponentError::EntityNotFound(parent_id));
+        }
+        entity.parent = Some(parent_id);
         let child_id = self.add_entity(entity);
-        let parent = self.entities.get_mut(parent_id);
-        if parent.is_none() {
-            panic!("parent entity not found");
+        self.get_entity_mut(parent_id).unwrap().add_child(child_id);
+        Ok(child_id)
+    }
+
+    pub fn contains_entity(&self, id: EntityId) -> bool {
+        self.get_entity(id).is_some()
+    }
+
+    pub fn get_entity(&self, id: EntityId) -> Option<&Entity> {
+        self.entities
+            .get(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.entity.as_ref())
+    }
+
+    pub fn get_entity_mut(&mut self, id: EntityId) -> Option<&mut Entity> {
+        self.entities
+            .get_mut(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.entity.as_mut())
+    }
+
+    /// iterate all alive entities with their handles
+    pub fn iter_entities(&self) -> impl Iterator<Item = (EntityId, &Entity)> {
+        self.entities
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                slot.entity.as_ref().map(|entity| {
+                    (
+                        EntityId {
+                            index: index as u32,
+                            generation: slot.generation,
+                        },
+                        entity,
+                    )
+                })
+            })
+    }
+
+    pub fn entity_count(&self) -> usize {
+        self.entities.len() - self.free_entities.len()
+    }
+
+    /// remove the entity and all its children, returns None if the handle is stale.
+    /// their components are dropped unless another alive entity still uses them,
+    /// so the ids left in the returned entity may be stale
+    pub fn remove_entity(&mut self, id: EntityId) -> Option<Entity> {
+        let entity = self.take_entity(id)?;
+        if let Some(parent_id) = entity.parent {
+            if let Some(parent) = self.get_entity_mut(parent_id) {
+                parent.remove_child(id);
+            }
+        }
+        let mut component_ids: Vec<ComponentId> = entity.components_map.values().copied().collect();
+        for child_id in &entity.children {
+            self.despawn_recursive(*child_id, &mut component_ids);
         }
-        let parent = parent.unwrap();
-        parent.add_child(child_id);
-        child_id
+        self.drop_unused_components(component_ids);
+        Some(entity)
     }
 
-    pub fn get_entity(&self, id: usize) -> &Entity {
-        &self.entities[id]
+    fn despawn_recursive(&mut self, id: EntityId, component_ids: &mut Vec<ComponentId>) {
+        if let Some(entity) = self.take_entity(id) {
+            component_ids.extend(entity.components_map.values().copied());
+            for child_id in &entity.children {
+                self.despawn_recursive(*child_id, component_ids);
+            }
+        }
     }
-    pub fn get_entity_mut(&mut self, id: usize) -> &mut Entity {
-        &mut self.entities[id]
+
+    // a material or mesh may be shared with entities outside of the removed tree
+    fn drop_unused_components(&mut self, component_ids: Vec<ComponentId>) {
+        let used: HashSet<ComponentId> = self
+            .iter_entities()
+            .flat_map(|(_, entity)| entity.components_map.values().copied())
+            .collect();
+        for component_id in component_ids {
+            if !used.contains(&component_id) {
+                self.components.remove_any(component_id);
+            }
+        }
     }
 
-    pub fn remove_entity(&mut self, id: usize) {
-        let entity = self.entities.remove(id);
-        drop(entity)
+    fn take_entity(&mut self, id: EntityId) -> Option<Entity> {
+        let slot = self.entities.get_mut(id.index as usize)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        let entity = slot.entity.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_entities.push(id.index);
+        Some(entity)
     }
 
-    // make component manually memory management
-    pub fn add_component<T: Any + 'static>(&mut self, component: T) -> usize {
-        let b = Box::new(component);
-        let type_id = TypeId::of::<T>();
-        let type_name = std::any::type_name::<T>();
-        let raw_ptr = Box::into_raw(b) as *mut dyn Any;
-        self.components.push(Component {
-            ptr: raw_ptr,
-            type_id,
-            type_name,
-        });
-        self.components.len() - 1
+    pub fn add_component<T: Any>(&mut self, component: T) -> ComponentId {
+        self.components.insert(component)
     }
 
-    pub fn set_entity_component<T: Any + 'static>(
+    /// panics when entity_id is stale
+    pub fn set_entity_component<T: Any>(
         &mut self,
-        entity_id: usize,
+        entity_id: EntityId,
         component: T,
         name: &str,
-    ) -> usize {
-        let component_ptr = self.add_component::<T>(component);
-        let entity = self.entities.get_mut(entity_id).unwrap();
-        entity.set_component_index(name, component_ptr);
-        component_ptr
+    ) -> ComponentId {
+        assert!(
+            self.contains_entity(entity_id),
+            "entity not found: {}",
+            entity_id
+        );
+        let component_id = self.add_component::<T>(component);
+        self.get_entity_mut(entity_id)
+            .unwrap()
+            .set_component_index(name, component_id);
+        component_id
     }
+
+    /// share an existing component with another entity, e.g. one material for many meshes
     pub fn set_entity_component_index(
         &mut self,
-        entity_id: usize,
-        component_id: usize,
+        entity_id: EntityId,
+        component_id: ComponentId,
         name: &str,
-    ) -> usize {
-        let entity = self.entities.get_mut(entity_id).unwrap();
+    ) -> Result<ComponentId, ComponentError> {
+        let entity = self
+            .get_entity_mut(entity_id)
+            .ok_or(ComponentError::EntityNotFound(entity_id))?;
         entity.set_component_index(name, component_id);
-        component_id
+        Ok(component_id)
     }
 
-    pub fn get_component<T: Any + 'static>(&self, component_index: usize) -> &mut T {
-        let component = self
-            .components
-            .get(component_index)
-            .expect("Invalid component index");
-
-        // 检查类型是否匹配
-        // if component.type_id != TypeId::of::<T>() {
-        //     panic!(
-        //         "Type mismatch: expected {}, but found {}",
-        //         std::any::type_name::<T>(),
-        //         component.type_name
-        //     );
-        // }
+    /// number of alive components of type T
+    pub fn component_count<T: Any>(&self) -> usize {
+        self.components.count::<T>()
+    }
 
-        let ptr = component.ptr as *mut T;
-        assert!(!ptr.is_null(), "Component pointer is null");
+    /// iterate entities holding all requested components, e.g.
+    /// `scene.query::<(&Mesh, &Box<dyn MaterialTrait>, Option<&Instance>)>()`
+    pub fn query<'a, Q: Query<'a>>(&'a self) -> impl Iterator<Item = (EntityId, Q::Item)> + 'a {
+        let may_match = Q::may_match(self);
+        self.iter_entities()
+            .take_while(move |_| may_match)
+            .filter_map(move |(entity_id, entity)| {
+                Q::fetch(self, entity).map(|item| (entity_id, item))
+            })
+    }
 
-        unsafe { &mut *ptr }
+    pub fn get_component<T: Any>(&self, component_id: ComponentId) -> Result<&T, ComponentError> {
+        self.components.get::<T>(component_id)
     }
 
-    pub fn drop_component<T: Any + 'static>(&mut self, component_index: usize) {
-        if component_index >= self.components.len() {
-            return;
-        }
-        let component = &self.components[component_index];
-        if component.type_id != TypeId::of::<T>() {
-            panic!(
-                "Type mismatch while dropping: expected {}, but found {}",
-                std::any::type_name::<T>(),
-                component.type_name
-            );
-        }
-        let component = self.components.remove(component_index);
-        drop(unsafe { Box::from_raw(component.ptr as *mut T) });
+    pub fn get_component_mut<T: Any>(
+        &mut self,
+        component_id: ComponentId,
+    ) -> Result<&mut T, ComponentError> {
+        self.components.get_mut::<T>(component_id)
     }
 
-    pub fn get_entity_component<T: Any + 'static>(&self, entity: &Entity, component: &str) -> &T {
-        let component_ptr = entity.get_component_index(component);
-        self.get_component::<T>(component_ptr)
+    /// drop the component, entities still holding its id will get ComponentError::NotFound
+    pub fn drop_component<T: Any>(&mut self, component_id: ComponentId) -> Result<(), ComponentError> {
+        self.components.remove::<T>(component_id).map(drop)
     }
 
-    pub fn get_entity_component_mut<T: Any + 'static>(
+    pub fn get_entity_component<T: Any>(
         &self,
-        entity: &Entity,
+        entity_id: EntityId,
         component: &str,
-    ) -> &mut T {
-        let component_ptr = entity.get_component_index(component);
-        self.get_component::<T>(component_ptr)
+    ) -> Result<&T, ComponentError> {
+        let component_id = self.get_entity_component_index(entity_id, component)?;
+        self.get_component::<T>(component_id)
     }
 
-    pub fn drop_entity_component<T: Any + 'static>(
+    pub fn get_entity_component_mut<T: Any>(
         &mut self,
-        entity: &mut Entity,
+        entity_id: EntityId,
         component: &str,
-    ) {
-        let component_ptr = entity.get_component_index(component);
-        self.drop_component::<T>(component_ptr);
-        entity.remove_component_index(component);
+    ) -> Result<&mut T, ComponentError> {
+        let component_id = self.get_entity_component_index(entity_id, component)?;
+        self.get_component_mut::<T>(component_id)
     }
 
-    pub fn get_entity_component_index(&self, entity_id: usize, component_name: &str) -> usize {
-        let entity = self.get_entity(entity_id);
-        entity.get_component_index(component_name)
+    pub fn drop_entity_component<T: Any>(
+        &mut self,
+        entity_id: EntityId,
+        component: &str,
+    ) -> Result<(), ComponentError> {
+        let component_id = self.get_entity_component_index(entity_id, component)?;
+        self.drop_component::<T>(component_id)?;
+        if let Some(entity) = self.get_entity_mut(entity_id) {
+            entity.remove_component_index(component);
+        }
+        Ok(())
+    }
+
+    pub fn get_entity_component_index(
+        &self,
+        entity_id: EntityId,
+        component_name: &str,
+    ) -> Result<ComponentId, ComponentError> {
+        let entity = self
+            .get_entity(entity_id)
+            .ok_or(ComponentError::EntityNotFound(entity_id))?;
+        entity
+            .get_component_index(component_name)
+            .ok_or_else(|| ComponentError::Missing(component_name.to_string()))
     }
 
     pub fn find_camera_entity(&self) -> Option<&Entity> {
-        for entity in &self.entities {
-            if entity.has_component("camera") {
-                return Some(entity);
-            }
-        }
-        None
+        self.iter_entities()
+            .map(|(_, entity)| entity)
+            .find(|entity| entity.has_component("camera"))
     }
 
-    pub fn get_default_camera(&self) -> Option<&mut Box<dyn CameraTrait>> {
-        if self.default_camera.is_none() {
-            return None;
-        }
-        let camera_entity: &Entity = self.get_entity(self.default_camera.unwrap());
+    pub fn get_default_camera(&self) -> Option<&dyn CameraTrait> {
+        self.get_entity_component::<Box<dyn CameraTrait>>(self.default_camera?, "camera")
+            .ok()
+            .map(|camera| camera.as_ref())
+    }
 
-        Some(self.get_entity_component_mut::<Box<dyn CameraTrait>>(camera_entity, "camera"))
+    pub fn get_default_camera_mut(&mut self) -> Option<&mut Box<dyn CameraTrait>> {
+        self.get_entity_component_mut::<Box<dyn CameraTrait>>(self.default_camera?, "camera")
+            .ok()
     }
 
     pub fn add_default_camera(&mut self, renderer: &renderer::Renderer) {
@@ -214,18 +326,25 @@ impl Scene {
     }
 
     // need default light for Blinn-Phong shading
-    pub fn add_default_directional_light(&mut self, renderer: &renderer::Renderer) {
+    pub fn add_default_directional_light(&mut self) {
         let entity_id = self.add_entity(Entity::new());
-        let light = DirectionalLight::new(
-            &renderer,
-            DEFAULT_LIGHT_BIND_INDEX,
-            DirectionalLightUniform {
-                intensity: 1.,
-                direction: [1., 1., -1.],
-                color: [1., 1., 0.8, 1.0],
-            },
-        );
+        let light = DirectionalLight::new(DirectionalLightUniform {
+            intensity: 1.,
+            direction: [1., 1., -1.],
+            color: [1., 1., 0.8],
+        });
         self.set_entity_component::<Box<dyn LightTrait>>(entity_id, Box::new(light), "light");
         self.default_light = Some(entity_id);
     }
+
+    // the side away from the default light isn't black
+    pub fn add_default_ambient_light(&mut self) {
+        let entity_id = self.add_entity(Entity::new());
+        let light = AmbientLight::new(AmbientLightUniform {
+            color: [1., 1., 1.],
+            intensity: 0.1,
+        });
+        self.set_entity_component::<Box<dyn LightTrait>>(entity_id, Box::new(light), "light");
+        self.default_ambient_light = Some(entity_id);
+    }
 }
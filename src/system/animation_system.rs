@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+
+use glam::{Quat, Vec3};
+
+use crate::{
+    component_store::ComponentId,
+    components::{
+        animation::{AnimationPlayer, AnimationProperty},
+        morph::MorphWeights,
+        transform::Transform,
+    },
+    entity::EntityId,
+    scene::Scene,
+};
+
+use super::system::{FrameContext, Stage, System};
+
+/// advance every AnimationPlayer and write the sampled values into the targets' local transform,
+/// runs before TransformSystem computes the global matrices
+pub struct AnimationSystem {}
+
+impl System for AnimationSystem {
+    fn update(&mut self, ctx: &mut FrameContext, scene: &mut Scene) {
+        Self::animate(scene, ctx.delta_time);
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::Update
+    }
+}
+
+impl AnimationSystem {
+    pub fn animate(scene: &mut Scene, delta_time: f32) {
+        if scene.component_count::<AnimationPlayer>() == 0 {
+            return;
+        }
+        // a player may be shared by several entities, advance it once
+        let players: HashSet<ComponentId> = scene
+            .iter_entities()
+            .filter_map(|(_, entity)| entity.get_component_id::<AnimationPlayer>())
+            .collect();
+        for player_id in players {
+            let Ok(player) = scene.get_component_mut::<AnimationPlayer>(player_id) else {
+                continue;
+            };
+            if !player.is_playing() {
+                continue;
+            }
+            player.advance(delta_time);
+            for (target, property, value) in player.sample() {
+                Self::apply(scene, target, property, &value);
+            }
+        }
+    }
+
+    fn apply(scene: &mut Scene, target: EntityId, property: AnimationProperty, value: &[f32]) {
+        if property == AnimationProperty::Weights {
+            Self::apply_weights(scene, target, value);
+            return;
+        }
+        let Some(transform_id) = scene
+            .get_entity(target)
+            .and_then(|entity| entity.get_component_id::<Transform>())
+        else {
+            return;
+        };
+        let Ok(transform) = scene.get_component_mut::<Transform>(transform_id) else {
+            return;
+        };
+        match property {
+            AnimationProperty::Translation if value.len() >= 3 => {
+                transform.position = Vec3::from_slice(value)
+            }
+            AnimationProperty::Rotation if value.len() >= 4 => {
+                transform.rotation = Quat::from_slice(value)
+            }
+            AnimationProperty::Scale if value.len() >= 3 => {
+                transform.scale = Vec3::from_slice(value)
+            }
+            _ => return,
+        }
+        transform.update_matrix();
+    }
+
+    // a gltf node's weights drive the morph targets of all its primitives
+    fn apply_weights(scene: &mut Scene, target: EntityId, value: &[f32]) {
+        let Some(entity) = scene.get_entity(target) else {
+            return;
+        };
+        let weight_ids: Vec<ComponentId> = std::iter::once(entity)
+            .chain(
+                entity
+                    .children
+                    .iter()
+                    .filter_map(|child| scene.get_entity(*child)),
+            )
+            .filter_map(|entity| entity.get_component_id::<MorphWeights>())
+            .collect();
+        for weight_id in weight_ids {
+            if let Ok(weights) = scene.get_component_mut::<MorphWeights>(weight_id) {
+                weights.weights = value.to_vec();
+            }
+        }
+    }
+}
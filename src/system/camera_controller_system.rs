@@ -0,0 +1,43 @@
+use winit::event::WindowEvent;
+
+use crate::{components::controller::map::MapController, scene::Scene};
+
+use super::system::{FrameContext, Stage, System};
+
+/// drive the scene's default camera with a MapController, events come from `Renderer::process_event`
+pub struct CameraControllerSystem {
+    pub controller: MapController,
+}
+
+impl CameraControllerSystem {
+    pub fn new(controller: MapController) -> CameraControllerSystem {
+        CameraControllerSystem { controller }
+    }
+}
+
+impl Default for CameraControllerSystem {
+    fn default() -> Self {
+        CameraControllerSystem::new(MapController::default())
+    }
+}
+
+impl System for CameraControllerSystem {
+    fn update(&mut self, ctx: &mut FrameContext, scene: &mut Scene) {
+        // keep the controller in sync with the window size
+        self.controller.config.width = ctx.renderer.viewport.width;
+        self.controller.config.height = ctx.renderer.viewport.height;
+        self.controller.config.scale_factor = ctx.renderer.viewport.scale_factor;
+        if let Some(camera) = scene.get_default_camera_mut() {
+            self.controller.update(camera);
+            camera.update_bind_group(ctx.renderer);
+        }
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::Update
+    }
+
+    fn on_event(&mut self, event: &WindowEvent) {
+        self.controller.process_events(event);
+    }
+}
@@ -1,145 +1,310 @@
-use wgpu::{CommandEncoder, StoreOp, VertexBufferLayout};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use glam::{Mat4, Quat, Vec3};
+use wgpu::{util::DeviceExt, CommandEncoder, StoreOp, VertexBufferLayout};
 
 use crate::{
     components::{
-        instance::Instance, lights::light::LightTrait, material::MaterialTrait, mesh::Mesh,
+        instance::Instance,
+        lights::light::{LightData, LightTrait, LightsUniform, LIGHTS_BIND_INDEX, MAX_LIGHTS},
+        material::MaterialTrait,
+        mesh::Mesh,
+        morph::MorphWeights,
         perspective_camera::CameraTrait,
+        shadow::{ShadowMapData, ShadowsUniform, MAX_SHADOW_MAPS},
+        skin::{Skin, SKIN_BIND_GROUP_INDEX},
+        transform::{Transform, TRANSFORM_BIND_GROUP_INDEX},
     },
-    entity::Entity,
+    component_store::ComponentId,
     renderer::Renderer,
     scene::Scene,
 };
 
-use super::system::System;
+use super::system::{FrameContext, Stage, System};
 
 pub struct EnvBindGroup {
     pub bind_group: wgpu::BindGroup,
-    pub bind_group_layout: wgpu::BindGroupLayout,
+    // the same bindings with the shadow maps sampled, for entities with receive_shadow
+    pub shadow_bind_group: wgpu::BindGroup,
     pub index: u32,
 }
 
+// array_stride and attributes of an instance buffer
+type InstanceAttributes = (wgpu::BufferAddress, Vec<wgpu::VertexAttribute>);
+
+/// the buffers and layouts MeshRender reuses every frame, owned by the renderer
+pub struct MeshRenderResources {
+    // used by entities without transform component
+    pub default_transform: Transform,
+    pub transform_bind_group_layout: wgpu::BindGroupLayout,
+    // keyed by the bind index of the default camera, None without camera
+    pub env_bind_group_layout: (Option<u32>, wgpu::BindGroupLayout),
+    lights_buffer: wgpu::Buffer,
+    shadows_buffer: wgpu::Buffer,
+    // receivers off see no shadow maps
+    no_shadows_buffer: wgpu::Buffer,
+    lights_limit: LimitReport,
+    shadow_maps_limit: LimitReport,
+}
+
+/// logs a limit the scene goes over once instead of every frame,
+/// and again after the scene was back under it
+#[derive(Default)]
+pub struct LimitReport {
+    reported: AtomicBool,
+}
+
+impl LimitReport {
+    /// true if the error was logged
+    pub fn report(&self, err: Option<anyhow::Error>) -> bool {
+        let Some(err) = err else {
+            self.reported.store(false, Ordering::Relaxed);
+            return false;
+        };
+        if self.reported.swap(true, Ordering::Relaxed) {
+            return false;
+        }
+        log::error!("{}, the others are ignored", err);
+        true
+    }
+}
+
+impl MeshRenderResources {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let uniform_buffer = |label: &str, contents: &[u8]| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            })
+        };
+        let lights_buffer = uniform_buffer(
+            "Lights Buffer",
+            bytemuck::cast_slice(&[LightsUniform::new(&[]).unwrap()]),
+        );
+        let no_shadows = ShadowsUniform::new(&[], 1);
+        let shadows_buffer = uniform_buffer("Shadows Buffer", bytemuck::cast_slice(&[no_shadows]));
+        let no_shadows_buffer =
+            uniform_buffer("No Shadows Buffer", bytemuck::cast_slice(&[no_shadows]));
+        Self {
+            default_transform: Transform::new_with_device(
+                device,
+                Vec3::ZERO,
+                Quat::IDENTITY,
+                Vec3::ONE,
+            ),
+            transform_bind_group_layout: Transform::create_bind_group_layout(device),
+            env_bind_group_layout: (Some(0), Self::create_env_bind_group_layout(device, Some(0))),
+            lights_buffer,
+            shadows_buffer,
+            no_shadows_buffer,
+            lights_limit: LimitReport::default(),
+            shadow_maps_limit: LimitReport::default(),
+        }
+    }
+
+    /// rebuild the env layout when the default camera's bind index changes,
+    /// the renderer calls it before the systems run
+    pub fn prepare(&mut self, device: &wgpu::Device, scene: &Scene) {
+        let camera_index = scene
+            .get_default_camera()
+            .map(|camera| camera.get_bind_index());
+        if camera_index != self.env_bind_group_layout.0 {
+            self.env_bind_group_layout = (
+                camera_index,
+                Self::create_env_bind_group_layout(device, camera_index),
+            );
+        }
+    }
+
+    fn create_env_bind_group_layout(
+        device: &wgpu::Device,
+        camera_index: Option<u32>,
+    ) -> wgpu::BindGroupLayout {
+        let uniform = |binding: u32, visibility: wgpu::ShaderStages| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let mut entries = vec![];
+        if let Some(camera_index) = camera_index {
+            entries.push(uniform(
+                camera_index,
+                wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+            ));
+        }
+        entries.extend([
+            uniform(LIGHTS_BIND_INDEX, wgpu::ShaderStages::FRAGMENT),
+            uniform(SHADOWS_BIND_INDEX, wgpu::ShaderStages::FRAGMENT),
+            wgpu::BindGroupLayoutEntry {
+                binding: SHADOWS_BIND_INDEX + 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Depth,
+                    view_dimension: wgpu::TextureViewDimension::D2Array,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: SHADOWS_BIND_INDEX + 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                count: None,
+            },
+        ]);
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Camera Bind Group Layout"),
+            entries: &entries,
+        })
+    }
+}
+
 pub struct RenderOptions<'a> {
-    entities: &'a Vec<Entity>,
-    renderer: &'a Renderer,
     scene: &'a Scene,
     render_pass: wgpu::RenderPass<'a>,
-    env_pipeline_layouts: &'a Vec<&'a wgpu::BindGroupLayout>,
     env_bind_groups: &'a Vec<EnvBindGroup>,
+    // used by entities without transform component
+    default_transform: &'a Transform,
 }
 
 pub struct MeshRender {}
 impl System for MeshRender {
-    fn update(&self, renderer: &Renderer, scene: &Scene) {
-        let frame = renderer
-            .surface
-            .get_current_texture()
-            .expect("Failed to acquire next swap chain texture");
-        let view = frame
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
-        let mut encoder = renderer
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
+    fn update(&mut self, ctx: &mut FrameContext, scene: &mut Scene) {
+        Self::render(ctx.encoder, ctx.view, scene, ctx.renderer);
+    }
 
-        Self::render(&mut encoder, &view, scene, renderer);
-        renderer.queue.submit(Some(encoder.finish()));
-        frame.present();
+    fn stage(&self) -> Stage {
+        Stage::Render
     }
 }
 const ENV_BIND_GROUP_INDEX: u32 = 1;
+/// first binding of the Shadows chunk in the env bind group, the maps and the sampler follow it
+pub const SHADOWS_BIND_INDEX: u32 = 2;
 
 impl MeshRender {
-    fn collect_light_bindings(
-        scene: &Scene,
-    ) -> (Vec<wgpu::BindGroupLayoutEntry>, Vec<wgpu::BindGroupEntry>) {
-        let mut bind_group_layout_entries: Vec<wgpu::BindGroupLayoutEntry> = vec![];
-        let mut bind_group_entries: Vec<wgpu::BindGroupEntry> = vec![];
-        let mut seen_bindings = std::collections::HashSet::new(); // 用于去重
-
-        scene.entities.iter().for_each(|entity| {
-            // check if entity has light component
-            if !entity.has_component("light") {
-                return;
-            }
-
-            let light = scene.get_entity_component::<Box<dyn LightTrait>>(&entity, "light");
-            let light_binding_index = light.get_bind_index();
-
-            // if seen, skip
-            if seen_bindings.contains(&light_binding_index) {
-                return;
-            }
-
-            // add to seen
-            seen_bindings.insert(light_binding_index);
-
-            // add BindGroupLayoutEntry
-            bind_group_layout_entries.push(wgpu::BindGroupLayoutEntry {
-                binding: light_binding_index,
-                visibility: wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            });
+    /// pack every light of the scene into one uniform, the lights past MAX_LIGHTS are dropped
+    /// and the error tells how many there were
+    pub fn collect_lights(scene: &Scene) -> (LightsUniform, Option<anyhow::Error>) {
+        let lights: Vec<LightData> = scene
+            .query::<&Box<dyn LightTrait>>()
+            .map(|(_, light)| light.get_light_data())
+            .collect();
+        match LightsUniform::new(&lights) {
+            Ok(uniform) => (uniform, None),
+            Err(err) => (LightsUniform::new(&lights[..MAX_LIGHTS]).unwrap(), Some(err)),
+        }
+    }
 
-            // add BindGroupEntry
-            bind_group_entries.push(wgpu::BindGroupEntry {
-                binding: light_binding_index,
-                resource: light.get_buffer().as_entire_binding(),
+    /// the shadow maps of every shadow casting light, light_index follows collect_lights.
+    /// the maps past MAX_SHADOW_MAPS are dropped and the error tells how many there were
+    pub fn collect_shadow_maps(scene: &Scene) -> (Vec<ShadowMapData>, Option<anyhow::Error>) {
+        let (camera_view, camera_projection) = scene
+            .get_default_camera()
+            .map_or((Mat4::IDENTITY, Mat4::IDENTITY), |camera| {
+                (camera.get_view_matrix(), camera.get_projection_matrix())
             });
-        });
-
-        (bind_group_layout_entries, bind_group_entries)
+        let mut maps = vec![];
+        let lights = scene.query::<&Box<dyn LightTrait>>().take(MAX_LIGHTS);
+        for (light_index, (_, light)) in lights.enumerate() {
+            let Some(config) = light.get_shadow_config() else {
+                continue;
+            };
+            for view in light.get_shadow_views(camera_view, camera_projection) {
+                maps.push(ShadowMapData::new(light_index as u32, &view, config));
+            }
+        }
+        if maps.len() <= MAX_SHADOW_MAPS {
+            return (maps, None);
+        }
+        let err = anyhow::anyhow!(
+            "the scene has {} shadow maps, at most {} are supported",
+            maps.len(),
+            MAX_SHADOW_MAPS
+        );
+        maps.truncate(MAX_SHADOW_MAPS);
+        (maps, Some(err))
     }
 
-    fn get_env_bind_groups(scene: &Scene, renderer: &Renderer) -> Vec<EnvBindGroup> {
+    fn get_env_bind_groups(
+        scene: &Scene,
+        renderer: &Renderer,
+        shadow_maps: &[ShadowMapData],
+    ) -> Vec<EnvBindGroup> {
         let device = &renderer.device;
+        let resources = &renderer.mesh_render_resources;
         let mut env_bind_groups: Vec<EnvBindGroup> = Vec::new();
-        let mut bind_group_layout_entries: Vec<wgpu::BindGroupLayoutEntry> = vec![];
         let mut bind_group_entries: Vec<wgpu::BindGroupEntry> = vec![];
 
         // join all bind groups to 1 bind group
         // add camera bind group
-        let camera: Option<&mut Box<dyn CameraTrait>> = scene.get_default_camera();
+        let camera: Option<&dyn CameraTrait> = scene.get_default_camera();
         if let Some(camera_val) = camera {
-            bind_group_layout_entries.push(wgpu::BindGroupLayoutEntry {
-                binding: camera_val.get_bind_index(),
-                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            });
             bind_group_entries.push(wgpu::BindGroupEntry {
                 binding: camera_val.get_bind_index(),
                 resource: camera_val.get_buffer().as_entire_binding(),
             });
         }
 
-        let (light_layout_entries, light_bind_entries) = Self::collect_light_bindings(scene);
-        bind_group_layout_entries.extend(light_layout_entries);
-        bind_group_entries.extend(light_bind_entries);
+        let (lights, err) = Self::collect_lights(scene);
+        resources.lights_limit.report(err);
+        renderer
+            .queue
+            .write_buffer(&resources.lights_buffer, 0, bytemuck::cast_slice(&[lights]));
+        bind_group_entries.push(wgpu::BindGroupEntry {
+            binding: LIGHTS_BIND_INDEX,
+            resource: resources.lights_buffer.as_entire_binding(),
+        });
 
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("Camera Bind Group Layout"),
-            entries: bind_group_layout_entries.as_slice(),
+        // the Shadows chunk, 2 is the shadows uniform, 3 the maps and 4 the comparison sampler
+        renderer.queue.write_buffer(
+            &resources.shadows_buffer,
+            0,
+            bytemuck::cast_slice(&[ShadowsUniform::new(
+                shadow_maps,
+                renderer.shadow_maps.size,
+            )]),
+        );
+        bind_group_entries.extend([
+            wgpu::BindGroupEntry {
+                binding: SHADOWS_BIND_INDEX + 1,
+                resource: wgpu::BindingResource::TextureView(&renderer.shadow_maps.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: SHADOWS_BIND_INDEX + 2,
+                resource: wgpu::BindingResource::Sampler(&renderer.shadow_maps.sampler),
+            },
+        ]);
+        let mut shadow_bind_group_entries = bind_group_entries.clone();
+        shadow_bind_group_entries.push(wgpu::BindGroupEntry {
+            binding: SHADOWS_BIND_INDEX,
+            resource: resources.shadows_buffer.as_entire_binding(),
+        });
+        bind_group_entries.push(wgpu::BindGroupEntry {
+            binding: SHADOWS_BIND_INDEX,
+            resource: resources.no_shadows_buffer.as_entire_binding(),
         });
+
+        let bind_group_layout = &resources.env_bind_group_layout.1;
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Camera Bind Group"),
-            layout: &bind_group_layout,
-            entries: &bind_group_entries.as_slice(),
+            layout: bind_group_layout,
+            entries: bind_group_entries.as_slice(),
+        });
+        let shadow_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Camera Shadow Bind Group"),
+            layout: bind_group_layout,
+            entries: shadow_bind_group_entries.as_slice(),
         });
         // env bind group use group 1, user's bind use group 0
         env_bind_groups.push(EnvBindGroup {
-            bind_group: bind_group,
-            bind_group_layout: bind_group_layout,
+            bind_group,
+            shadow_bind_group,
             index: ENV_BIND_GROUP_INDEX,
         });
         env_bind_groups
@@ -148,14 +313,38 @@ impl MeshRender {
     pub fn render(
         encoder: &mut CommandEncoder,
         view: &wgpu::TextureView,
-        scene: &Scene,
+        scene: &mut Scene,
         renderer: &Renderer,
     ) {
-        let env_bind_groups = Self::get_env_bind_groups(scene, renderer);
+        let resources = &renderer.mesh_render_resources;
+        let default_transform = &resources.default_transform;
+        // depth of the shadow casters first, the main pass samples it
+        let (shadow_maps, err) = Self::collect_shadow_maps(scene);
+        resources.shadow_maps_limit.report(err);
+        let view_projections: Vec<Mat4> = shadow_maps
+            .iter()
+            .map(|shadow_map| Mat4::from_cols_array_2d(&shadow_map.view_projection))
+            .collect();
+        renderer.shadow_maps.render(
+            encoder,
+            &renderer.queue,
+            scene,
+            &view_projections,
+            default_transform,
+        );
+        let env_bind_groups = Self::get_env_bind_groups(scene, renderer, &shadow_maps);
+        let env_pipeline_layouts: Vec<&wgpu::BindGroupLayout> = vec![
+            &resources.env_bind_group_layout.1,
+            &resources.transform_bind_group_layout,
+        ];
+
+        // pipelines need &mut material, create them before the render pass borrows the scene
+        Self::prepare_pipelines(scene, renderer, &env_pipeline_layouts);
+        let scene: &Scene = scene;
         let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Render Pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
+                view,
                 resolve_target: None,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(scene.background_color),
@@ -173,55 +362,89 @@ impl MeshRender {
             timestamp_writes: None,
             occlusion_query_set: None,
         });
-        let env_pipeline_layouts: &Vec<&wgpu::BindGroupLayout> = &env_bind_groups
-            .iter()
-            .map(|env_bind_group| &env_bind_group.bind_group_layout)
-            .collect();
-
-        let entities = &scene.entities;
 
         Self::iter_entities(RenderOptions {
-            entities,
-            renderer,
             scene,
             render_pass,
-            env_pipeline_layouts,
             env_bind_groups: &env_bind_groups,
+            default_transform,
         });
     }
 
+    fn prepare_pipelines(
+        scene: &mut Scene,
+        renderer: &Renderer,
+        env_pipeline_layouts: &Vec<&wgpu::BindGroupLayout>,
+    ) {
+        // instance attributes are copied out, the layouts borrow them once the scene is free
+        let pending: Vec<(
+            ComponentId,
+            VertexBufferLayout<'static>,
+            Option<InstanceAttributes>,
+        )> = scene
+            .query::<(&Mesh, Option<&Instance>)>()
+            .filter_map(|(entity_id, (mesh, instance))| {
+                let material_id = scene
+                    .get_entity(entity_id)?
+                    .get_component_id::<Box<dyn MaterialTrait>>()?;
+                let instance = instance.map(|instance| {
+                    let layout = instance.vertex_buffer_layout();
+                    (layout.array_stride, layout.attributes.to_vec())
+                });
+                Some((material_id, mesh.vertex_buffer_layout.clone(), instance))
+            })
+            .collect();
+        for (material_id, mesh_layout, instance) in &pending {
+            let mut env_vertex_buffer_layout = vec![mesh_layout.clone()];
+            if let Some((array_stride, attributes)) = instance {
+                env_vertex_buffer_layout.push(VertexBufferLayout {
+                    array_stride: *array_stride,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes,
+                });
+            }
+            if let Ok(material) = scene.get_component_mut::<Box<dyn MaterialTrait>>(*material_id) {
+                material.get_render_pipeline(
+                    renderer,
+                    env_pipeline_layouts,
+                    env_vertex_buffer_layout,
+                );
+            }
+        }
+    }
+
     pub fn iter_entities(option: RenderOptions) {
         let RenderOptions {
-            entities,
-            renderer,
             scene,
             mut render_pass,
-            env_pipeline_layouts,
             env_bind_groups,
+            default_transform,
         } = option;
-        for entity in entities {
-            if !entity.has_component("mesh") || !entity.has_component("material") {
+        let renderables = scene.query::<(
+            &Mesh,
+            &Box<dyn MaterialTrait>,
+            Option<&Instance>,
+            Option<&Transform>,
+            Option<&Skin>,
+            Option<&MorphWeights>,
+        )>();
+        for (entity_id, (mesh, material, instance, transform, skin, morph_weights)) in renderables {
+            let Some(pipeline) = material.get_pipeline() else {
                 continue;
-            }
-            let mut env_vertex_buffer_layout: Vec<VertexBufferLayout> = Vec::new();
+            };
+            let receive_shadow = scene
+                .get_entity(entity_id)
+                .is_some_and(|entity| entity.receive_shadow);
             let mut instance_len = 1;
-            let mesh = scene.get_entity_component::<Mesh>(&entity, "mesh");
-            let material =
-                scene.get_entity_component::<Box<dyn MaterialTrait>>(&entity, "material");
-            let mateiral_mut =
-                scene.get_entity_component_mut::<Box<dyn MaterialTrait>>(&entity, "material");
 
             // bind mesh
             render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-            env_vertex_buffer_layout.push(mesh.get_buffer_layout());
 
             // bind instance buffer
-            if entity.has_component("instance") {
-                let instance = scene.get_entity_component::<Instance>(&entity, "instance");
+            if let Some(instance) = instance {
                 render_pass
                     .set_vertex_buffer(Instance::get_buffer_index(), instance.buffer.slice(..));
-                env_vertex_buffer_layout.push(Instance::get_buffer_layout());
-                instance_len = instance.data.len() as u32
+                instance_len = instance.count()
             }
 
             // set index buffer
@@ -229,16 +452,28 @@ impl MeshRender {
 
             // bind env bind group
             for env_bind_group in env_bind_groups {
-                render_pass.set_bind_group(env_bind_group.index, &env_bind_group.bind_group, &[]);
+                let bind_group = if receive_shadow {
+                    &env_bind_group.shadow_bind_group
+                } else {
+                    &env_bind_group.bind_group
+                };
+                render_pass.set_bind_group(env_bind_group.index, bind_group, &[]);
+            }
+
+            // bind model matrix
+            let transform = transform.unwrap_or(default_transform);
+            render_pass.set_bind_group(TRANSFORM_BIND_GROUP_INDEX, &transform.bind_group, &[]);
+
+            // bind joint matrices of skinned mesh, morph weights carry them when both are used
+            if let Some(morph_weights) = morph_weights {
+                render_pass.set_bind_group(SKIN_BIND_GROUP_INDEX, &morph_weights.bind_group, &[]);
+            } else if let Some(skin) = skin {
+                render_pass.set_bind_group(SKIN_BIND_GROUP_INDEX, &skin.bind_group, &[]);
             }
 
             // set pipeline and bind group layout
             render_pass.set_bind_group(0, material.get_bind_group(), &[]);
-            render_pass.set_pipeline(mateiral_mut.get_render_pipeline(
-                renderer,
-                env_pipeline_layouts,
-                env_vertex_buffer_layout,
-            ));
+            render_pass.set_pipeline(pipeline);
 
             render_pass.draw_indexed(0..mesh.num_indices, 0, 0..instance_len);
         }
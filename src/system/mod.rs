@@ -1,2 +1,8 @@
+pub mod animation_system;
+pub mod camera_controller_system;
 pub mod mesh_render;
+pub mod morph_system;
+pub mod schedule;
+pub mod skin_system;
 pub mod system;
+pub mod transform_system;
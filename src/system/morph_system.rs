@@ -0,0 +1,24 @@
+use crate::{components::morph::MorphWeights, renderer::Renderer, scene::Scene};
+
+use super::system::{FrameContext, Stage, System};
+
+/// upload the weights of every MorphWeights, so edits from code or animation show up the same frame
+pub struct MorphSystem {}
+
+impl System for MorphSystem {
+    fn update(&mut self, ctx: &mut FrameContext, scene: &mut Scene) {
+        Self::update_weights(scene, ctx.renderer);
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::PostUpdate
+    }
+}
+
+impl MorphSystem {
+    pub fn update_weights(scene: &Scene, renderer: &Renderer) {
+        for (_, weights) in scene.query::<&MorphWeights>() {
+            weights.update_buffer(renderer);
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use winit::event::WindowEvent;
+
+use crate::scene::Scene;
+
+use super::system::{FrameContext, Stage, System};
+
+struct ScheduledSystem {
+    name: String,
+    stage: Stage,
+    system: Box<dyn System>,
+}
+
+/// ordered system list, sorted by stage and then by insertion order
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<ScheduledSystem>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add a system at the end of its stage, a system with the same name is replaced and returned
+    pub fn add_system(
+        &mut self,
+        stage: Stage,
+        name: String,
+        system: Box<dyn System>,
+    ) -> Option<Box<dyn System>> {
+        let old = self.remove_system(&name);
+        let position = self
+            .systems
+            .iter()
+            .position(|scheduled| scheduled.stage > stage)
+            .unwrap_or(self.systems.len());
+        self.systems.insert(
+            position,
+            ScheduledSystem {
+                name,
+                stage,
+                system,
+            },
+        );
+        old
+    }
+
+    pub fn remove_system(&mut self, name: &str) -> Option<Box<dyn System>> {
+        let index = self
+            .systems
+            .iter()
+            .position(|scheduled| scheduled.name == name)?;
+        Some(self.systems.remove(index).system)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.systems.iter().any(|scheduled| scheduled.name == name)
+    }
+
+    /// system names in run order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.systems.iter().map(|scheduled| scheduled.name.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.systems.is_empty()
+    }
+
+    pub fn run(&mut self, ctx: &mut FrameContext, scene: &mut Scene) {
+        for scheduled in &mut self.systems {
+            scheduled.system.update(ctx, scene);
+        }
+    }
+
+    pub fn on_event(&mut self, event: &WindowEvent) {
+        for scheduled in &mut self.systems {
+            scheduled.system.on_event(event);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use std::collections::HashSet;
+
+use glam::Mat4;
+
+use crate::{
+    components::{skin::Skin, transform::Transform},
+    renderer::Renderer,
+    scene::Scene,
+};
+
+use super::system::{FrameContext, Stage, System};
+
+/// upload joint matrices of every Skin, needs the global matrices so it runs after TransformSystem
+pub struct SkinSystem {}
+
+impl System for SkinSystem {
+    fn update(&mut self, ctx: &mut FrameContext, scene: &mut Scene) {
+        Self::update_skins(scene, ctx.renderer);
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::PostUpdate
+    }
+}
+
+impl SkinSystem {
+    pub fn update_skins(scene: &Scene, renderer: &Renderer) {
+        let mut updated = HashSet::new();
+        for (entity_id, (skin, transform)) in scene.query::<(&Skin, Option<&Transform>)>() {
+            // primitives of one node share their skin
+            let skin_id = scene
+                .get_entity(entity_id)
+                .and_then(|entity| entity.get_component_id::<Skin>());
+            if !updated.insert(skin_id) {
+                continue;
+            }
+            let mesh_global_matrix = transform.map_or(Mat4::IDENTITY, |t| t.global_matrix);
+            let joint_matrices = skin.joint_matrices(scene, mesh_global_matrix);
+            skin.update_buffer(renderer, &joint_matrices);
+        }
+    }
+}
@@ -1,6 +1,35 @@
+use winit::event::WindowEvent;
+
 use crate::{renderer::Renderer, scene::Scene};
 
+/// systems run stage by stage, systems in the same stage run in the order they were added
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    PreUpdate,
+    // camera controllers and user logic
+    Update,
+    // transform propagation
+    PostUpdate,
+    Render,
+}
+
+/// shared by all systems of one frame, their commands are submitted together
+pub struct FrameContext<'a> {
+    pub renderer: &'a Renderer,
+    pub encoder: &'a mut wgpu::CommandEncoder,
+    pub view: &'a wgpu::TextureView,
+    // seconds since last frame, 0 for the first frame
+    pub delta_time: f32,
+    pub frame_index: u64,
+}
+
 // ecs's system module
 pub trait System {
-    fn update(&self, renderer: &Renderer, scene: &Scene);
+    fn update(&mut self, ctx: &mut FrameContext, scene: &mut Scene);
+
+    fn stage(&self) -> Stage {
+        Stage::Update
+    }
+
+    fn on_event(&mut self, _event: &WindowEvent) {}
 }
@@ -0,0 +1,57 @@
+use glam::Mat4;
+
+use crate::{
+    components::transform::Transform, entity::EntityId, renderer::Renderer, scene::Scene,
+};
+
+use super::system::{FrameContext, Stage, System};
+
+/// walk the scene graph from root entities, multiply parent global matrix into children and upload it.
+/// entity without transform passes its parent's global matrix to its children
+pub struct TransformSystem {}
+
+impl System for TransformSystem {
+    fn update(&mut self, ctx: &mut FrameContext, scene: &mut Scene) {
+        Self::update_transforms(scene, ctx.renderer);
+    }
+
+    fn stage(&self) -> Stage {
+        Stage::PostUpdate
+    }
+}
+
+impl TransformSystem {
+    pub fn update_transforms(scene: &mut Scene, renderer: &Renderer) {
+        let roots: Vec<EntityId> = scene
+            .iter_entities()
+            .filter(|(_, entity)| !entity.is_child())
+            .map(|(entity_id, _)| entity_id)
+            .collect();
+        for entity_id in roots {
+            Self::update_entity(scene, renderer, entity_id, Mat4::IDENTITY);
+        }
+    }
+
+    fn update_entity(
+        scene: &mut Scene,
+        renderer: &Renderer,
+        entity_id: EntityId,
+        parent_matrix: Mat4,
+    ) {
+        let Some(entity) = scene.get_entity(entity_id) else {
+            return;
+        };
+        let children = entity.children.clone();
+        let transform_id = entity.get_component_id::<Transform>();
+        let mut global_matrix = parent_matrix;
+        if let Some(Ok(transform)) =
+            transform_id.map(|id| scene.get_component_mut::<Transform>(id))
+        {
+            global_matrix = transform.update_global_matrix(parent_matrix);
+            transform.update_bind_group(renderer);
+        }
+        for child_id in children {
+            Self::update_entity(scene, renderer, child_id, global_matrix);
+        }
+    }
+}
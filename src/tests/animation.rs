@@ -0,0 +1,183 @@
+use glam::{Mat4, Quat, Vec3};
+
+use crate::{
+    components::{
+        animation::{
+            AnimationChannel, AnimationClip, AnimationPlayer, AnimationProperty, Interpolation,
+        },
+        morph::MorphWeights,
+        skin::Skin,
+        transform::Transform,
+    },
+    scene::Scene,
+    system::{
+        animation_system::AnimationSystem, mesh_render::MeshRender,
+        transform_system::TransformSystem,
+    },
+    utils::gltf::load_gltf,
+};
+
+use super::{
+    gltf::{find_child, make_glb, triangle_bin},
+    headless::make_headless_mini_gpu,
+};
+
+fn translation_channel(interpolation: Interpolation, values: Vec<f32>) -> AnimationChannel {
+    AnimationChannel {
+        target: Scene::new().add_default_entity(),
+        property: AnimationProperty::Translation,
+        interpolation,
+        times: vec![0., 1.],
+        values,
+    }
+}
+
+#[test]
+fn test_animation_channel_sample() {
+    let linear = translation_channel(Interpolation::Linear, vec![0., 0., 0., 2., 4., 6.]);
+    assert_eq!(linear.sample(0.5), vec![1., 2., 3.]);
+    // clamped outside the keyframes
+    assert_eq!(linear.sample(2.), vec![2., 4., 6.]);
+
+    let step = translation_channel(Interpolation::Step, vec![0., 0., 0., 2., 4., 6.]);
+    assert_eq!(step.sample(0.9), vec![0., 0., 0.]);
+
+    // in tangent, value, out tangent per key, flat tangents ease in and out
+    let cubic = translation_channel(
+        Interpolation::CubicSpline,
+        vec![
+            0., 0., 0., 0., 0., 0., 0., 0., 0., 0., 0., 0., 2., 4., 6., 0., 0., 0.,
+        ],
+    );
+    assert_eq!(cubic.stride(), 3);
+    assert_eq!(cubic.sample(0.5), vec![1., 2., 3.]);
+    assert!(cubic.sample(0.25)[0] < 0.5);
+
+    let quarter_turn = Quat::from_rotation_y(std::f32::consts::FRAC_PI_2);
+    let rotation = AnimationChannel {
+        property: AnimationProperty::Rotation,
+        values: [Quat::IDENTITY.to_array(), quarter_turn.to_array()].concat(),
+        ..translation_channel(Interpolation::Linear, vec![])
+    };
+    let sampled = Quat::from_slice(&rotation.sample(0.5));
+    assert!(sampled.abs_diff_eq(Quat::from_rotation_y(std::f32::consts::FRAC_PI_4), 1e-5));
+}
+
+#[test]
+fn test_animation_player_cross_fade() {
+    let clip = |name: &str, x: f32| AnimationClip {
+        name: name.to_string(),
+        channels: vec![translation_channel(
+            Interpolation::Linear,
+            vec![x, 0., 0., x, 0., 0.],
+        )],
+    };
+    let mut player = AnimationPlayer::new(vec![clip("idle", 0.), clip("walk", 4.)]);
+    player.play(player.clip_index("idle").unwrap());
+    player.cross_fade(1, 1.);
+    player.advance(0.25);
+    assert_eq!(player.sample()[0].2, vec![1., 0., 0.]);
+    player.advance(1.);
+    assert_eq!(player.states.len(), 1);
+    assert_eq!(player.sample()[0].2, vec![4., 0., 0.]);
+
+    player.pause();
+    assert!(!player.is_playing());
+    player.stop();
+    assert!(player.sample().is_empty());
+}
+
+#[test]
+fn test_gltf_skin_and_animation() {
+    // the triangle is bound to one joint, the joint moves up by 1 over a second
+    let joints: [u16; 12] = [0; 12];
+    let weights: [f32; 12] = [1., 0., 0., 0., 1., 0., 0., 0., 1., 0., 0., 0.];
+    let inverse_bind_matrix = Mat4::IDENTITY.to_cols_array();
+    let times: [f32; 2] = [0., 1.];
+    let translations: [f32; 6] = [0., 0., 0., 0., 1., 0.];
+    let mut bin = triangle_bin();
+    bin.extend_from_slice(bytemuck::cast_slice(&joints));
+    bin.extend_from_slice(bytemuck::cast_slice(&weights));
+    bin.extend_from_slice(bytemuck::cast_slice(&inverse_bind_matrix));
+    bin.extend_from_slice(bytemuck::cast_slice(&times));
+    bin.extend_from_slice(bytemuck::cast_slice(&translations));
+    // a morph target too, so skin and weights share group 3
+    bin.extend_from_slice(bytemuck::cast_slice(&[0f32; 9]));
+    let json = r#"{
+  "asset": {"version": "2.0"},
+  "scenes": [{"nodes": [0, 1]}],
+  "nodes": [
+    {"name": "body", "mesh": 0, "skin": 0},
+    {"name": "bone"}
+  ],
+  "meshes": [{"name": "tri", "primitives": [{"attributes": {"POSITION": 0, "NORMAL": 1, "JOINTS_0": 4, "WEIGHTS_0": 5}, "indices": 3, "targets": [{"POSITION": 9}]}]}],
+  "skins": [{"joints": [1], "inverseBindMatrices": 6}],
+  "animations": [{"name": "rise", "channels": [{"sampler": 0, "target": {"node": 1, "path": "translation"}}], "samplers": [{"input": 7, "output": 8}]}],
+  "buffers": [{"byteLength": 312}],
+  "bufferViews": [
+    {"buffer": 0, "byteOffset": 0, "byteLength": 36},
+    {"buffer": 0, "byteOffset": 36, "byteLength": 36},
+    {"buffer": 0, "byteOffset": 72, "byteLength": 24},
+    {"buffer": 0, "byteOffset": 96, "byteLength": 12},
+    {"buffer": 0, "byteOffset": 108, "byteLength": 24},
+    {"buffer": 0, "byteOffset": 132, "byteLength": 48},
+    {"buffer": 0, "byteOffset": 180, "byteLength": 64},
+    {"buffer": 0, "byteOffset": 244, "byteLength": 8},
+    {"buffer": 0, "byteOffset": 252, "byteLength": 24},
+    {"buffer": 0, "byteOffset": 276, "byteLength": 36}
+  ],
+  "accessors": [
+    {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [-1, 0, -1], "max": [1, 0, 1]},
+    {"bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3"},
+    {"bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC2"},
+    {"bufferView": 3, "componentType": 5125, "count": 3, "type": "SCALAR"},
+    {"bufferView": 4, "componentType": 5123, "count": 3, "type": "VEC4"},
+    {"bufferView": 5, "componentType": 5126, "count": 3, "type": "VEC4"},
+    {"bufferView": 6, "componentType": 5126, "count": 1, "type": "MAT4"},
+    {"bufferView": 7, "componentType": 5126, "count": 2, "type": "SCALAR", "min": [0], "max": [1]},
+    {"bufferView": 8, "componentType": 5126, "count": 2, "type": "VEC3"},
+    {"bufferView": 9, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 0, 0], "max": [0, 0, 0]}
+  ]
+}"#;
+    let glb = make_glb(json, &bin);
+    let mut mini_gpu = make_headless_mini_gpu(16, 16);
+    let model_id = pollster::block_on(load_gltf(&glb, &mut mini_gpu)).unwrap();
+
+    let scene = &mut mini_gpu.scene;
+    let body = find_child(scene, model_id, "body");
+    let bone = find_child(scene, model_id, "bone");
+    let primitive = scene.get_entity(body).unwrap().children[0];
+    let skin = scene
+        .get_entity_component::<Skin>(primitive, "skin")
+        .unwrap();
+    assert_eq!(skin.joints, vec![bone]);
+    assert!(scene
+        .get_entity_component::<MorphWeights>(primitive, "weights")
+        .is_ok());
+
+    let player = scene
+        .get_entity_component_mut::<AnimationPlayer>(model_id, "animation_player")
+        .unwrap();
+    player.play(player.clip_index("rise").unwrap());
+    AnimationSystem::animate(scene, 0.5);
+    TransformSystem::update_transforms(scene, &mini_gpu.renderer);
+    let bone_transform = scene
+        .get_entity_component::<Transform>(bone, "transform")
+        .unwrap();
+    assert_eq!(bone_transform.position, Vec3::new(0., 0.5, 0.));
+
+    let skin = scene
+        .get_entity_component::<Skin>(primitive, "skin")
+        .unwrap();
+    let joint_matrices = skin.joint_matrices(scene, Mat4::IDENTITY);
+    assert_eq!(
+        joint_matrices[0],
+        Mat4::from_translation(Vec3::new(0., 0.5, 0.))
+    );
+
+    // the skinned pipeline and bind group are valid
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+}
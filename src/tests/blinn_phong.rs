@@ -0,0 +1,160 @@
+use glam::Vec3;
+use wgpu::naga;
+
+use crate::{
+    components::{
+        lights::{directional_light::DirectionalLight, light::LightTrait},
+        material::MaterialTrait,
+        materials::{
+            blinn_phong::{BlinnPhongMaterial, BlinnPhongMaterialConfig, BlinnUniform},
+            shader::ShaderParser,
+        },
+        mesh::{Mesh, VertexFormat, VertexPositionNormal},
+    },
+    entity::Entity,
+    mini_gpu::MiniGPU,
+    system::mesh_render::MeshRender,
+};
+
+use super::{headless::make_headless_mini_gpu, lights::wgsl_struct_layout};
+
+/// a blinn phong plane on xz, the default light is mirrored from the camera so the highlight is at the center
+fn make_plane(config: BlinnPhongMaterialConfig, light_intensity: f32) -> MiniGPU {
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    mini_gpu.scene.background_color = wgpu::Color::BLACK;
+    let light_id = mini_gpu.scene.default_light.unwrap();
+    let light = mini_gpu
+        .scene
+        .get_entity_component_mut::<Box<dyn LightTrait>>(light_id, "light")
+        .unwrap();
+    let light = light
+        .as_any_mut()
+        .downcast_mut::<DirectionalLight>()
+        .unwrap();
+    light.uniform.direction = [0., -1., 1.];
+    light.uniform.color = [1., 1., 1.];
+    light.uniform.intensity = light_intensity;
+
+    let vertices: Vec<VertexPositionNormal> = [[-10., 0., 10.], [10., 0., 10.], [0., 0., -10.]]
+        .into_iter()
+        .map(|position| VertexPositionNormal {
+            position,
+            normal: [0., 1., 0.],
+        })
+        .collect();
+    let mesh = Mesh::new(
+        bytemuck::cast_slice(&vertices),
+        vec![0, 1, 2],
+        VertexFormat::PositionNormal,
+        &mini_gpu.renderer,
+    );
+    let material = BlinnPhongMaterial::new(config, &mini_gpu.renderer);
+    let entity_id = mini_gpu.scene.add_entity(Entity::new());
+    mini_gpu.scene.set_entity_component(entity_id, mesh, "mesh");
+    mini_gpu
+        .scene
+        .set_entity_component::<Box<dyn MaterialTrait>>(entity_id, Box::new(material), "material");
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu
+}
+
+fn pixel(mini_gpu: &mut MiniGPU, x: u32, y: u32) -> [u8; 4] {
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+    let image = mini_gpu.renderer.capture_image().unwrap();
+    image.get_pixel(x, y).0
+}
+
+#[test]
+fn test_blinn_uniform_matches_wgsl() {
+    use std::mem::{offset_of, size_of};
+    let source = ShaderParser::new().parse_shader(include_str!(
+        "../components/materials/shaders/blinn_phong.wgsl"
+    ));
+    let (members, size) = wgsl_struct_layout(&source, "BlinnUniform");
+    let offsets = [
+        ("diffuse_color", offset_of!(BlinnUniform, diffuse_color)),
+        (
+            "diffuse_strength",
+            offset_of!(BlinnUniform, diffuse_strength),
+        ),
+        ("specular_color", offset_of!(BlinnUniform, specular_color)),
+        (
+            "specular_strength",
+            offset_of!(BlinnUniform, specular_strength),
+        ),
+        ("ambient_color", offset_of!(BlinnUniform, ambient_color)),
+        ("shininess", offset_of!(BlinnUniform, shininess)),
+        ("opacity", offset_of!(BlinnUniform, opacity)),
+    ]
+    .map(|(name, offset)| (name.to_string(), offset as u32));
+    assert_eq!(members, offsets);
+    assert_eq!(size as usize, size_of::<BlinnUniform>());
+
+    // every texture on still validates
+    let mut parser = ShaderParser::new();
+    for name in [
+        "HAS_TEXTURE",
+        "HAS_DIFFUSE_MAP",
+        "HAS_SPECULAR_MAP",
+        "HAS_NORMAL_MAP",
+        "HAS_AMBIENT_MAP",
+    ] {
+        parser.defines.insert(name.to_string(), "true".to_string());
+    }
+    let source = parser.parse_shader(include_str!(
+        "../components/materials/shaders/blinn_phong.wgsl"
+    ));
+    let module = naga::front::wgsl::parse_str(&source).unwrap();
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .unwrap();
+}
+
+#[test]
+fn test_blinn_phong_specular_color_and_shininess() {
+    let config = |specular_strength, shininess| BlinnPhongMaterialConfig {
+        diffuse_color: Vec3::new(1., 0., 0.),
+        specular_color: Vec3::new(0., 0., 1.),
+        specular_strength,
+        shininess,
+        ..Default::default()
+    };
+    // the highlight has the specular color, not the diffuse one
+    let [red, green, blue, _] = pixel(&mut make_plane(config(1., 32.), 1.), 16, 16);
+    assert!(
+        red > 0 && green == 0 && blue > 128,
+        "{} {} {}",
+        red,
+        green,
+        blue
+    );
+    let [_, _, blue, _] = pixel(&mut make_plane(config(0., 32.), 1.), 16, 16);
+    assert_eq!(blue, 0);
+
+    // a higher shininess makes the highlight smaller
+    let wide = pixel(&mut make_plane(config(1., 4.), 1.), 16, 26)[2];
+    let narrow = pixel(&mut make_plane(config(1., 256.), 1.), 16, 26)[2];
+    assert!(wide > narrow, "{} {}", wide, narrow);
+}
+
+#[test]
+fn test_blinn_phong_ambient_color() {
+    // only the default ambient light
+    let lit = pixel(
+        &mut make_plane(BlinnPhongMaterialConfig::default(), 0.),
+        16,
+        16,
+    );
+    assert!(lit[0] > 0);
+    let config = BlinnPhongMaterialConfig {
+        ambient_color: Vec3::ZERO,
+        ..Default::default()
+    };
+    let dark = pixel(&mut make_plane(config, 0.), 16, 16);
+    assert_eq!(dark[..3], [0, 0, 0]);
+}
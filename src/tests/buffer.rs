@@ -0,0 +1,67 @@
+use glam::Mat4;
+
+use crate::{
+    components::{
+        instance::{Instance, InstanceData},
+        material::MaterialTrait,
+        materials::basic::{BasicMaterial, BasicMaterialConfig},
+        mesh::Mesh,
+    },
+    entity::Entity,
+    system::mesh_render::MeshRender,
+};
+
+use super::headless::make_headless_mini_gpu;
+
+#[test]
+fn test_update_mesh_and_instance_buffers() {
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    mini_gpu.scene.background_color = wgpu::Color::BLACK;
+    // starts as a degenerate triangle, nothing is drawn
+    let mut mesh = Mesh::new_position_only(vec![0.; 9], vec![0, 1, 2], &mini_gpu.renderer);
+    // a big quad on the xz plane under the default camera, one more vertex and triangle than before
+    let quad: [f32; 12] = [-10., 0., 10., 10., 0., 10., 10., 0., -10., -10., 0., -10.];
+    mesh.update_vertices(bytemuck::cast_slice(&quad), &mini_gpu.renderer);
+    mesh.update_indices(vec![0, 1, 2, 0, 2, 3], &mini_gpu.renderer);
+    assert_eq!(mesh.num_indices, 6);
+    assert!(mesh.vertex_buffer.size() >= 48);
+    assert!(mesh.index_buffer.size() >= 24);
+
+    let mut instance = Instance::new(vec![], &mini_gpu.renderer);
+    let identity = InstanceData {
+        data: Mat4::IDENTITY.to_cols_array_2d(),
+    };
+    instance.set_data(&[identity; 3], &mini_gpu.renderer);
+    let size = instance.buffer.size();
+    assert!(size >= 3 * 64);
+    // in place
+    instance.update_range(1, &[identity], &mini_gpu.renderer);
+    assert_eq!(instance.buffer.size(), size);
+    // past the end appends
+    instance.update_range(3, &[identity; 2], &mini_gpu.renderer);
+    assert_eq!(instance.count(), 5);
+    assert!(instance.buffer.size() >= 5 * 64);
+
+    let material = BasicMaterial::new(
+        BasicMaterialConfig {
+            color: [0., 1., 0., 1.],
+            ..Default::default()
+        },
+        &mini_gpu.renderer,
+    );
+    let entity_id = mini_gpu.scene.add_entity(Entity::new());
+    mini_gpu.scene.set_entity_component(entity_id, mesh, "mesh");
+    mini_gpu
+        .scene
+        .set_entity_component(entity_id, instance, "instance");
+    mini_gpu
+        .scene
+        .set_entity_component::<Box<dyn MaterialTrait>>(entity_id, Box::new(material), "material");
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+
+    let image = mini_gpu.renderer.capture_image().unwrap();
+    assert_eq!(image.get_pixel(16, 16).0, [0, 255, 0, 255]);
+}
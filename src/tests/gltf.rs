@@ -0,0 +1,397 @@
+use glam::{Vec2, Vec3, Vec4};
+
+use crate::{
+    components::{
+        lights::{
+            directional_light::DirectionalLight, light::LightTrait, point_light::PointLight,
+            spot_light::SpotLight,
+        },
+        material::MaterialTrait,
+        materials::pbr::{AlphaMode, PBRMaterial, TextureTransform},
+        mesh::{Mesh, VertexFormat},
+        orthographic_camera::OrthographicCamera,
+        perspective_camera::{CameraTrait, PerspectiveCamera},
+        transform::Transform,
+    },
+    entity::EntityId,
+    geometry::normals::{flat_normals, smooth_normals},
+    scene::Scene,
+    system::mesh_render::MeshRender,
+    utils::gltf::{load_gltf, load_gltf_with_options, GltfLoadOptions},
+};
+
+use super::headless::make_headless_mini_gpu;
+
+/// pack a gltf json and its binary buffer into a glb
+pub fn make_glb(json: &str, bin: &[u8]) -> Vec<u8> {
+    let mut json = json.as_bytes().to_vec();
+    json.resize(json.len().div_ceil(4) * 4, b' ');
+    let mut bin = bin.to_vec();
+    bin.resize(bin.len().div_ceil(4) * 4, 0);
+    let total = 12 + 8 + json.len() + 8 + bin.len();
+    let mut glb = Vec::with_capacity(total);
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(total as u32).to_le_bytes());
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"JSON");
+    glb.extend_from_slice(&json);
+    glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"BIN\0");
+    glb.extend_from_slice(&bin);
+    glb
+}
+
+/// one triangle on the xz plane with normals, uvs and u32 indices
+pub fn triangle_bin() -> Vec<u8> {
+    let positions: [f32; 9] = [-1., 0., 1., 1., 0., 1., 0., 0., -1.];
+    let normals: [f32; 9] = [0., 1., 0., 0., 1., 0., 0., 1., 0.];
+    let tex_coords: [f32; 6] = [0., 0., 1., 0., 0.5, 1.];
+    let indices: [u32; 3] = [0, 1, 2];
+    let mut bin: Vec<u8> = Vec::new();
+    bin.extend_from_slice(bytemuck::cast_slice(&positions));
+    bin.extend_from_slice(bytemuck::cast_slice(&normals));
+    bin.extend_from_slice(bytemuck::cast_slice(&tex_coords));
+    bin.extend_from_slice(bytemuck::cast_slice(&indices));
+    bin
+}
+
+const TRIANGLE_ACCESSORS: &str = r#"
+  "buffers": [{"byteLength": 108}],
+  "bufferViews": [
+    {"buffer": 0, "byteOffset": 0, "byteLength": 36},
+    {"buffer": 0, "byteOffset": 36, "byteLength": 36},
+    {"buffer": 0, "byteOffset": 72, "byteLength": 24},
+    {"buffer": 0, "byteOffset": 96, "byteLength": 12}
+  ],
+  "accessors": [
+    {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [-1, 0, -1], "max": [1, 0, 1]},
+    {"bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3"},
+    {"bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC2"},
+    {"bufferView": 3, "componentType": 5125, "count": 3, "type": "SCALAR"}
+  ]"#;
+
+pub fn find_child(scene: &Scene, parent: EntityId, name: &str) -> EntityId {
+    let entity = scene.get_entity(parent).unwrap();
+    *entity
+        .children
+        .iter()
+        .find(|id| scene.get_entity(**id).unwrap().name == name)
+        .unwrap_or_else(|| panic!("no child named {}", name))
+}
+
+#[test]
+fn test_gltf_nodes_and_pbr_material() {
+    let json = format!(
+        r#"{{
+  "asset": {{"version": "2.0"}},
+  "scene": 0,
+  "scenes": [{{"nodes": [0]}}],
+  "nodes": [
+    {{"name": "root", "translation": [1, 2, 3], "children": [1, 2]}},
+    {{"name": "a", "mesh": 0, "scale": [2, 2, 2]}},
+    {{"name": "b", "mesh": 0, "rotation": [0, 0.7071068, 0, 0.7071068]}}
+  ],
+  "meshes": [{{"name": "tri", "primitives": [{{"attributes": {{"POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2}}, "indices": 3, "material": 0}}]}}],
+  "materials": [{{
+    "name": "leaf",
+    "pbrMetallicRoughness": {{"baseColorFactor": [0.2, 0.8, 0.2, 0.5], "metallicFactor": 0.1, "roughnessFactor": 0.7}},
+    "emissiveFactor": [0.1, 0, 0],
+    "alphaMode": "MASK",
+    "alphaCutoff": 0.3,
+    "doubleSided": true
+  }}],
+  {}
+}}"#,
+        TRIANGLE_ACCESSORS
+    );
+    let glb = make_glb(&json, &triangle_bin());
+    let mut mini_gpu = make_headless_mini_gpu(16, 16);
+    let model_id = pollster::block_on(load_gltf(&glb, &mut mini_gpu)).unwrap();
+
+    let scene = &mini_gpu.scene;
+    let root = find_child(scene, model_id, "root");
+    let root_transform = scene
+        .get_entity_component::<Transform>(root, "transform")
+        .unwrap();
+    assert_eq!(root_transform.position, Vec3::new(1., 2., 3.));
+    let a = find_child(scene, root, "a");
+    let b = find_child(scene, root, "b");
+    let a_transform = scene
+        .get_entity_component::<Transform>(a, "transform")
+        .unwrap();
+    assert_eq!(a_transform.scale, Vec3::splat(2.));
+
+    // both nodes reuse the mesh and material of the same gltf mesh
+    let a_primitive = scene.get_entity(a).unwrap().children[0];
+    let b_primitive = scene.get_entity(b).unwrap().children[0];
+    let index = |id, name| scene.get_entity_component_index(id, name).unwrap();
+    assert_eq!(index(a_primitive, "mesh"), index(b_primitive, "mesh"));
+    assert_eq!(
+        index(a_primitive, "material"),
+        index(b_primitive, "material")
+    );
+
+    let material_id = index(a_primitive, "material");
+    let material = mini_gpu
+        .scene
+        .get_component_mut::<Box<dyn MaterialTrait>>(material_id)
+        .unwrap();
+    let material = material.as_any().downcast_mut::<PBRMaterial>().unwrap();
+    assert_eq!(material.config.name, "leaf");
+    assert_eq!(
+        material.config.base_color_factor,
+        Vec4::new(0.2, 0.8, 0.2, 0.5)
+    );
+    assert_eq!(material.config.roughness_factor, 0.7);
+    assert_eq!(material.config.emissive_factor, Vec3::new(0.1, 0., 0.));
+    assert_eq!(material.config.alpha_mode, AlphaMode::Mask);
+    assert_eq!(material.config.alpha_cutoff, 0.3);
+    assert!(material.config.double_sided);
+
+    // the imported hierarchy renders
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+}
+
+#[test]
+fn test_untextured_material_shared_by_vertex_formats() {
+    // the same material on a primitive with TEXCOORD_0 and on one without
+    let json = format!(
+        r#"{{
+  "asset": {{"version": "2.0"}},
+  "scenes": [{{"nodes": [0, 1]}}],
+  "nodes": [
+    {{"name": "uv", "mesh": 0, "translation": [-1, 0, 0]}},
+    {{"name": "no-uv", "mesh": 1, "translation": [1, 0, 0]}}
+  ],
+  "meshes": [
+    {{"primitives": [{{"attributes": {{"POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2}}, "indices": 3, "material": 0}}]}},
+    {{"primitives": [{{"attributes": {{"POSITION": 0, "NORMAL": 1}}, "indices": 3, "material": 0}}]}}
+  ],
+  "materials": [{{"pbrMetallicRoughness": {{"baseColorFactor": [1, 0, 0, 1], "metallicFactor": 0}}}}],
+  {}
+}}"#,
+        TRIANGLE_ACCESSORS
+    );
+    let glb = make_glb(&json, &triangle_bin());
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    mini_gpu.scene.background_color = wgpu::Color::BLACK;
+    let model_id = pollster::block_on(load_gltf(&glb, &mut mini_gpu)).unwrap();
+
+    let scene = &mini_gpu.scene;
+    let primitive = |name| {
+        let node = find_child(scene, model_id, name);
+        scene.get_entity(node).unwrap().children[0]
+    };
+    let (uv, no_uv) = (primitive("uv"), primitive("no-uv"));
+    let format = |id| {
+        scene
+            .get_entity_component::<Mesh>(id, "mesh")
+            .unwrap()
+            .vertex_format
+    };
+    assert_eq!(format(uv), VertexFormat::PositionNormalTexture);
+    assert_eq!(format(no_uv), VertexFormat::PositionNormal);
+    let index = |id| scene.get_entity_component_index(id, "material").unwrap();
+    assert_ne!(index(uv), index(no_uv));
+
+    // both triangles are drawn where they belong
+    let camera = scene.get_default_camera().unwrap();
+    let view_projection = camera.get_projection_matrix() * camera.get_view_matrix();
+    let pixels: Vec<(u32, u32)> = [-1., 1.]
+        .map(|x| {
+            let ndc = view_projection.project_point3(Vec3::new(x, 0., 1. / 3.));
+            (((ndc.x + 1.) * 16.) as u32, ((1. - ndc.y) * 16.) as u32)
+        })
+        .to_vec();
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+    let image = mini_gpu.renderer.capture_image().unwrap();
+    for (x, y) in pixels {
+        let [red, green, blue, _] = image.get_pixel(x, y).0;
+        assert!(
+            red > 0 && green == 0 && blue == 0,
+            "{} {} {:?}",
+            x,
+            y,
+            image.get_pixel(x, y)
+        );
+    }
+}
+
+#[test]
+fn test_texture_transform_matches_khr_texture_transform() {
+    let transform = TextureTransform {
+        offset: Vec2::new(0.5, 0.),
+        rotation: std::f32::consts::FRAC_PI_2,
+        scale: Vec2::splat(2.),
+    };
+    // scale, then rotate the uv clockwise in uv space, then offset
+    let uv = transform.to_mat3().transform_point2(Vec2::new(1., 0.));
+    assert!(uv.abs_diff_eq(Vec2::new(0.5, -2.), 1e-5), "{:?}", uv);
+}
+
+#[test]
+fn test_gltf_primitive_without_normals_uvs_or_indices() {
+    // a quad as a non-indexed triangle strip with positions only
+    let positions: [f32; 12] = [0., 0., 0., 1., 0., 0., 0., 0., -1., 1., 0., -1.];
+    let json = r#"{
+  "asset": {"version": "2.0"},
+  "scenes": [{"nodes": [0]}],
+  "nodes": [{"mesh": 0}],
+  "meshes": [{"primitives": [{"attributes": {"POSITION": 0}, "mode": 5}]}],
+  "buffers": [{"byteLength": 48}],
+  "bufferViews": [{"buffer": 0, "byteLength": 48}],
+  "accessors": [{"bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3", "min": [0, 0, -1], "max": [1, 0, 0]}]
+}"#;
+    let glb = make_glb(json, bytemuck::cast_slice(&positions));
+    let mut mini_gpu = make_headless_mini_gpu(16, 16);
+    let model_id = pollster::block_on(load_gltf(&glb, &mut mini_gpu)).unwrap();
+
+    let scene = &mini_gpu.scene;
+    let node = scene.get_entity(model_id).unwrap().children[0];
+    let primitive = scene.get_entity(node).unwrap().children[0];
+    let mesh = scene
+        .get_entity_component::<Mesh>(primitive, "mesh")
+        .unwrap();
+    assert_eq!(mesh.vertex_format, VertexFormat::PositionNormal);
+    // two triangles, unwelded for flat normals
+    assert_eq!(mesh.num_indices, 6);
+}
+
+#[test]
+fn test_gltf_skips_line_primitives() {
+    // one mesh with a triangle and the same vertices drawn as lines
+    let json = format!(
+        r#"{{
+  "asset": {{"version": "2.0"}},
+  "scenes": [{{"nodes": [0]}}],
+  "nodes": [{{"name": "mixed", "mesh": 0}}],
+  "meshes": [{{"primitives": [
+    {{"attributes": {{"POSITION": 0, "NORMAL": 1}}, "indices": 3}},
+    {{"attributes": {{"POSITION": 0}}, "mode": 1}}
+  ]}}],
+  {}
+}}"#,
+        TRIANGLE_ACCESSORS
+    );
+    let glb = make_glb(&json, &triangle_bin());
+    let mut mini_gpu = make_headless_mini_gpu(16, 16);
+    let model_id = pollster::block_on(load_gltf(&glb, &mut mini_gpu)).unwrap();
+
+    let scene = &mini_gpu.scene;
+    let node = find_child(scene, model_id, "mixed");
+    let children = &scene.get_entity(node).unwrap().children;
+    assert_eq!(children.len(), 1);
+    let mesh = scene
+        .get_entity_component::<Mesh>(children[0], "mesh")
+        .unwrap();
+    assert_eq!(mesh.num_indices, 3);
+}
+
+#[test]
+fn test_flat_and_smooth_normals() {
+    let positions = [[0., 0., 0.], [1., 0., 0.], [0., 0., -1.]];
+    assert_eq!(flat_normals(&positions), vec![[0., 1., 0.]; 3]);
+    // two faces folded along the x axis, the shared edge gets the average
+    let positions = [[0., 0., 0.], [1., 0., 0.], [0., 0., -1.], [0., -1., 0.]];
+    let normals = smooth_normals(&positions, &[0, 1, 2, 0, 3, 1]);
+    assert_eq!(normals[2], [0., 1., 0.]);
+    assert_eq!(normals[3], [0., 0., 1.]);
+    let shared = Vec3::from(normals[0]);
+    assert!(shared.abs_diff_eq(Vec3::new(0., 1., 1.).normalize(), 1e-6));
+}
+
+#[test]
+fn test_gltf_cameras_and_lights() {
+    let json = r#"{
+  "asset": {"version": "2.0"},
+  "extensionsUsed": ["KHR_lights_punctual"],
+  "extensions": {"KHR_lights_punctual": {"lights": [
+    {"type": "directional", "color": [1, 0.5, 0.5], "intensity": 2},
+    {"type": "point", "intensity": 10, "range": 5},
+    {"type": "spot", "intensity": 20, "spot": {"innerConeAngle": 0.2, "outerConeAngle": 0.5}}
+  ]}},
+  "scenes": [{"nodes": [0, 1, 2, 4, 5]}],
+  "nodes": [
+    {"name": "rig", "translation": [0, 0, 5], "children": [3]},
+    {"name": "sun", "rotation": [-0.7071068, 0, 0, 0.7071068], "extensions": {"KHR_lights_punctual": {"light": 0}}},
+    {"name": "bulb", "translation": [0, 2, 0], "extensions": {"KHR_lights_punctual": {"light": 1}}},
+    {"name": "eye", "camera": 0},
+    {"name": "top", "camera": 1},
+    {"name": "lamp", "translation": [1, 3, 0], "rotation": [-0.7071068, 0, 0, 0.7071068], "extensions": {"KHR_lights_punctual": {"light": 2}}}
+  ],
+  "cameras": [
+    {"type": "perspective", "perspective": {"yfov": 0.8, "aspectRatio": 1.5, "znear": 0.1, "zfar": 100}},
+    {"type": "orthographic", "orthographic": {"xmag": 4, "ymag": 2, "znear": 0.1, "zfar": 50}}
+  ]
+}"#;
+    let glb = make_glb(json, &[]);
+    let mut mini_gpu = make_headless_mini_gpu(16, 16);
+    let options = GltfLoadOptions { use_camera: true };
+    let model_id =
+        pollster::block_on(load_gltf_with_options(&glb, &mut mini_gpu, options)).unwrap();
+
+    let scene = &mut mini_gpu.scene;
+    let rig = find_child(scene, model_id, "rig");
+    let eye = find_child(scene, rig, "eye");
+    assert_eq!(scene.default_camera, Some(eye));
+    let camera = scene.get_default_camera_mut().unwrap();
+    let camera = camera.as_any().downcast_mut::<PerspectiveCamera>().unwrap();
+    // placed by the parent's translation, looking down -z
+    assert_eq!(camera.config.position, Vec3::new(0., 0., 5.));
+    assert_eq!(camera.config.target, Vec3::new(0., 0., 4.));
+    assert!((camera.config.fov - 0.8f32.to_degrees()).abs() < 1e-4);
+    assert_eq!(camera.config.aspect, 1.5);
+
+    let top = find_child(scene, model_id, "top");
+    let camera = scene
+        .get_entity_component_mut::<Box<dyn CameraTrait>>(top, "camera")
+        .unwrap();
+    let camera = camera
+        .as_any()
+        .downcast_mut::<OrthographicCamera>()
+        .unwrap();
+    assert_eq!(camera.config.width, 8.);
+    assert_eq!(camera.config.aspect, 2.);
+
+    let sun = find_child(scene, model_id, "sun");
+    let light = scene
+        .get_entity_component_mut::<Box<dyn LightTrait>>(sun, "light")
+        .unwrap();
+    let light = light
+        .as_any_mut()
+        .downcast_mut::<DirectionalLight>()
+        .unwrap();
+    let direction = Vec3::from_array(light.uniform.direction);
+    assert!(direction.abs_diff_eq(Vec3::NEG_Y, 1e-5), "{:?}", direction);
+    assert_eq!(light.uniform.color, [1., 0.5, 0.5]);
+    assert_eq!(light.uniform.intensity, 2.);
+
+    let bulb = find_child(scene, model_id, "bulb");
+    let light = scene
+        .get_entity_component_mut::<Box<dyn LightTrait>>(bulb, "light")
+        .unwrap();
+    let light = light.as_any_mut().downcast_mut::<PointLight>().unwrap();
+    assert_eq!(light.uniform.position, [0., 2., 0.]);
+    assert_eq!(light.uniform.intensity, 10.);
+    assert_eq!(light.uniform.range, 5.);
+
+    let lamp = find_child(scene, model_id, "lamp");
+    let light = scene
+        .get_entity_component_mut::<Box<dyn LightTrait>>(lamp, "light")
+        .unwrap();
+    let light = light.as_any_mut().downcast_mut::<SpotLight>().unwrap();
+    assert_eq!(light.uniform.position, [1., 3., 0.]);
+    let direction = Vec3::from_array(light.uniform.direction);
+    assert!(direction.abs_diff_eq(Vec3::NEG_Y, 1e-5), "{:?}", direction);
+    // no range in the file means no limit
+    assert_eq!(light.uniform.range, 0.);
+    assert_eq!(light.uniform.inner_cone_cos, 0.2_f32.cos());
+    assert_eq!(light.uniform.outer_cone_cos, 0.5_f32.cos());
+}
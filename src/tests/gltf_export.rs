@@ -0,0 +1,139 @@
+use glam::{Mat4, Quat, Vec3};
+
+use crate::{
+    components::{
+        instance::{Instance, InstanceData},
+        material::MaterialTrait,
+        materials::{
+            basic::{BasicMaterial, BasicMaterialConfig},
+            blinn_phong::{BlinnPhongMaterial, BlinnPhongMaterialConfig},
+        },
+        mesh::Mesh,
+        transform::Transform,
+    },
+    entity::Entity,
+    geometry::sphere::{make_sphere_mesh, MakeSphereConfig},
+    utils::{gltf::load_gltf, gltf_export::export_glb, texture::Texture},
+};
+
+use super::{gltf::find_child, headless::make_headless_mini_gpu};
+
+#[test]
+fn test_export_glb_round_trip() {
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    let renderer = &mini_gpu.renderer;
+    let scene = &mut mini_gpu.scene;
+
+    let root = scene.add_entity(Entity {
+        name: "root".to_string(),
+        ..Default::default()
+    });
+    let transform = Transform::new(
+        renderer,
+        Vec3::new(1., 2., 3.),
+        Quat::from_rotation_y(0.5),
+        Vec3::splat(2.),
+    );
+    scene.set_entity_component(root, transform, "transform");
+    let sphere = make_sphere_mesh(MakeSphereConfig::default(), renderer);
+    let vertex_count = sphere.vertices.len() / sphere.vertex_buffer_layout.array_stride as usize;
+    let index_count = sphere.indices.len();
+    scene.set_entity_component(root, sphere, "mesh");
+    let image = image::DynamicImage::ImageRgba8(image::RgbaImage::from_fn(2, 2, |x, y| {
+        image::Rgba([x as u8 * 255, y as u8 * 255, 0, 255])
+    }));
+    let texture = Texture::from_image(&renderer.device, &renderer.queue, &image, None).unwrap();
+    let material = BasicMaterial::new(
+        BasicMaterialConfig {
+            texture: Some(texture),
+            ..Default::default()
+        },
+        renderer,
+    );
+    scene.set_entity_component::<Box<dyn MaterialTrait>>(root, Box::new(material), "material");
+
+    // a blinn-phong quad drawn twice through instancing
+    let child = scene
+        .add_entity_child(
+            root,
+            Entity {
+                name: "quad".to_string(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let positions: [f32; 12] = [0., 0., 0., 1., 0., 0., 1., 1., 0., 0., 1., 0.];
+    let quad = Mesh::new_position_only(positions.to_vec(), vec![0, 1, 2, 0, 2, 3], renderer);
+    scene.set_entity_component(child, quad, "mesh");
+    let material = BlinnPhongMaterial::new(
+        BlinnPhongMaterialConfig {
+            diffuse_color: Vec3::new(1., 0., 0.),
+            opacity: 0.5,
+            ..Default::default()
+        },
+        renderer,
+    );
+    scene.set_entity_component::<Box<dyn MaterialTrait>>(child, Box::new(material), "material");
+    let instances = [Vec3::X, Vec3::Y]
+        .map(|offset| InstanceData {
+            data: Mat4::from_translation(offset).to_cols_array_2d(),
+        })
+        .to_vec();
+    scene.set_entity_component(child, Instance::new(instances, renderer), "instance");
+
+    let glb = export_glb(scene, root, renderer).unwrap();
+
+    let (document, buffers, images) = gltf::import_slice(&glb).unwrap();
+    assert_eq!(images.len(), 1);
+    assert_eq!((images[0].width, images[0].height), (2, 2));
+    let node = document.default_scene().unwrap().nodes().next().unwrap();
+    assert_eq!(node.name(), Some("root"));
+    let (translation, _, scale) = node.transform().decomposed();
+    assert_eq!(translation, [1., 2., 3.]);
+    assert_eq!(scale, [2., 2., 2.]);
+
+    let primitive = node.mesh().unwrap().primitives().next().unwrap();
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+    assert_eq!(reader.read_positions().unwrap().count(), vertex_count);
+    assert_eq!(reader.read_normals().unwrap().count(), vertex_count);
+    assert_eq!(
+        reader.read_tex_coords(0).unwrap().into_f32().count(),
+        vertex_count
+    );
+    assert_eq!(
+        reader.read_indices().unwrap().into_u32().count(),
+        index_count
+    );
+    let pbr = primitive.material().pbr_metallic_roughness();
+    assert!(pbr.base_color_texture().is_some());
+
+    let quad = node.children().next().unwrap();
+    assert_eq!(quad.name(), Some("quad"));
+    let instances: Vec<_> = quad.children().collect();
+    assert_eq!(instances.len(), 2);
+    assert_eq!(instances[1].transform().decomposed().0, [0., 1., 0.]);
+    let material = instances[0]
+        .mesh()
+        .unwrap()
+        .primitives()
+        .next()
+        .unwrap()
+        .material();
+    assert_eq!(
+        material.pbr_metallic_roughness().base_color_factor(),
+        [1., 0., 0., 0.5]
+    );
+    assert_eq!(material.alpha_mode(), gltf::material::AlphaMode::Blend);
+    // both instances share one mesh
+    assert_eq!(document.meshes().count(), 2);
+
+    // and it loads back into mini_gpu
+    let imported = pollster::block_on(load_gltf(&glb, &mut mini_gpu)).unwrap();
+    let root = find_child(&mini_gpu.scene, imported, "root");
+    let transform = mini_gpu
+        .scene
+        .get_entity_component::<Transform>(root, "transform")
+        .unwrap();
+    assert!(transform.position.abs_diff_eq(Vec3::new(1., 2., 3.), 1e-6));
+    find_child(&mini_gpu.scene, root, "quad");
+}
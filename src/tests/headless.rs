@@ -0,0 +1,148 @@
+use glam::Vec4;
+
+use crate::{
+    components::{
+        lights::{directional_light::DirectionalLight, light::LightTrait},
+        material::MaterialTrait,
+        materials::{
+            basic::{BasicMaterial, BasicMaterialConfig},
+            pbr::{PBRMaterial, PBRMaterialConfig},
+        },
+        mesh::{Mesh, VertexFormat, VertexPositionNormal},
+        perspective_camera::PerspectiveCamera,
+    },
+    entity::Entity,
+    mini_gpu::MiniGPU,
+    renderer::HeadlessConfig,
+    system::mesh_render::MeshRender,
+};
+
+pub fn make_headless_mini_gpu(width: u32, height: u32) -> MiniGPU {
+    pollster::block_on(MiniGPU::new_headless(HeadlessConfig {
+        width,
+        height,
+        force_fallback_adapter: true,
+        ..Default::default()
+    }))
+}
+
+#[test]
+fn test_headless_clear_color() {
+    let mut mini_gpu = make_headless_mini_gpu(64, 32);
+    mini_gpu.scene.background_color = wgpu::Color::RED;
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+
+    let image = mini_gpu.renderer.capture_image().unwrap();
+    assert_eq!(image.dimensions(), (64, 32));
+    assert_eq!(image.get_pixel(10, 10).0, [255, 0, 0, 255]);
+}
+
+#[test]
+fn test_headless_draw_mesh() {
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    mini_gpu.scene.background_color = wgpu::Color::BLACK;
+    // a big triangle on the xz plane, seen from the default camera at (0, 1, 1)
+    let mesh = Mesh::new_position_only(
+        vec![-10., 0., 10., 10., 0., 10., 0., 0., -10.],
+        vec![0, 1, 2],
+        &mini_gpu.renderer,
+    );
+    let material = BasicMaterial::new(
+        BasicMaterialConfig {
+            color: [0., 1., 0., 1.],
+            ..Default::default()
+        },
+        &mini_gpu.renderer,
+    );
+    let entity_id = mini_gpu.scene.add_entity(Entity::new());
+    mini_gpu.scene.set_entity_component(entity_id, mesh, "mesh");
+    mini_gpu
+        .scene
+        .set_entity_component::<Box<dyn MaterialTrait>>(entity_id, Box::new(material), "material");
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+
+    let image = mini_gpu.renderer.capture_image().unwrap();
+    assert_eq!(image.get_pixel(16, 16).0, [0, 255, 0, 255]);
+    // the top of the frame is above the horizon
+    assert_eq!(image.get_pixel(16, 0).0, [0, 0, 0, 255]);
+}
+
+#[test]
+fn test_resize_updates_camera_aspect() {
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    mini_gpu.resize(64, 16);
+    // minimized windows report zero size, it is ignored
+    mini_gpu.resize(0, 0);
+    assert_eq!((mini_gpu.config.width, mini_gpu.config.height), (64, 16));
+    let camera = mini_gpu.scene.get_default_camera_mut().unwrap();
+    let camera = camera.as_any().downcast_mut::<PerspectiveCamera>().unwrap();
+    assert_eq!(camera.config.aspect, 4.);
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+    let image = mini_gpu.renderer.capture_image().unwrap();
+    assert_eq!(image.dimensions(), (64, 16));
+}
+
+#[test]
+fn test_headless_draw_pbr_mesh() {
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    mini_gpu.scene.background_color = wgpu::Color::BLACK;
+    // light straight down onto the xz plane
+    let light_id = mini_gpu.scene.default_light.unwrap();
+    let light = mini_gpu
+        .scene
+        .get_entity_component_mut::<Box<dyn LightTrait>>(light_id, "light")
+        .unwrap();
+    let light = light
+        .as_any_mut()
+        .downcast_mut::<DirectionalLight>()
+        .unwrap();
+    light.uniform.direction = [0., -1., 0.];
+    light.uniform.color = [1., 1., 1.];
+
+    let vertices: Vec<VertexPositionNormal> = [[-10., 0., 10.], [10., 0., 10.], [0., 0., -10.]]
+        .into_iter()
+        .map(|position| VertexPositionNormal {
+            position,
+            normal: [0., 1., 0.],
+        })
+        .collect();
+    let mesh = Mesh::new(
+        bytemuck::cast_slice(&vertices),
+        vec![0, 1, 2],
+        VertexFormat::PositionNormal,
+        &mini_gpu.renderer,
+    );
+    let material = PBRMaterial::new(
+        PBRMaterialConfig {
+            base_color_factor: Vec4::new(1., 0., 0., 1.),
+            metallic_factor: 0.,
+            roughness_factor: 0.8,
+            ..Default::default()
+        },
+        &mini_gpu.renderer,
+    );
+    let entity_id = mini_gpu.scene.add_entity(Entity::new());
+    mini_gpu.scene.set_entity_component(entity_id, mesh, "mesh");
+    mini_gpu
+        .scene
+        .set_entity_component::<Box<dyn MaterialTrait>>(entity_id, Box::new(material), "material");
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+
+    let image = mini_gpu.renderer.capture_image().unwrap();
+    let [r, g, b, _] = image.get_pixel(16, 16).0;
+    // lit well above the ambient term, the dielectric specular stays faint
+    assert!(r > 100, "pixel {:?}", (r, g, b));
+    assert!(g < r / 2 && b < r / 2, "pixel {:?}", (r, g, b));
+}
@@ -0,0 +1,125 @@
+use glam::Mat4;
+
+use crate::{
+    components::{
+        instance::{Instance, InstanceLayout},
+        material::MaterialTrait,
+        materials::basic::{BasicMaterial, BasicMaterialConfig},
+    },
+    entity::Entity,
+    geometry::plane::{make_plane_mesh, MakePlaneConfig},
+    system::mesh_render::MeshRender,
+    utils::texture::Texture,
+};
+
+use super::headless::make_headless_mini_gpu;
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct InstanceValue {
+    model_matrix: [[f32; 4]; 4],
+    color: [f32; 4],
+    uv_offset_scale: [f32; 4],
+    value: f32,
+}
+
+fn make_layout() -> InstanceLayout {
+    InstanceLayout::default()
+        .with_attribute("color", wgpu::VertexFormat::Float32x4)
+        .with_attribute("uv_offset_scale", wgpu::VertexFormat::Float32x4)
+        .with_attribute("value", wgpu::VertexFormat::Float32)
+}
+
+#[test]
+fn test_instance_layout() {
+    let layout = make_layout();
+    assert_eq!(layout.stride(), std::mem::size_of::<InstanceValue>() as u64);
+    let locations: Vec<u32> = layout
+        .vertex_attributes()
+        .iter()
+        .map(|attribute| attribute.shader_location)
+        .collect();
+    // 9 is left to the mesh's vertex color
+    assert_eq!(locations, [5, 6, 7, 8, 10, 11, 12]);
+    assert_eq!(layout.attribute("value").unwrap().offset, 96);
+
+    let chunk = layout.shader_chunk();
+    assert!(chunk.contains("@location(8) model_matrix_3: vec4<f32>,"));
+    assert!(chunk.contains("@location(12) value: f32,"));
+    assert!(chunk.contains("fn instance_model_matrix(instance: InstanceInput) -> mat4x4<f32>"));
+
+    // 2 byte formats are padded to 4, vertex buffers need aligned offsets and strides
+    let layout = InstanceLayout::new()
+        .with_attribute("flags", wgpu::VertexFormat::Uint8x2)
+        .with_attribute("value", wgpu::VertexFormat::Float32)
+        .with_attribute("uv", wgpu::VertexFormat::Unorm8x2);
+    assert_eq!(layout.attribute("value").unwrap().offset, 4);
+    assert_eq!(layout.attribute("uv").unwrap().offset, 8);
+    assert_eq!(layout.stride(), 12);
+}
+
+#[test]
+#[should_panic(expected = "f64 attributes aren't supported")]
+fn test_instance_layout_rejects_f64() {
+    InstanceLayout::new().with_attribute("value", wgpu::VertexFormat::Float64);
+}
+
+#[test]
+fn test_instance_color_and_uv() {
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    mini_gpu.scene.background_color = wgpu::Color::BLACK;
+    // the left half of the texture is white, the instance only samples that half
+    let image = image::DynamicImage::ImageRgba8(image::RgbaImage::from_fn(2, 1, |x, _| {
+        image::Rgba(if x == 0 { [255; 4] } else { [0, 0, 0, 255] })
+    }));
+    let texture = Texture::from_image(
+        &mini_gpu.renderer.device,
+        &mini_gpu.renderer.queue,
+        &image,
+        None,
+    )
+    .unwrap();
+    let layout = make_layout();
+    let material = BasicMaterial::new(
+        BasicMaterialConfig {
+            texture: Some(texture),
+            shader: Some(layout.parse_shader(include_str!(
+                "../components/materials/shaders/instance.wgsl"
+            ))),
+            ..Default::default()
+        },
+        &mini_gpu.renderer,
+    );
+    let mesh = make_plane_mesh(
+        MakePlaneConfig {
+            width: 20.,
+            height: 20.,
+            ..Default::default()
+        },
+        &mini_gpu.renderer,
+    );
+    let instances = [InstanceValue {
+        model_matrix: Mat4::IDENTITY.to_cols_array_2d(),
+        color: [0., 1., 0., 1.],
+        uv_offset_scale: [0., 0., 0.25, 1.],
+        value: 0.5,
+    }];
+    let instance = Instance::with_layout(layout, &instances, &mini_gpu.renderer);
+    assert_eq!(instance.count(), 1);
+
+    let entity_id = mini_gpu.scene.add_entity(Entity::new());
+    mini_gpu.scene.set_entity_component(entity_id, mesh, "mesh");
+    mini_gpu
+        .scene
+        .set_entity_component(entity_id, instance, "instance");
+    mini_gpu
+        .scene
+        .set_entity_component::<Box<dyn MaterialTrait>>(entity_id, Box::new(material), "material");
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+
+    let image = mini_gpu.renderer.capture_image().unwrap();
+    assert_eq!(image.get_pixel(16, 16).0, [0, 255, 0, 255]);
+}
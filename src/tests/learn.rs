@@ -70,6 +70,8 @@ fn test_dyn_box_trait() {
     s1.display();
     let mut scene = Scene::new();
     let index = scene.add_component::<Box<dyn Trait>>(s1);
-    let s1 = scene.get_component::<Box<Struct1>>(index);
+    // stored as Box<dyn Trait>, reading it back as the concrete box is a type mismatch
+    assert!(scene.get_component::<Box<Struct1>>(index).is_err());
+    let s1 = scene.get_component::<Box<dyn Trait>>(index).unwrap();
     s1.display();
 }
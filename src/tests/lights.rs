@@ -0,0 +1,270 @@
+use glam::Vec4;
+use wgpu::naga;
+
+use crate::{
+    components::{
+        lights::{
+            ambient_light::AmbientLight,
+            directional_light::DirectionalLight,
+            hemisphere_light::{HemisphereLight, HemisphereLightUniform},
+            light::{LightData, LightTrait, LightsUniform, LIGHT_KIND_DIRECTIONAL, MAX_LIGHTS},
+            point_light::{PointLight, PointLightUniform},
+            spot_light::{SpotLight, SpotLightUniform},
+        },
+        material::MaterialTrait,
+        materials::pbr::{PBRMaterial, PBRMaterialConfig},
+        materials::shader::ShaderParser,
+        materials::shaderlib::SHADER_LIB,
+        mesh::{Mesh, VertexFormat, VertexPositionNormal},
+    },
+    entity::Entity,
+    mini_gpu::MiniGPU,
+    system::mesh_render::{LimitReport, MeshRender},
+};
+
+use super::headless::make_headless_mini_gpu;
+
+/// a red pbr plane on xz with the default directional light turned off
+fn make_dark_plane() -> MiniGPU {
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    mini_gpu.scene.background_color = wgpu::Color::BLACK;
+    let light_id = mini_gpu.scene.default_light.unwrap();
+    let light = mini_gpu
+        .scene
+        .get_entity_component_mut::<Box<dyn LightTrait>>(light_id, "light")
+        .unwrap();
+    let light = light
+        .as_any_mut()
+        .downcast_mut::<DirectionalLight>()
+        .unwrap();
+    light.uniform.intensity = 0.;
+
+    let vertices: Vec<VertexPositionNormal> = [[-10., 0., 10.], [10., 0., 10.], [0., 0., -10.]]
+        .into_iter()
+        .map(|position| VertexPositionNormal {
+            position,
+            normal: [0., 1., 0.],
+        })
+        .collect();
+    let mesh = Mesh::new(
+        bytemuck::cast_slice(&vertices),
+        vec![0, 1, 2],
+        VertexFormat::PositionNormal,
+        &mini_gpu.renderer,
+    );
+    let material = PBRMaterial::new(
+        PBRMaterialConfig {
+            base_color_factor: Vec4::new(1., 0., 0., 1.),
+            metallic_factor: 0.,
+            roughness_factor: 0.8,
+            ..Default::default()
+        },
+        &mini_gpu.renderer,
+    );
+    let entity_id = mini_gpu.scene.add_entity(Entity::new());
+    mini_gpu.scene.set_entity_component(entity_id, mesh, "mesh");
+    mini_gpu
+        .scene
+        .set_entity_component::<Box<dyn MaterialTrait>>(entity_id, Box::new(material), "material");
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu
+}
+
+fn center_red(mini_gpu: &mut MiniGPU) -> u8 {
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+    let image = mini_gpu.renderer.capture_image().unwrap();
+    image.get_pixel(16, 16).0[0]
+}
+
+fn add_light(mini_gpu: &mut MiniGPU, light: Box<dyn LightTrait>) {
+    let entity_id = mini_gpu.scene.add_entity(Entity::new());
+    mini_gpu
+        .scene
+        .set_entity_component::<Box<dyn LightTrait>>(entity_id, light, "light");
+}
+
+#[test]
+fn test_point_light() {
+    let mut mini_gpu = make_dark_plane();
+    // only the ambient term without point and spot lights
+    let dark = center_red(&mut mini_gpu);
+
+    let light = PointLight::new(PointLightUniform {
+        position: [0., 1., 0.],
+        intensity: 5.,
+        ..Default::default()
+    });
+    add_light(&mut mini_gpu, Box::new(light));
+    let lit = center_red(&mut mini_gpu);
+    assert!(lit > dark + 100, "{} {}", dark, lit);
+
+    // out of range
+    let mut mini_gpu = make_dark_plane();
+    let light = PointLight::new(PointLightUniform {
+        position: [0., 1., 0.],
+        intensity: 5.,
+        range: 0.5,
+        ..Default::default()
+    });
+    add_light(&mut mini_gpu, Box::new(light));
+    assert_eq!(center_red(&mut mini_gpu), dark);
+}
+
+#[test]
+fn test_spot_light_cone() {
+    let mut mini_gpu = make_dark_plane();
+    let dark = center_red(&mut mini_gpu);
+
+    // the cone of 0.3 radians around -y covers the origin
+    let mut uniform = SpotLightUniform {
+        position: [0., 1., 0.],
+        intensity: 5.,
+        ..Default::default()
+    };
+    uniform.set_cone_angles(0.2, 0.3);
+    let light = SpotLight::new(uniform);
+    add_light(&mut mini_gpu, Box::new(light));
+    let lit = center_red(&mut mini_gpu);
+    assert!(lit > dark + 100, "{} {}", dark, lit);
+
+    // tilted away, the origin is outside the cone
+    let mut mini_gpu = make_dark_plane();
+    uniform.direction = [1., -1., 0.];
+    let light = SpotLight::new(uniform);
+    add_light(&mut mini_gpu, Box::new(light));
+    assert_eq!(center_red(&mut mini_gpu), dark);
+}
+
+#[test]
+fn test_lights_add_up() {
+    let point = |x: f32| {
+        PointLight::new(PointLightUniform {
+            position: [x, 1., 0.],
+            intensity: 0.2,
+            ..Default::default()
+        })
+    };
+    let mut mini_gpu = make_dark_plane();
+    add_light(&mut mini_gpu, Box::new(point(-1.)));
+    let one = center_red(&mut mini_gpu);
+    for x in [1., -0.5, 0.5] {
+        add_light(&mut mini_gpu, Box::new(point(x)));
+    }
+    let four = center_red(&mut mini_gpu);
+    assert!(four > one + 30, "{} {}", one, four);
+}
+
+#[test]
+fn test_lights_limit() {
+    let lights = vec![LightData::default(); MAX_LIGHTS + 1];
+    let err = LightsUniform::new(&lights).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "the scene has {} lights, at most {} are supported",
+            MAX_LIGHTS + 1,
+            MAX_LIGHTS
+        )
+    );
+
+    // the default directional and ambient lights and MAX_LIGHTS point lights, the last two are dropped
+    let mut mini_gpu = make_dark_plane();
+    for _ in 0..MAX_LIGHTS {
+        let light = PointLight::new(PointLightUniform::default());
+        add_light(&mut mini_gpu, Box::new(light));
+    }
+    let (uniform, err) = MeshRender::collect_lights(&mini_gpu.scene);
+    assert_eq!(uniform.count, MAX_LIGHTS as u32);
+    assert_eq!(uniform.lights[0].kind, LIGHT_KIND_DIRECTIONAL);
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+
+    // logged on the first frame over the limit only, and again once it was back under
+    let report = LimitReport::default();
+    assert!(report.report(err));
+    assert!(!report.report(MeshRender::collect_lights(&mini_gpu.scene).1));
+    assert!(!report.report(None));
+    assert!(report.report(MeshRender::collect_lights(&mini_gpu.scene).1));
+}
+
+#[test]
+fn test_ambient_and_hemisphere_lights() {
+    let mut mini_gpu = make_dark_plane();
+    // the default ambient light only
+    let ambient = center_red(&mut mini_gpu);
+    let ambient_id = mini_gpu.scene.default_ambient_light.unwrap();
+    let light = mini_gpu
+        .scene
+        .get_entity_component_mut::<Box<dyn LightTrait>>(ambient_id, "light")
+        .unwrap();
+    let light = light.as_any_mut().downcast_mut::<AmbientLight>().unwrap();
+    light.uniform.intensity = 0.;
+    assert_eq!(center_red(&mut mini_gpu), 0);
+    assert!(ambient > 0);
+
+    // the plane faces the white sky, the ground is black
+    let light = HemisphereLight::new(HemisphereLightUniform::default());
+    add_light(&mut mini_gpu, Box::new(light));
+    let sky = center_red(&mut mini_gpu);
+    assert!(sky > ambient + 50, "{} {}", ambient, sky);
+
+    let mut mini_gpu = make_dark_plane();
+    let light = HemisphereLight::new(HemisphereLightUniform {
+        up: [0., -1., 0.],
+        ..Default::default()
+    });
+    add_light(&mut mini_gpu, Box::new(light));
+    assert_eq!(center_red(&mut mini_gpu), ambient);
+}
+
+/// member offsets and size of a wgsl struct
+pub(super) fn wgsl_struct_layout(source: &str, name: &str) -> (Vec<(String, u32)>, u32) {
+    let module = naga::front::wgsl::parse_str(source).unwrap();
+    let (_, ty) = module
+        .types
+        .iter()
+        .find(|(_, ty)| ty.name.as_deref() == Some(name))
+        .unwrap();
+    let naga::TypeInner::Struct { members, span } = &ty.inner else {
+        panic!("{} is not a struct", name);
+    };
+    let members = members
+        .iter()
+        .map(|member| (member.name.clone().unwrap(), member.offset))
+        .collect();
+    (members, *span)
+}
+
+#[test]
+fn test_light_layouts_match_wgsl() {
+    use std::mem::{offset_of, size_of};
+    // the Lights chunk includes the Shadows chunk
+    let source = &ShaderParser::new().parse_shader(&SHADER_LIB["Lights"]);
+
+    let (members, size) = wgsl_struct_layout(source, "Light");
+    let offsets = [
+        ("position", offset_of!(LightData, position)),
+        ("kind", offset_of!(LightData, kind)),
+        ("color", offset_of!(LightData, color)),
+        ("intensity", offset_of!(LightData, intensity)),
+        ("direction", offset_of!(LightData, direction)),
+        ("range", offset_of!(LightData, range)),
+        ("ground_color", offset_of!(LightData, ground_color)),
+        ("decay", offset_of!(LightData, decay)),
+        ("inner_cone_cos", offset_of!(LightData, inner_cone_cos)),
+        ("outer_cone_cos", offset_of!(LightData, outer_cone_cos)),
+    ]
+    .map(|(name, offset)| (name.to_string(), offset as u32));
+    assert_eq!(members, offsets);
+    assert_eq!(size as usize, size_of::<LightData>());
+
+    let (members, size) = wgsl_struct_layout(source, "Lights");
+    let offsets = [
+        ("count", offset_of!(LightsUniform, count)),
+        ("lights", offset_of!(LightsUniform, lights)),
+    ]
+    .map(|(name, offset)| (name.to_string(), offset as u32));
+    assert_eq!(members, offsets);
+    assert_eq!(size as usize, size_of::<LightsUniform>());
+}
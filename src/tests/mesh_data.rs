@@ -0,0 +1,94 @@
+use glam::{Mat4, Vec3};
+
+use crate::{
+    components::mesh::{Mesh, VertexFormat},
+    geometry::{
+        mesh_data::MeshData,
+        plane::{make_plane_data, MakePlaneConfig},
+        sphere::{make_sphere_data, MakeSphereConfig},
+    },
+};
+
+use super::headless::make_headless_mini_gpu;
+
+// a unit quad in the xy plane, uvs follow x and y
+fn quad() -> MeshData {
+    MeshData {
+        uvs: Some(vec![[0., 0.], [1., 0.], [1., 1.], [0., 1.]]),
+        ..MeshData::new(
+            vec![[0., 0., 0.], [1., 0., 0.], [1., 1., 0.], [0., 1., 0.]],
+            vec![0, 1, 2, 0, 2, 3],
+        )
+    }
+}
+
+#[test]
+fn test_mesh_data_normals_and_tangents() {
+    let mut data = quad();
+    data.compute_tangents().unwrap();
+    assert!(data
+        .normals
+        .unwrap()
+        .iter()
+        .all(|normal| *normal == [0., 0., 1.]));
+    assert!(data
+        .tangents
+        .unwrap()
+        .iter()
+        .all(|tangent| *tangent == [1., 0., 0., 1.]));
+
+    let mut data = quad();
+    data.compute_flat_normals();
+    assert_eq!(data.vertex_count(), 6);
+    assert_eq!(data.uvs.as_ref().unwrap()[4], [1., 1.]);
+    // welding the flat quad brings the shared corners back
+    data.weld(0.);
+    assert_eq!(data.vertex_count(), 4);
+    assert_eq!(data.indices.len(), 6);
+
+    assert!(MeshData::new(vec![], vec![]).compute_tangents().is_err());
+}
+
+#[test]
+fn test_mesh_data_merge_transform_and_bounds() {
+    let mut data = quad();
+    let mut other = quad();
+    other.colors = Some(vec![[1., 0., 0., 1.]; 4]);
+    // mirrored, so the winding is flipped to keep facing +z after the normal flips
+    other
+        .transform(Mat4::from_translation(Vec3::X * 2.) * Mat4::from_scale(Vec3::new(-1., 1., 1.)));
+    assert_eq!(other.indices[..3], [0, 2, 1]);
+    other.compute_smooth_normals();
+    assert_eq!(other.normals.as_ref().unwrap()[0], [0., 0., 1.]);
+
+    data.merge(&other);
+    assert_eq!(data.vertex_count(), 8);
+    assert_eq!(data.indices[6..9], [4, 6, 5]);
+    // the first quad had no colors or normals, they're filled in
+    assert_eq!(data.colors.as_ref().unwrap()[0], [1., 1., 1., 1.]);
+    assert_eq!(data.normals.as_ref().unwrap()[0], [0., 0., 1.]);
+    let bounds = data.bounds().unwrap();
+    assert_eq!(bounds.min, Vec3::ZERO);
+    assert_eq!(bounds.max, Vec3::new(2., 1., 0.));
+    assert!(MeshData::default().bounds().is_none());
+}
+
+#[test]
+fn test_mesh_from_data() {
+    let mini_gpu = make_headless_mini_gpu(32, 32);
+    let sphere = Mesh::from_data(
+        &make_sphere_data(MakeSphereConfig::default()),
+        &mini_gpu.renderer,
+    );
+    assert_eq!(sphere.vertex_format, VertexFormat::PositionNormalTexture);
+
+    let mut data = make_plane_data(MakePlaneConfig::default());
+    data.uvs = None;
+    data.colors = Some(vec![[1.; 4]; data.vertex_count()]);
+    let plane = Mesh::from_data(&data, &mini_gpu.renderer);
+    assert_eq!(plane.vertex_format, VertexFormat::PositionNormalColor);
+    assert_eq!(plane.vertices.len(), data.vertex_count() * 40);
+
+    let points = Mesh::from_data(&MeshData::new(vec![[0.; 3]], vec![0]), &mini_gpu.renderer);
+    assert_eq!(points.vertex_format, VertexFormat::PositionOnly);
+}
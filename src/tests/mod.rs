@@ -1,2 +1,38 @@
 #[cfg(test)]
+mod animation;
+#[cfg(test)]
+mod blinn_phong;
+#[cfg(test)]
+mod buffer;
+#[cfg(test)]
+mod gltf;
+#[cfg(test)]
+mod gltf_export;
+#[cfg(test)]
+mod headless;
+#[cfg(test)]
+mod instance;
+#[cfg(test)]
 mod learn;
+#[cfg(test)]
+mod lights;
+#[cfg(test)]
+mod mesh_data;
+#[cfg(test)]
+mod morph;
+#[cfg(test)]
+mod obj;
+#[cfg(test)]
+mod ply;
+#[cfg(test)]
+mod render_state;
+#[cfg(test)]
+mod scene;
+#[cfg(test)]
+mod shadow;
+#[cfg(test)]
+mod stl;
+#[cfg(test)]
+mod system;
+#[cfg(test)]
+mod transform;
@@ -0,0 +1,114 @@
+use crate::{
+    components::{
+        animation::AnimationPlayer,
+        lights::{directional_light::DirectionalLight, light::LightTrait},
+        mesh::Mesh,
+        morph::{MorphTarget, MorphTargets, MorphWeights},
+    },
+    system::{animation_system::AnimationSystem, mesh_render::MeshRender},
+    utils::gltf::load_gltf,
+};
+
+use super::{
+    gltf::{make_glb, triangle_bin},
+    headless::make_headless_mini_gpu,
+};
+
+#[test]
+fn test_gltf_morph_targets() {
+    // one target lifts the triangle above the camera, the animation turns it on over a second
+    let target_positions: [f32; 9] = [0., 5., 0., 0., 5., 0., 0., 5., 0.];
+    let times: [f32; 2] = [0., 1.];
+    let weights: [f32; 2] = [0., 1.];
+    let mut bin = triangle_bin();
+    bin.extend_from_slice(bytemuck::cast_slice(&target_positions));
+    bin.extend_from_slice(bytemuck::cast_slice(&times));
+    bin.extend_from_slice(bytemuck::cast_slice(&weights));
+    let json = r#"{
+  "asset": {"version": "2.0"},
+  "scenes": [{"nodes": [0]}],
+  "nodes": [{"name": "face", "mesh": 0}],
+  "meshes": [{"name": "tri", "weights": [0], "primitives": [{"attributes": {"POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2}, "indices": 3, "targets": [{"POSITION": 4}]}]}],
+  "animations": [{"name": "lift", "channels": [{"sampler": 0, "target": {"node": 0, "path": "weights"}}], "samplers": [{"input": 5, "output": 6}]}],
+  "buffers": [{"byteLength": 160}],
+  "bufferViews": [
+    {"buffer": 0, "byteOffset": 0, "byteLength": 36},
+    {"buffer": 0, "byteOffset": 36, "byteLength": 36},
+    {"buffer": 0, "byteOffset": 72, "byteLength": 24},
+    {"buffer": 0, "byteOffset": 96, "byteLength": 12},
+    {"buffer": 0, "byteOffset": 108, "byteLength": 36},
+    {"buffer": 0, "byteOffset": 144, "byteLength": 8},
+    {"buffer": 0, "byteOffset": 152, "byteLength": 8}
+  ],
+  "accessors": [
+    {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3", "min": [-1, 0, -1], "max": [1, 0, 1]},
+    {"bufferView": 1, "componentType": 5126, "count": 3, "type": "VEC3"},
+    {"bufferView": 2, "componentType": 5126, "count": 3, "type": "VEC2"},
+    {"bufferView": 3, "componentType": 5125, "count": 3, "type": "SCALAR"},
+    {"bufferView": 4, "componentType": 5126, "count": 3, "type": "VEC3", "min": [0, 5, 0], "max": [0, 5, 0]},
+    {"bufferView": 5, "componentType": 5126, "count": 2, "type": "SCALAR", "min": [0], "max": [1]},
+    {"bufferView": 6, "componentType": 5126, "count": 2, "type": "SCALAR"}
+  ]
+}"#;
+    let glb = make_glb(json, &bin);
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    mini_gpu.scene.background_color = wgpu::Color::BLACK;
+    let light_id = mini_gpu.scene.default_light.unwrap();
+    let light = mini_gpu
+        .scene
+        .get_entity_component_mut::<Box<dyn LightTrait>>(light_id, "light")
+        .unwrap();
+    let light = light
+        .as_any_mut()
+        .downcast_mut::<DirectionalLight>()
+        .unwrap();
+    light.uniform.direction = [0., -1., 0.];
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    let model_id = pollster::block_on(load_gltf(&glb, &mut mini_gpu)).unwrap();
+
+    let scene = &mini_gpu.scene;
+    let node = scene.get_entity(model_id).unwrap().children[0];
+    let primitive = scene.get_entity(node).unwrap().children[0];
+    let mesh = scene
+        .get_entity_component::<Mesh>(primitive, "mesh")
+        .unwrap();
+    assert_eq!(mesh.morph_targets.as_ref().unwrap().count, 1);
+    let weights = scene
+        .get_entity_component::<MorphWeights>(primitive, "weights")
+        .unwrap();
+    assert_eq!(weights.weights, vec![0.]);
+
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+    let image = mini_gpu.renderer.capture_image().unwrap();
+    assert!(image.get_pixel(16, 16).0[0] > 50);
+
+    let player = mini_gpu
+        .scene
+        .get_entity_component_mut::<AnimationPlayer>(model_id, "animation_player")
+        .unwrap();
+    player.play(0).looping = false;
+    AnimationSystem::animate(&mut mini_gpu.scene, 0.5);
+    let weights = mini_gpu
+        .scene
+        .get_entity_component::<MorphWeights>(primitive, "weights")
+        .unwrap();
+    assert_eq!(weights.weights, vec![0.5]);
+
+    // fully blended the triangle is out of view
+    AnimationSystem::animate(&mut mini_gpu.scene, 1.);
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+    let image = mini_gpu.renderer.capture_image().unwrap();
+    assert_eq!(image.get_pixel(16, 16).0[..3], [0, 0, 0]);
+}
+
+#[test]
+fn test_morph_texture_over_device_limit() {
+    let mini_gpu = make_headless_mini_gpu(16, 16);
+    let max_height = mini_gpu.renderer.device.limits().max_texture_dimension_2d as usize;
+    // 2 texels per vertex in rows of 2048, the size is checked before the targets are read
+    let vertex_count = max_height * 1024 + 1;
+    let result = MorphTargets::new(&mini_gpu.renderer, vertex_count, &[MorphTarget::default()]);
+    assert!(result.is_err());
+}
@@ -0,0 +1,286 @@
+use std::{collections::HashMap, io::Cursor};
+
+use glam::Vec3;
+
+use crate::{
+    components::{
+        material::MaterialTrait,
+        materials::{
+            blinn_phong::BlinnPhongMaterial,
+            render_state::{BlendMode, RenderState},
+        },
+        mesh::{Mesh, VertexFormat},
+    },
+    entity::EntityId,
+    mini_gpu::MiniGPU,
+    system::mesh_render::MeshRender,
+    utils::obj::load_obj_by_url,
+};
+
+use super::{gltf::find_child, headless::make_headless_mini_gpu};
+
+const MULTI_MATERIAL_OBJ: &str = "
+mtllib materials.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+o red
+usemtl red
+f 1/1/1 2/2/1 3/3/1
+o textured
+usemtl textured
+f 1/1/1 3/3/1 4/4/1
+o missing
+usemtl not_in_mtl
+f 1/1/1 2/2/1 4/4/1
+";
+
+const MATERIALS_MTL: &str = "
+newmtl textured
+Kd 1 1 1
+map_Kd textures/diffuse.png
+map_Bump -bm 0.5 textures/normal.png
+map_Ks textures/missing.png
+
+newmtl red
+Ka 0.1 0.1 0.1
+Kd 1 0 0
+Ks 0.5 0.5 0.5
+Ns 64
+Tr 0.25
+";
+
+fn material_config(mini_gpu: &MiniGPU, entity: EntityId) -> &BlinnPhongMaterial {
+    let material = mini_gpu
+        .scene
+        .get_entity_component::<Box<dyn MaterialTrait>>(entity, "material")
+        .unwrap();
+    material
+        .as_any_ref()
+        .downcast_ref::<BlinnPhongMaterial>()
+        .unwrap()
+}
+
+#[test]
+fn test_obj_multi_material() {
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    let mut png = Cursor::new(vec![]);
+    image::DynamicImage::new_rgba8(1, 1)
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .unwrap();
+    let png = png.into_inner();
+    let files: HashMap<String, &[u8]> = HashMap::from([
+        ("model.obj".to_string(), MULTI_MATERIAL_OBJ.as_bytes()),
+        ("materials.mtl".to_string(), MATERIALS_MTL.as_bytes()),
+        ("textures/diffuse.png".to_string(), png.as_slice()),
+        ("textures/normal.png".to_string(), png.as_slice()),
+    ]);
+    let model = pollster::block_on(load_obj_by_url("model.obj", &files, &mut mini_gpu)).unwrap();
+
+    // materials are matched by the name used in the obj, not by their order in the mtl
+    let red = find_child(&mini_gpu.scene, model, "red");
+    let config = &material_config(&mini_gpu, red).config;
+    assert_eq!(config.name, "red");
+    assert_eq!(config.ambient_color, Vec3::splat(0.1));
+    assert_eq!(config.diffuse_color, Vec3::new(1., 0., 0.));
+    assert_eq!(config.specular_color, Vec3::splat(0.5));
+    assert_eq!(config.shininess, 64.);
+    assert_eq!(config.opacity, 0.75);
+    assert_eq!(config.render_state.blend, BlendMode::Alpha);
+    assert!(!config.render_state.depth_write);
+    assert!(config.diffuse_texture.is_none());
+
+    let textured = find_child(&mini_gpu.scene, model, "textured");
+    let config = &material_config(&mini_gpu, textured).config;
+    assert_eq!(config.name, "textured");
+    assert!(config.use_texture);
+    assert_eq!(config.render_state, RenderState::default());
+    assert!(config.diffuse_texture.is_some());
+    assert!(config.normal_texture.is_some());
+    // a texture that can't be found is skipped
+    assert!(config.specular_texture.is_none());
+
+    let missing = find_child(&mini_gpu.scene, model, "missing");
+    assert_eq!(
+        material_config(&mini_gpu, missing).config.name,
+        "blinn_phong"
+    );
+}
+
+// two quads on the xz plane left and right of the origin, with and without uvs
+const SHARED_MATERIAL_OBJ: &str = "
+mtllib shared.mtl
+v -1 0 0.5
+v -0.2 0 0.5
+v -0.2 0 -0.5
+v -1 0 -0.5
+v 0.2 0 0.5
+v 1 0 0.5
+v 1 0 -0.5
+v 0.2 0 -0.5
+vt 0 0
+vn 0 1 0
+o uv
+usemtl red
+f 1/1/1 2/1/1 3/1/1 4/1/1
+o no_uv
+usemtl red
+f 5//1 6//1 7//1 8//1
+";
+
+#[test]
+fn test_obj_untextured_material_shared_by_vertex_formats() {
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    mini_gpu.scene.background_color = wgpu::Color::BLACK;
+    let files: HashMap<String, &[u8]> = HashMap::from([
+        ("model.obj".to_string(), SHARED_MATERIAL_OBJ.as_bytes()),
+        (
+            "shared.mtl".to_string(),
+            "newmtl red\nKd 1 0 0\n".as_bytes(),
+        ),
+    ]);
+    let model = pollster::block_on(load_obj_by_url("model.obj", &files, &mut mini_gpu)).unwrap();
+
+    let scene = &mini_gpu.scene;
+    let uv = find_child(scene, model, "uv");
+    let no_uv = find_child(scene, model, "no_uv");
+    let format = |id| {
+        scene
+            .get_entity_component::<Mesh>(id, "mesh")
+            .unwrap()
+            .vertex_format
+    };
+    assert_eq!(format(uv), VertexFormat::PositionNormalTexture);
+    assert_eq!(format(no_uv), VertexFormat::PositionNormal);
+    // one material per vertex layout, the pipeline is built for the first mesh drawn
+    let index = |id| scene.get_entity_component_index(id, "material").unwrap();
+    assert_ne!(index(uv), index(no_uv));
+    assert_eq!(material_config(&mini_gpu, no_uv).config.name, "red");
+
+    let camera = scene.get_default_camera().unwrap();
+    let view_projection = camera.get_projection_matrix() * camera.get_view_matrix();
+    let pixels = [-0.6, 0.6].map(|x| {
+        let ndc = view_projection.project_point3(Vec3::new(x, 0., 0.));
+        (((ndc.x + 1.) * 16.) as u32, ((1. - ndc.y) * 16.) as u32)
+    });
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+    let image = mini_gpu.renderer.capture_image().unwrap();
+    for (x, y) in pixels {
+        assert!(image.get_pixel(x, y).0[0] > 0, "{} {}", x, y);
+    }
+}
+
+// two quads folded along x = 1, no normals. the first model has uvs and a textured material
+const NO_NORMALS_OBJ: &str = "
+mtllib materials.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 1 0 -1
+v 1 1 -1
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+o uv
+usemtl textured
+s 1
+f 1/1 2/2 3/3 4/4
+f 2/1 5/2 6/3 3/4
+o smooth
+usemtl textured
+f 1 2 3 4
+f 2 5 6 3
+o flat
+s off
+f 1 2 3 4
+f 2 5 6 3
+";
+
+fn vertex_normals(mini_gpu: &MiniGPU, entity: EntityId) -> Vec<(Vec3, Vec3)> {
+    let mesh = mini_gpu
+        .scene
+        .get_entity_component::<Mesh>(entity, "mesh")
+        .unwrap();
+    let stride = mesh.vertex_buffer_layout.array_stride as usize;
+    mesh.vertices
+        .chunks_exact(stride)
+        .map(|vertex| {
+            let floats: &[f32] = bytemuck::cast_slice(vertex);
+            (
+                Vec3::from_slice(&floats[0..3]),
+                Vec3::from_slice(&floats[3..6]),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_obj_without_normals() {
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    let mut png = Cursor::new(vec![]);
+    image::DynamicImage::new_rgba8(1, 1)
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .unwrap();
+    let png = png.into_inner();
+    let files: HashMap<String, &[u8]> = HashMap::from([
+        ("model.obj".to_string(), NO_NORMALS_OBJ.as_bytes()),
+        ("materials.mtl".to_string(), MATERIALS_MTL.as_bytes()),
+        ("textures/diffuse.png".to_string(), png.as_slice()),
+        ("textures/normal.png".to_string(), png.as_slice()),
+    ]);
+    let model = pollster::block_on(load_obj_by_url("model.obj", &files, &mut mini_gpu)).unwrap();
+    // area weighted, so not exactly the diagonal
+    let is_averaged = |normal: Vec3| normal.x > 0.1 && normal.z > 0.1;
+
+    let uv = find_child(&mini_gpu.scene, model, "uv");
+    let mesh = mini_gpu
+        .scene
+        .get_entity_component::<Mesh>(uv, "mesh")
+        .unwrap();
+    assert_eq!(mesh.vertex_format, VertexFormat::PositionNormalTexture);
+    assert!(material_config(&mini_gpu, uv).config.use_texture);
+    // split by the uv seam, but still averaged over both quads
+    for (position, normal) in vertex_normals(&mini_gpu, uv) {
+        if position.x == 1. && position.z == 0. {
+            assert!(is_averaged(normal));
+        }
+    }
+
+    let smooth = find_child(&mini_gpu.scene, model, "smooth");
+    let mesh = mini_gpu
+        .scene
+        .get_entity_component::<Mesh>(smooth, "mesh")
+        .unwrap();
+    assert_eq!(mesh.vertex_format, VertexFormat::PositionNormal);
+    assert_eq!(mesh.indices.len(), 12);
+    // the textured material is swapped for an untextured copy
+    let config = &material_config(&mini_gpu, smooth).config;
+    assert_eq!(config.name, "textured");
+    assert!(!config.use_texture);
+    let normals = vertex_normals(&mini_gpu, smooth);
+    assert_eq!(normals.len(), 6);
+    assert!(normals.iter().any(|(_, normal)| is_averaged(*normal)));
+
+    let flat = find_child(&mini_gpu.scene, model, "flat");
+    let normals = vertex_normals(&mini_gpu, flat);
+    assert_eq!(normals.len(), 12);
+    assert!(normals
+        .iter()
+        .all(|(_, normal)| *normal == Vec3::Z || *normal == Vec3::X));
+
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+}
@@ -0,0 +1,117 @@
+use crate::{
+    components::{
+        material::MaterialTrait,
+        materials::{basic::BasicMaterial, blinn_phong::BlinnPhongMaterial},
+        mesh::{Mesh, VertexFormat},
+    },
+    system::mesh_render::MeshRender,
+    utils::ply::{load_ply, parse_ply},
+};
+
+use super::{gltf::find_child, headless::make_headless_mini_gpu};
+
+const COLORED_QUAD_PLY: &str = "ply
+format ascii 1.0
+comment a red and green quad
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 0 0 255 0 0
+1 1 0 0 255 0
+0 1 0 0 255 0
+4 0 1 2 3
+";
+
+#[test]
+fn test_load_ply_mesh() {
+    let ply = parse_ply(COLORED_QUAD_PLY.as_bytes()).unwrap();
+    assert_eq!(ply.indices, [0, 1, 2, 0, 2, 3]);
+    assert_eq!(ply.colors.as_ref().unwrap()[2], [0., 1., 0., 1.]);
+    assert!(ply.normals.is_none());
+    // a corrupt count runs out of data instead of allocating for it
+    let corrupt = COLORED_QUAD_PLY.replace("element vertex 4", "element vertex 99999999999");
+    assert!(parse_ply(corrupt.as_bytes()).is_err());
+
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    let model = load_ply(COLORED_QUAD_PLY.as_bytes(), &mut mini_gpu).unwrap();
+    let child = find_child(&mini_gpu.scene, model, "ply");
+    let mesh = mini_gpu
+        .scene
+        .get_entity_component::<Mesh>(child, "mesh")
+        .unwrap();
+    assert_eq!(mesh.vertex_format, VertexFormat::PositionNormalColor);
+    let material = mini_gpu
+        .scene
+        .get_entity_component::<Box<dyn MaterialTrait>>(child, "material")
+        .unwrap();
+    let material = material
+        .as_any_ref()
+        .downcast_ref::<BlinnPhongMaterial>()
+        .unwrap();
+    assert!(material.config.vertex_color);
+
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+}
+
+#[test]
+fn test_load_ply_point_cloud() {
+    // binary big endian, double positions and float colors, an extra element after the vertices
+    let header = "ply
+format binary_big_endian 1.0
+element vertex 3
+property double x
+property double y
+property double z
+property float r
+property float g
+property float b
+element camera 1
+property float view_px
+end_header
+";
+    let mut bytes = header.as_bytes().to_vec();
+    for i in 0..3 {
+        bytes.extend([i as f64, 0., 0.].map(f64::to_be_bytes).concat());
+        bytes.extend([1f32, 0.5, 0.].map(f32::to_be_bytes).concat());
+    }
+    bytes.extend(1f32.to_be_bytes());
+    let ply = parse_ply(&bytes).unwrap();
+    assert_eq!(ply.positions[2], [2., 0., 0.]);
+    assert_eq!(ply.colors.as_ref().unwrap()[1], [1., 0.5, 0., 1.]);
+    assert!(ply.indices.is_empty());
+
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    let model = load_ply(&bytes, &mut mini_gpu).unwrap();
+    let child = find_child(&mini_gpu.scene, model, "ply");
+    let mesh = mini_gpu
+        .scene
+        .get_entity_component::<Mesh>(child, "mesh")
+        .unwrap();
+    assert_eq!(mesh.vertex_format, VertexFormat::PositionColor);
+    assert_eq!(mesh.indices, [0, 1, 2]);
+    let material = mini_gpu
+        .scene
+        .get_entity_component::<Box<dyn MaterialTrait>>(child, "material")
+        .unwrap();
+    let material = material
+        .as_any_ref()
+        .downcast_ref::<BasicMaterial>()
+        .unwrap();
+    assert_eq!(material.config.topology, wgpu::PrimitiveTopology::PointList);
+
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+}
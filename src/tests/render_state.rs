@@ -0,0 +1,137 @@
+use crate::{
+    components::{
+        material::MaterialTrait,
+        materials::{
+            basic::{BasicMaterial, BasicMaterialConfig},
+            render_state::{BlendMode, RenderState},
+        },
+        mesh::Mesh,
+    },
+    entity::Entity,
+    mini_gpu::MiniGPU,
+    system::mesh_render::MeshRender,
+};
+
+use super::headless::make_headless_mini_gpu;
+
+/// a big triangle on the xz plane at height y, counter clockwise from the default camera unless flipped
+fn add_triangle(
+    mini_gpu: &mut MiniGPU,
+    y: f32,
+    flipped: bool,
+    color: [f32; 4],
+    render_state: RenderState,
+) {
+    let indices = if flipped {
+        vec![0, 2, 1]
+    } else {
+        vec![0, 1, 2]
+    };
+    let mesh = Mesh::new_position_only(
+        vec![-10., y, 10., 10., y, 10., 0., y, -10.],
+        indices,
+        &mini_gpu.renderer,
+    );
+    let material = BasicMaterial::new(
+        BasicMaterialConfig {
+            color,
+            render_state,
+            ..Default::default()
+        },
+        &mini_gpu.renderer,
+    );
+    let entity_id = mini_gpu.scene.add_entity(Entity::new());
+    mini_gpu.scene.set_entity_component(entity_id, mesh, "mesh");
+    mini_gpu
+        .scene
+        .set_entity_component::<Box<dyn MaterialTrait>>(entity_id, Box::new(material), "material");
+}
+
+fn render_center(mut mini_gpu: MiniGPU) -> [u8; 4] {
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+    let image = mini_gpu.renderer.capture_image().unwrap();
+    image.get_pixel(16, 16).0
+}
+
+fn make_scene(background_color: wgpu::Color) -> MiniGPU {
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    mini_gpu.scene.background_color = background_color;
+    mini_gpu
+}
+
+#[test]
+fn test_cull_mode_and_front_face() {
+    const GREEN: [f32; 4] = [0., 1., 0., 1.];
+    let cases = [
+        (RenderState::default(), [0, 255, 0, 255]),
+        (
+            RenderState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            [0, 0, 0, 255],
+        ),
+        (
+            RenderState {
+                cull_mode: Some(wgpu::Face::Back),
+                front_face: wgpu::FrontFace::Cw,
+                ..Default::default()
+            },
+            [0, 255, 0, 255],
+        ),
+    ];
+    for (render_state, expected) in cases {
+        let mut mini_gpu = make_scene(wgpu::Color::BLACK);
+        add_triangle(&mut mini_gpu, 0., true, GREEN, render_state);
+        assert_eq!(render_center(mini_gpu), expected, "{:?}", render_state);
+    }
+}
+
+#[test]
+fn test_blend_and_depth_test() {
+    let additive = RenderState {
+        blend: BlendMode::Additive,
+        ..Default::default()
+    };
+    let mut mini_gpu = make_scene(wgpu::Color::RED);
+    add_triangle(&mut mini_gpu, 0., false, [0., 1., 0., 1.], additive);
+    assert_eq!(render_center(mini_gpu), [255, 255, 0, 255]);
+
+    // the far triangle is drawn last, it only shows when it skips the depth test
+    for (depth_test, expected) in [(true, [0, 0, 255, 255]), (false, [0, 255, 0, 255])] {
+        let mut mini_gpu = make_scene(wgpu::Color::BLACK);
+        add_triangle(
+            &mut mini_gpu,
+            0.2,
+            false,
+            [0., 0., 1., 1.],
+            RenderState::default(),
+        );
+        let render_state = RenderState {
+            depth_test,
+            ..Default::default()
+        };
+        add_triangle(&mut mini_gpu, 0., false, [0., 1., 0., 1.], render_state);
+        assert_eq!(render_center(mini_gpu), expected);
+    }
+}
+
+#[test]
+fn test_wireframe_polygon_mode() {
+    let mut mini_gpu = make_scene(wgpu::Color::BLACK);
+    let render_state = RenderState {
+        polygon_mode: wgpu::PolygonMode::Line,
+        ..Default::default()
+    };
+    let supported = render_state.supported_polygon_mode(&mini_gpu.renderer.device);
+    add_triangle(&mut mini_gpu, 0., false, [0., 1., 0., 1.], render_state);
+    // only the edges are drawn, far from the center
+    let expected = match supported {
+        wgpu::PolygonMode::Line => [0, 0, 0, 255],
+        _ => [0, 255, 0, 255],
+    };
+    assert_eq!(render_center(mini_gpu), expected);
+}
@@ -0,0 +1,197 @@
+use std::{cell::Cell, rc::Rc};
+
+use crate::{component_store::ComponentError, entity::Entity, scene::Scene};
+
+#[test]
+fn test_entity_handles_stay_valid_after_remove() {
+    let mut scene = Scene::new();
+    let a = scene.add_default_entity();
+    let b = scene.add_default_entity();
+    let c = scene.add_default_entity();
+    scene.get_entity_mut(c).unwrap().name = "c".to_string();
+
+    assert!(scene.remove_entity(a).is_some());
+    // later handles still point to the same entity
+    assert_eq!(scene.get_entity(c).unwrap().name, "c");
+    assert!(scene.get_entity(b).is_some());
+    assert_eq!(scene.entity_count(), 2);
+}
+
+#[test]
+fn test_stale_entity_handle() {
+    let mut scene = Scene::new();
+    let a = scene.add_default_entity();
+    scene.remove_entity(a);
+    assert!(scene.get_entity(a).is_none());
+    assert!(scene.remove_entity(a).is_none());
+
+    // the freed slot is reused with a new generation
+    let b = scene.add_default_entity();
+    assert_eq!(a.index, b.index);
+    assert_ne!(a, b);
+    assert!(scene.get_entity(a).is_none());
+    assert!(scene.get_entity(b).is_some());
+}
+
+#[test]
+fn test_stale_handle_add_child_and_set_component_index() {
+    let mut scene = Scene::new();
+    let parent = scene.add_default_entity();
+    let shared = scene.add_component(String::from("shared"));
+    scene.remove_entity(parent);
+    scene.add_default_entity();
+
+    assert!(matches!(
+        scene.add_entity_child(parent, Entity::new()),
+        Err(ComponentError::EntityNotFound(id)) if id == parent
+    ));
+    // nothing is added for the stale parent
+    assert_eq!(scene.entity_count(), 1);
+    assert!(matches!(
+        scene.set_entity_component_index(parent, shared, "material"),
+        Err(ComponentError::EntityNotFound(id)) if id == parent
+    ));
+}
+
+#[test]
+fn test_remove_entity_despawns_children() {
+    let mut scene = Scene::new();
+    let root = scene.add_default_entity();
+    let child = scene.add_entity_child(root, Entity::new()).unwrap();
+    let grand_child = scene.add_entity_child(child, Entity::new()).unwrap();
+    let other = scene.add_default_entity();
+
+    assert_eq!(scene.get_entity(grand_child).unwrap().parent, Some(child));
+    scene.remove_entity(child);
+    assert!(scene.get_entity(child).is_none());
+    assert!(scene.get_entity(grand_child).is_none());
+    assert!(scene.get_entity(root).unwrap().children.is_empty());
+    assert!(scene.get_entity(other).is_some());
+    assert_eq!(scene.entity_count(), 2);
+}
+
+#[test]
+fn test_default_camera_handle_invalidated() {
+    let mut scene = Scene::new();
+    let camera = scene.add_default_entity();
+    scene.default_camera = Some(camera);
+    scene.remove_entity(camera);
+    scene.add_default_entity();
+    assert!(scene.get_default_camera().is_none());
+}
+
+#[test]
+fn test_query_components() {
+    let mut scene = Scene::new();
+    let a = scene.add_default_entity();
+    let b = scene.add_default_entity();
+    let c = scene.add_default_entity();
+    scene.set_entity_component(a, 1u32, "id");
+    scene.set_entity_component(a, String::from("a"), "name");
+    scene.set_entity_component(b, 2u32, "id");
+    scene.set_entity_component(c, String::from("c"), "name");
+
+    let ids: Vec<_> = scene.query::<&u32>().map(|(id, value)| (id, *value)).collect();
+    assert_eq!(ids, vec![(a, 1), (b, 2)]);
+
+    let both: Vec<_> = scene.query::<(&u32, &String)>().collect();
+    assert_eq!(both.len(), 1);
+    assert_eq!(both[0].0, a);
+
+    let optional: Vec<_> = scene
+        .query::<(&u32, Option<&String>)>()
+        .map(|(_, (_, name))| name.cloned())
+        .collect();
+    assert_eq!(optional, vec![Some("a".to_string()), None]);
+
+    assert_eq!(scene.query::<&f32>().count(), 0);
+}
+
+#[test]
+fn test_component_type_mismatch() {
+    let mut scene = Scene::new();
+    let entity = scene.add_default_entity();
+    scene.set_entity_component(entity, 1.0f32, "value");
+    assert!(matches!(
+        scene.get_entity_component::<u32>(entity, "value"),
+        Err(ComponentError::TypeMismatch { .. })
+    ));
+    assert!(matches!(
+        scene.get_entity_component::<f32>(entity, "missing"),
+        Err(ComponentError::Missing(_))
+    ));
+    *scene.get_entity_component_mut::<f32>(entity, "value").unwrap() = 2.0;
+    assert_eq!(*scene.get_entity_component::<f32>(entity, "value").unwrap(), 2.0);
+}
+
+#[test]
+fn test_drop_component_keeps_other_ids() {
+    let mut scene = Scene::new();
+    let a = scene.add_component(String::from("a"));
+    let b = scene.add_component(String::from("b"));
+    let shared = scene.add_component(String::from("shared"));
+    let e1 = scene.add_default_entity();
+    let e2 = scene.add_default_entity();
+    scene.set_entity_component_index(e1, shared, "material").unwrap();
+    scene.set_entity_component_index(e2, shared, "material").unwrap();
+
+    scene.drop_component::<String>(a).unwrap();
+    assert!(matches!(
+        scene.get_component::<String>(a),
+        Err(ComponentError::NotFound(_))
+    ));
+    assert_eq!(scene.get_component::<String>(b).unwrap(), "b");
+    assert_eq!(
+        scene.get_entity_component::<String>(e2, "material").unwrap(),
+        "shared"
+    );
+
+    // the freed slot is reused, the old id stays stale
+    let c = scene.add_component(String::from("c"));
+    assert_eq!(a.index, c.index);
+    assert!(scene.get_component::<String>(a).is_err());
+    assert_eq!(scene.get_component::<String>(c).unwrap(), "c");
+}
+
+struct DropCounter(Rc<Cell<u32>>);
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+#[test]
+fn test_components_dropped_with_scene() {
+    let count = Rc::new(Cell::new(0));
+    let mut scene = Scene::new();
+    let entity = scene.add_default_entity();
+    scene.set_entity_component(entity, DropCounter(count.clone()), "counter");
+    let id = scene.add_component(DropCounter(count.clone()));
+    scene.drop_component::<DropCounter>(id).unwrap();
+    assert_eq!(count.get(), 1);
+    drop(scene);
+    assert_eq!(count.get(), 2);
+}
+
+#[test]
+fn test_remove_entity_drops_unused_components() {
+    let count = Rc::new(Cell::new(0));
+    let mut scene = Scene::new();
+    let root = scene.add_default_entity();
+    let child = scene.add_entity_child(root, Entity::new()).unwrap();
+    let other = scene.add_default_entity();
+    scene.set_entity_component(root, DropCounter(count.clone()), "counter");
+    scene.set_entity_component(child, DropCounter(count.clone()), "counter");
+    let shared = scene.set_entity_component(child, String::from("shared"), "material");
+    scene.set_entity_component_index(other, shared, "material").unwrap();
+
+    scene.remove_entity(root);
+    assert_eq!(count.get(), 2);
+    assert_eq!(scene.component_count::<DropCounter>(), 0);
+    // still used by other
+    assert_eq!(
+        scene.get_entity_component::<String>(other, "material").unwrap(),
+        "shared"
+    );
+}
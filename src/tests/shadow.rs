@@ -0,0 +1,259 @@
+use glam::{Mat4, Quat, Vec3, Vec4};
+
+use crate::{
+    components::{
+        lights::{
+            directional_light::{DirectionalLight, DirectionalLightUniform},
+            light::LightTrait,
+            spot_light::{SpotLight, SpotLightUniform},
+        },
+        material::MaterialTrait,
+        materials::{
+            pbr::{PBRMaterial, PBRMaterialConfig},
+            shader::ShaderParser,
+            shaderlib::SHADER_LIB,
+        },
+        mesh::{Mesh, VertexFormat, VertexPositionNormal},
+        shadow::{
+            cascade_splits, directional_shadow_views, ShadowConfig, ShadowFit, ShadowMapData,
+            ShadowsUniform,
+        },
+        transform::Transform,
+    },
+    entity::Entity,
+    mini_gpu::MiniGPU,
+    system::mesh_render::MeshRender,
+};
+
+use super::{headless::make_headless_mini_gpu, lights::wgsl_struct_layout};
+
+#[test]
+fn test_cascade_splits() {
+    let uniform = cascade_splits(1., 9., 4, 0.);
+    assert_eq!(uniform, vec![3., 5., 7., 9.]);
+
+    let logarithmic = cascade_splits(1., 8., 3, 1.);
+    for (split, expected) in logarithmic.iter().zip([2., 4., 8.]) {
+        assert!((split - expected).abs() < 1e-4, "{:?}", logarithmic);
+    }
+}
+
+#[test]
+fn test_cascades_cover_camera_frustum() {
+    let view = Mat4::look_at_rh(Vec3::new(3., 2., 5.), Vec3::ZERO, Vec3::Y);
+    let projection = Mat4::perspective_rh(1.2, 1.5, 0.1, 100.);
+    let config = ShadowConfig {
+        fit: ShadowFit::Camera {
+            max_distance: 40.,
+            cascades: 3,
+            split_lambda: 0.7,
+            caster_distance: 10.,
+        },
+        ..Default::default()
+    };
+    let direction = Vec3::new(-1., -2., -0.5);
+    let views = directional_shadow_views(direction, &config, view, projection);
+    assert_eq!(views.len(), 3);
+    assert!(views
+        .windows(2)
+        .all(|w| w[0].split_depth < w[1].split_depth));
+    assert!((views[2].split_depth - 40.).abs() < 1e-3);
+
+    // every point of the view up to max_distance lands in the map of its cascade
+    let inverse_view = view.inverse();
+    let (x_scale, y_scale) = (1. / projection.x_axis.x, 1. / projection.y_axis.y);
+    for depth in [0.1, 0.5, 2., 7., 15., 30., 40.] {
+        let shadow_view = views
+            .iter()
+            .find(|shadow_view| depth <= shadow_view.split_depth + 1e-3)
+            .unwrap();
+        for (x, y) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.), (0., 0.)] {
+            let point = Vec3::new(x * x_scale * depth, y * y_scale * depth, -depth);
+            let world = inverse_view.transform_point3(point);
+            let light = shadow_view.view_projection.project_point3(world);
+            assert!(
+                light.x.abs() <= 1. && light.y.abs() <= 1.,
+                "{} {}",
+                depth,
+                light
+            );
+            assert!((0. ..=1.).contains(&light.z), "{} {}", depth, light);
+        }
+    }
+}
+
+/// a red plane on xz receiving shadows and an invisible caster above the origin,
+/// lit by nothing but the ambient light
+fn make_shadow_scene() -> MiniGPU {
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    mini_gpu.scene.background_color = wgpu::Color::BLACK;
+    let light_id = mini_gpu.scene.default_light.unwrap();
+    let light = mini_gpu
+        .scene
+        .get_entity_component_mut::<Box<dyn LightTrait>>(light_id, "light")
+        .unwrap();
+    let light = light
+        .as_any_mut()
+        .downcast_mut::<DirectionalLight>()
+        .unwrap();
+    light.uniform.intensity = 0.;
+
+    let plane = make_mesh(&mut mini_gpu, 10., 0.);
+    let material = PBRMaterial::new(
+        PBRMaterialConfig {
+            base_color_factor: Vec4::new(1., 0., 0., 1.),
+            metallic_factor: 0.,
+            roughness_factor: 0.8,
+            ..Default::default()
+        },
+        &mini_gpu.renderer,
+    );
+    let plane_id = mini_gpu.scene.add_entity(Entity {
+        cast_shadow: true,
+        receive_shadow: true,
+        ..Default::default()
+    });
+    mini_gpu.scene.set_entity_component(plane_id, plane, "mesh");
+    mini_gpu
+        .scene
+        .set_entity_component::<Box<dyn MaterialTrait>>(plane_id, Box::new(material), "material");
+
+    // no material, only drawn into the shadow maps
+    let caster = make_mesh(&mut mini_gpu, 0.3, 0.);
+    let caster_id = mini_gpu.scene.add_entity(Entity {
+        cast_shadow: true,
+        ..Default::default()
+    });
+    mini_gpu
+        .scene
+        .set_entity_component(caster_id, caster, "mesh");
+    let transform = Transform::new(
+        &mini_gpu.renderer,
+        Vec3::new(0., 0.5, 0.),
+        Quat::IDENTITY,
+        Vec3::ONE,
+    );
+    mini_gpu
+        .scene
+        .set_entity_component(caster_id, transform, "transform");
+
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu
+}
+
+/// a square on xz at height y, facing up
+fn make_mesh(mini_gpu: &mut MiniGPU, half_size: f32, y: f32) -> Mesh {
+    let vertices: Vec<VertexPositionNormal> = [[-1., 1.], [1., 1.], [1., -1.], [-1., -1.]]
+        .into_iter()
+        .map(|[x, z]| VertexPositionNormal {
+            position: [x * half_size, y, z * half_size],
+            normal: [0., 1., 0.],
+        })
+        .collect();
+    Mesh::new(
+        bytemuck::cast_slice(&vertices),
+        vec![0, 1, 2, 0, 2, 3],
+        VertexFormat::PositionNormal,
+        &mini_gpu.renderer,
+    )
+}
+
+fn add_light(mini_gpu: &mut MiniGPU, light: Box<dyn LightTrait>) {
+    let entity_id = mini_gpu.scene.add_entity(Entity::new());
+    mini_gpu
+        .scene
+        .set_entity_component::<Box<dyn LightTrait>>(entity_id, light, "light");
+}
+
+/// red of the pixel at the origin and of one on the plane away from the caster
+fn render_red(mini_gpu: &mut MiniGPU) -> (u8, u8) {
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+    let image = mini_gpu.renderer.capture_image().unwrap();
+    (image.get_pixel(16, 16).0[0], image.get_pixel(16, 28).0[0])
+}
+
+fn set_receive_shadow(mini_gpu: &mut MiniGPU, receive_shadow: bool) {
+    let plane_id = mini_gpu
+        .scene
+        .query::<&Box<dyn MaterialTrait>>()
+        .map(|(entity_id, _)| entity_id)
+        .next()
+        .unwrap();
+    mini_gpu
+        .scene
+        .get_entity_mut(plane_id)
+        .unwrap()
+        .receive_shadow = receive_shadow;
+}
+
+#[test]
+fn test_directional_light_shadow() {
+    let mut mini_gpu = make_shadow_scene();
+    let light = DirectionalLight::new(DirectionalLightUniform {
+        direction: [0., -1., 0.],
+        color: [1., 1., 1.],
+        intensity: 1.,
+    })
+    .with_shadow(ShadowConfig::default());
+    add_light(&mut mini_gpu, Box::new(light));
+
+    let (shadowed, lit) = render_red(&mut mini_gpu);
+    assert!(lit > shadowed + 50, "{} {}", shadowed, lit);
+
+    set_receive_shadow(&mut mini_gpu, false);
+    let (center, _) = render_red(&mut mini_gpu);
+    assert!(center > shadowed + 50, "{} {}", shadowed, center);
+}
+
+#[test]
+fn test_spot_light_shadow() {
+    let mut mini_gpu = make_shadow_scene();
+    let mut uniform = SpotLightUniform {
+        position: [0., 3., 0.],
+        intensity: 20.,
+        ..Default::default()
+    };
+    uniform.set_cone_angles(0.6, 0.8);
+    let light = SpotLight::new(uniform).with_shadow(ShadowConfig::default());
+    add_light(&mut mini_gpu, Box::new(light));
+
+    let (shadowed, lit) = render_red(&mut mini_gpu);
+    assert!(lit > shadowed + 100, "{} {}", shadowed, lit);
+}
+
+#[test]
+fn test_shadow_layouts_match_wgsl() {
+    use std::mem::{offset_of, size_of};
+    let source = ShaderParser::new().parse_shader(&SHADER_LIB["Lights"]);
+
+    let (members, size) = wgsl_struct_layout(&source, "ShadowMap");
+    let offsets = [
+        (
+            "view_projection",
+            offset_of!(ShadowMapData, view_projection),
+        ),
+        ("light_index", offset_of!(ShadowMapData, light_index)),
+        ("split_depth", offset_of!(ShadowMapData, split_depth)),
+        ("bias", offset_of!(ShadowMapData, bias)),
+        ("normal_offset", offset_of!(ShadowMapData, normal_offset)),
+        ("pcf_radius", offset_of!(ShadowMapData, pcf_radius)),
+        ("perspective", offset_of!(ShadowMapData, perspective)),
+        ("_padding", offset_of!(ShadowMapData, _padding)),
+    ]
+    .map(|(name, offset)| (name.to_string(), offset as u32));
+    assert_eq!(members, offsets);
+    assert_eq!(size as usize, size_of::<ShadowMapData>());
+
+    let (members, size) = wgsl_struct_layout(&source, "Shadows");
+    let offsets = [
+        ("count", offset_of!(ShadowsUniform, count)),
+        ("texel_size", offset_of!(ShadowsUniform, texel_size)),
+        ("_padding", offset_of!(ShadowsUniform, _padding)),
+        ("maps", offset_of!(ShadowsUniform, maps)),
+    ]
+    .map(|(name, offset)| (name.to_string(), offset as u32));
+    assert_eq!(members, offsets);
+    assert_eq!(size as usize, size_of::<ShadowsUniform>());
+}
@@ -0,0 +1,64 @@
+use crate::{
+    components::mesh::{Mesh, VertexFormat},
+    system::mesh_render::MeshRender,
+    utils::stl::{load_stl, parse_stl},
+};
+
+use super::{gltf::find_child, headless::make_headless_mini_gpu};
+
+const ASCII_STL: &str = "solid quad
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 1 1 0
+    endloop
+  endfacet
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 1 0
+      vertex 0 1 0
+    endloop
+  endfacet
+endsolid quad
+";
+
+#[test]
+fn test_load_stl() {
+    let ascii = parse_stl(ASCII_STL.as_bytes()).unwrap();
+    assert_eq!(ascii.len(), 1);
+    assert_eq!(ascii[0].name, "quad");
+
+    // the same triangles as binary, the header starts with "solid" like many exporters do
+    let mut binary = b"solid quad".to_vec();
+    binary.resize(80, 0);
+    binary.extend(2u32.to_le_bytes());
+    for triangle in ascii[0].positions.chunks_exact(3) {
+        binary.extend([0f32, 0., 1.].map(f32::to_le_bytes).concat());
+        for position in triangle {
+            binary.extend(position.map(f32::to_le_bytes).concat());
+        }
+        binary.extend([0, 0]);
+    }
+    let solids = parse_stl(&binary).unwrap();
+    assert_eq!(solids[0].name, "quad");
+    assert_eq!(solids[0].positions, ascii[0].positions);
+
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    let model = load_stl(&binary, &mut mini_gpu).unwrap();
+    let quad = find_child(&mini_gpu.scene, model, "quad");
+    let mesh = mini_gpu
+        .scene
+        .get_entity_component::<Mesh>(quad, "mesh")
+        .unwrap();
+    assert_eq!(mesh.vertex_format, VertexFormat::PositionNormal);
+    assert_eq!(mesh.indices.len(), 6);
+    let normal: &[f32] = bytemuck::cast_slice(&mesh.vertices[12..24]);
+    assert_eq!(normal, [0., 0., 1.]);
+
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+}
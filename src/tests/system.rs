@@ -0,0 +1,75 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    scene::Scene,
+    system::{
+        mesh_render::MeshRender,
+        system::{FrameContext, Stage, System},
+    },
+};
+
+use super::headless::make_headless_mini_gpu;
+
+struct RecordSystem {
+    name: &'static str,
+    stage: Stage,
+    log: Rc<RefCell<Vec<(&'static str, u64)>>>,
+}
+
+impl System for RecordSystem {
+    fn update(&mut self, ctx: &mut FrameContext, _scene: &mut Scene) {
+        self.log.borrow_mut().push((self.name, ctx.frame_index));
+    }
+
+    fn stage(&self) -> Stage {
+        self.stage
+    }
+}
+
+#[test]
+fn test_systems_run_in_stage_order() {
+    let mut mini_gpu = make_headless_mini_gpu(16, 16);
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let record = |name, stage| {
+        Box::new(RecordSystem {
+            name,
+            stage,
+            log: log.clone(),
+        })
+    };
+    let renderer = &mut mini_gpu.renderer;
+    renderer.add_system("render".to_string(), Box::new(MeshRender {}));
+    renderer.add_system("late".to_string(), record("late", Stage::PostUpdate));
+    renderer.add_system("first".to_string(), record("first", Stage::Update));
+    renderer.add_system("second".to_string(), record("second", Stage::Update));
+    renderer.add_system("pre".to_string(), record("pre", Stage::PreUpdate));
+    assert_eq!(
+        renderer.schedule.names().collect::<Vec<_>>(),
+        vec![
+            "pre",
+            "animation",
+            "first",
+            "second",
+            "transform",
+            "skin",
+            "morph",
+            "late",
+            "render"
+        ]
+    );
+
+    renderer.render(&mut mini_gpu.scene).unwrap();
+    renderer.render(&mut mini_gpu.scene).unwrap();
+    let names = ["pre", "first", "second", "late"];
+    let expected: Vec<_> = (0..2)
+        .flat_map(|frame| names.iter().map(move |name| (*name, frame)))
+        .collect();
+    assert_eq!(*log.borrow(), expected);
+    assert_eq!(renderer.frame_index, 2);
+
+    // replacing a system keeps a single entry
+    renderer.add_system("first".to_string(), record("first", Stage::Render));
+    assert_eq!(renderer.schedule.names().last(), Some("first"));
+    assert!(renderer.remove_system("first").is_some());
+    assert!(!renderer.schedule.contains("first"));
+}
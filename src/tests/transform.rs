@@ -0,0 +1,104 @@
+use glam::{Mat4, Vec3};
+
+use crate::{
+    components::{
+        material::MaterialTrait,
+        materials::blinn_phong::{BlinnPhongMaterial, BlinnPhongMaterialConfig},
+        mesh::{Mesh, VertexFormat, VertexPositionNormal},
+        transform::Transform,
+    },
+    entity::{Entity, EntityId},
+    mini_gpu::MiniGPU,
+    system::mesh_render::MeshRender,
+};
+
+use super::headless::make_headless_mini_gpu;
+
+// a big triangle on the xz plane, seen from the default camera at (0, 1, 1)
+fn add_ground(mini_gpu: &mut MiniGPU, parent_id: EntityId) -> EntityId {
+    let vertices = [[-10., 0., 10.], [10., 0., 10.], [0., 0., -10.]].map(|position| {
+        VertexPositionNormal {
+            position,
+            normal: [0., 1., 0.],
+        }
+    });
+    let mesh = Mesh::new(
+        bytemuck::cast_slice(&vertices),
+        vec![0, 1, 2],
+        VertexFormat::PositionNormal,
+        &mini_gpu.renderer,
+    );
+    let material = BlinnPhongMaterial::new(
+        BlinnPhongMaterialConfig {
+            diffuse_color: Vec3::new(1., 1., 1.),
+            ..Default::default()
+        },
+        &mini_gpu.renderer,
+    );
+    let child_id = mini_gpu.scene.add_entity_child(parent_id, Entity::new()).unwrap();
+    mini_gpu.scene.set_entity_component(child_id, mesh, "mesh");
+    mini_gpu.scene.set_entity_component::<Box<dyn MaterialTrait>>(
+        child_id,
+        Box::new(material),
+        "material",
+    );
+    let transform = Transform::default(&mini_gpu.renderer);
+    mini_gpu
+        .scene
+        .set_entity_component(child_id, transform, "transform");
+    child_id
+}
+
+#[test]
+fn test_transform_setters_update_matrix() {
+    let mini_gpu = make_headless_mini_gpu(4, 4);
+    let mut transform = Transform::default(&mini_gpu.renderer);
+    transform.set_position(Vec3::new(1., 2., 3.));
+    transform.set_scale(Vec3::new(2., 2., 2.));
+    assert_eq!(
+        transform.matrix,
+        Mat4::from_scale_rotation_translation(
+            Vec3::new(2., 2., 2.),
+            glam::Quat::IDENTITY,
+            Vec3::new(1., 2., 3.)
+        )
+    );
+}
+
+#[test]
+fn test_parent_transform_moves_children() {
+    let mut mini_gpu = make_headless_mini_gpu(32, 32);
+    mini_gpu.scene.background_color = wgpu::Color::BLACK;
+    let parent_id = mini_gpu.scene.add_default_entity();
+    let transform = Transform::default(&mini_gpu.renderer);
+    mini_gpu
+        .scene
+        .set_entity_component(parent_id, transform, "transform");
+    let child_id = add_ground(&mut mini_gpu, parent_id);
+    mini_gpu
+        .renderer
+        .add_system("render".to_string(), Box::new(MeshRender {}));
+
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+    let image = mini_gpu.renderer.capture_image().unwrap();
+    assert_ne!(image.get_pixel(16, 16).0, [0, 0, 0, 255]);
+
+    // move the parent out of view, the child should follow
+    mini_gpu
+        .scene
+        .get_entity_component_mut::<Transform>(parent_id, "transform")
+        .unwrap()
+        .set_position(Vec3::new(0., 100., 0.));
+    mini_gpu.renderer.render(&mut mini_gpu.scene).unwrap();
+    let image = mini_gpu.renderer.capture_image().unwrap();
+    assert_eq!(image.get_pixel(16, 16).0, [0, 0, 0, 255]);
+
+    let child_transform = mini_gpu
+        .scene
+        .get_entity_component::<Transform>(child_id, "transform")
+        .unwrap();
+    assert_eq!(
+        child_transform.global_matrix,
+        Mat4::from_translation(Vec3::new(0., 100., 0.))
+    );
+}
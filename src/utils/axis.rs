@@ -1,7 +1,7 @@
 use crate::{
     components::{
         material::{Material, MaterialConfig, MaterialTrait},
-        materials::shader::ShaderParser,
+        materials::{render_state::RenderState, shader::ShaderParser},
         mesh::Mesh,
     },
     entity,
@@ -49,6 +49,7 @@ pub fn add_xyz_line(mini_gpu: &mut MiniGPU, user_size: Option<f32>) {
                 .to_string(),
             topology: wgpu::PrimitiveTopology::LineList,
             uniforms: vec![0.],
+            render_state: RenderState::transparent(),
         },
         &mini_gpu.renderer,
     );
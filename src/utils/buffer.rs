@@ -0,0 +1,26 @@
+use crate::renderer::Renderer;
+
+/// write contents at the start of buffer. a buffer that is too small is replaced by one of the same usage
+/// with at least twice its size, so growing data doesn't reallocate every frame.
+/// returns true when the buffer was replaced
+pub fn write_or_grow(
+    renderer: &Renderer,
+    buffer: &mut wgpu::Buffer,
+    contents: &[u8],
+    label: &str,
+) -> bool {
+    let grown = contents.len() as u64 > buffer.size();
+    if grown {
+        let size = (contents.len() as u64).max(buffer.size() * 2);
+        *buffer = renderer.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: wgpu::util::align_to(size, wgpu::COPY_BUFFER_ALIGNMENT),
+            usage: buffer.usage(),
+            mapped_at_creation: false,
+        });
+    }
+    if !contents.is_empty() {
+        renderer.queue.write_buffer(buffer, 0, contents);
+    }
+    grown
+}
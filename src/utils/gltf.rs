@@ -1,13 +1,46 @@
-use anyhow::Ok;
-use gltf::{buffer::Data, iter::Materials, Document};
-use image::GenericImage;
-use wgpu::util::DeviceExt;
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+use gltf::{
+    animation::util::ReadOutputs,
+    buffer::Data,
+    image::Format,
+    iter::Materials,
+    texture::{MagFilter, MinFilter, WrappingMode},
+    Document,
+};
+use image::{DynamicImage, ImageBuffer};
 
 use crate::{
-    components::{material::MaterialTrait, materials, mesh::Mesh},
-    entity::Entity,
+    component_store::ComponentId,
+    components::{
+        animation::{
+            AnimationChannel, AnimationClip, AnimationPlayer, AnimationProperty, Interpolation,
+        },
+        lights::{
+            directional_light::{DirectionalLight, DirectionalLightUniform},
+            light::LightTrait,
+            point_light::{PointLight, PointLightUniform},
+            spot_light::{SpotLight, SpotLightUniform},
+        },
+        material::MaterialTrait,
+        materials::pbr::{AlphaMode, PBRMaterial, PBRMaterialConfig, TextureTransform},
+        mesh::{
+            Mesh, VertexFormat, VertexPositionNormal, VertexPositionNormalTexture,
+            VertexPositionNormalTextureSkin,
+        },
+        morph::{MorphTarget, MorphTargets, MorphWeights, MAX_MORPH_TARGETS},
+        orthographic_camera::{OrthographicCamera, OrthographicCameraConfig},
+        perspective_camera::{CameraTrait, PerspectiveCamera, PerspectiveCameraConfig},
+        skin::{Skin, MAX_JOINTS},
+        transform::Transform,
+    },
+    entity::{Entity, EntityId},
+    geometry::normals::flat_normals,
     mini_gpu::MiniGPU,
     renderer::Renderer,
+    scene::DEFAULT_CAMERA_BIND_INDEX,
 };
 
 use super::texture::Texture;
@@ -19,156 +52,944 @@ pub struct ModelVertex {
     pub normal: [f32; 3],
 }
 
-pub async fn load_gltf(glb_model: &[u8], mini_gpu: &mut MiniGPU) -> anyhow::Result<usize> {
-    // check obj_materials result
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GltfLoadOptions {
+    /// make the first imported camera the scene's default_camera
+    pub use_camera: bool,
+}
+
+/// load a glb/gltf file, nodes of the default scene become child entities of the returned entity
+/// with their local transforms, meshes used by several nodes share the same Mesh component
+pub async fn load_gltf(glb_model: &[u8], mini_gpu: &mut MiniGPU) -> anyhow::Result<EntityId> {
+    load_gltf_with_options(glb_model, mini_gpu, GltfLoadOptions::default()).await
+}
+
+pub async fn load_gltf_with_options(
+    glb_model: &[u8],
+    mini_gpu: &mut MiniGPU,
+    options: GltfLoadOptions,
+) -> anyhow::Result<EntityId> {
     let (gltf, buffers, images) = gltf::import_slice(glb_model)?;
-    let materials = gltf.materials();
-    let materials_map = make_material_map(materials, images, &mini_gpu.renderer)?;
+    let materials = make_material_map(gltf.materials(), &images, &mini_gpu.renderer)?;
 
-    let parent_id = &mini_gpu.scene.add_default_entity();
-    append_mesh_children(*parent_id, mini_gpu, &gltf, &buffers, materials_map);
-    Ok(*parent_id)
+    let parent_id = mini_gpu.scene.add_default_entity();
+    let transform = Transform::default(&mini_gpu.renderer);
+    mini_gpu
+        .scene
+        .set_entity_component(parent_id, transform, "transform");
+    append_scene_nodes(
+        parent_id, mini_gpu, &gltf, &buffers, &images, materials, options,
+    )?;
+    Ok(parent_id)
 }
 
-pub fn make_material_map<'a>(
+pub fn make_material_map(
     materials: Materials,
-    images: Vec<gltf::image::Data>,
+    images: &[gltf::image::Data],
     renderer: &Renderer,
 ) -> anyhow::Result<Vec<Box<dyn MaterialTrait>>> {
     let mut material_vec: Vec<Box<dyn MaterialTrait>> = Vec::new();
-
     for material in materials {
-        let diffuse_texture: Option<Texture>;
-
-        // 检查是否有基础颜色纹理
-        if let Some(texture_info) = material.pbr_metallic_roughness().base_color_texture() {
-            let texture = texture_info.texture();
-            let image_index = texture.source().index();
-            let image_data = &images[image_index];
-
-            // 创建纹理
-            diffuse_texture = Some(Texture::from_rgb_data(
-                &renderer.device,
-                &renderer.queue,
-                &image_data.pixels,
-                image_data.width,
-                image_data.height,
-                texture.name().unwrap_or("Unnamed texture"),
-            )?);
-            if diffuse_texture.is_none() {
-                println!("diffuse_texture is none {:?}", texture);
-                println!("image_data is none {:?}", image_data);
-                continue;
-            }
-        } else {
-            println!("no base_color_texture {:?}", material.name());
-            let mut image = image::DynamicImage::new_rgba8(512, 512);
-            for x in 0..512 {
-                for y in 0..512 {
-                    image.put_pixel(x, y, image::Rgba([200, 200, 200, 255]));
-                }
-            }
-            diffuse_texture = Some(Texture::from_image(
-                &renderer.device,
-                &renderer.queue,
-                &image,
-                material.name(),
-            )?);
-        }
-
-        // 创建材质
-        let material = materials::basic::BasicMaterial::new(
-            materials::basic::BasicMaterialConfig {
-                name: material.name().unwrap_or("Unnamed image").to_string(),
-                texture: diffuse_texture,
-                ..Default::default()
-            },
-            &renderer,
-        );
+        let config = make_pbr_config(&material, images, renderer)?;
+        material_vec.push(Box::new(PBRMaterial::new(config, renderer)));
+    }
+    Ok(material_vec)
+}
 
-        material_vec.push(Box::new(material));
+/// map a glTF metallic-roughness material onto PBRMaterialConfig
+pub fn make_pbr_config(
+    material: &gltf::Material,
+    images: &[gltf::image::Data],
+    renderer: &Renderer,
+) -> anyhow::Result<PBRMaterialConfig> {
+    let pbr = material.pbr_metallic_roughness();
+    let mut config = PBRMaterialConfig {
+        name: material.name().unwrap_or("Unnamed material").to_string(),
+        base_color_factor: Vec4::from_array(pbr.base_color_factor()),
+        metallic_factor: pbr.metallic_factor(),
+        roughness_factor: pbr.roughness_factor(),
+        emissive_factor: Vec3::from_array(material.emissive_factor()),
+        alpha_mode: match material.alpha_mode() {
+            gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+            gltf::material::AlphaMode::Mask => AlphaMode::Mask,
+            gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+        },
+        alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5),
+        double_sided: material.double_sided(),
+        ..Default::default()
+    };
+
+    // 颜色纹理是 srgb, 其余是线性数据
+    if let Some(info) = pbr.base_color_texture() {
+        config.base_color_texture = Some(load_texture(info.texture(), images, true, renderer)?);
+        config.base_color_uv_transform = texture_transform(&info);
+    }
+    if let Some(info) = pbr.metallic_roughness_texture() {
+        config.metallic_roughness_texture =
+            Some(load_texture(info.texture(), images, false, renderer)?);
+        config.metallic_roughness_uv_transform = texture_transform(&info);
+    }
+    if let Some(info) = material.emissive_texture() {
+        config.emissive_texture = Some(load_texture(info.texture(), images, true, renderer)?);
+        config.emissive_uv_transform = texture_transform(&info);
+    }
+    // the gltf crate doesn't parse KHR_texture_transform on normal and occlusion textures
+    if let Some(normal) = material.normal_texture() {
+        config.normal_scale = normal.scale();
+        config.normal_texture = Some(load_texture(normal.texture(), images, false, renderer)?);
+    }
+    if let Some(occlusion) = material.occlusion_texture() {
+        config.occlusion_strength = occlusion.strength();
+        config.occlusion_texture =
+            Some(load_texture(occlusion.texture(), images, false, renderer)?);
     }
+    Ok(config)
+}
 
-    Ok(material_vec)
+fn texture_transform(info: &gltf::texture::Info) -> TextureTransform {
+    if info.tex_coord() != 0 {
+        log::warn!(
+            "only TEXCOORD_0 is supported, texCoord {} is ignored",
+            info.tex_coord()
+        );
+    }
+    info.texture_transform()
+        .map(|transform| TextureTransform {
+            offset: Vec2::from_array(transform.offset()),
+            rotation: transform.rotation(),
+            scale: Vec2::from_array(transform.scale()),
+        })
+        .unwrap_or_default()
 }
 
-pub fn append_mesh_children(
-    parent: usize,
+pub fn load_texture(
+    texture: gltf::Texture,
+    images: &[gltf::image::Data],
+    srgb: bool,
+    renderer: &Renderer,
+) -> anyhow::Result<Texture> {
+    let image_data = images
+        .get(texture.source().index())
+        .ok_or_else(|| anyhow!("image {} not found", texture.source().index()))?;
+    let image = image_data_to_dynamic(image_data)?;
+    let format = if srgb {
+        wgpu::TextureFormat::Rgba8UnormSrgb
+    } else {
+        wgpu::TextureFormat::Rgba8Unorm
+    };
+    let mut result = Texture::from_image_with_format(
+        &renderer.device,
+        &renderer.queue,
+        &image,
+        texture.name(),
+        format,
+    )?;
+
+    let sampler = texture.sampler();
+    let address_mode = |mode: WrappingMode| match mode {
+        WrappingMode::ClampToEdge => wgpu::AddressMode::ClampToEdge,
+        WrappingMode::MirroredRepeat => wgpu::AddressMode::MirrorRepeat,
+        WrappingMode::Repeat => wgpu::AddressMode::Repeat,
+    };
+    let mag_filter = match sampler.mag_filter() {
+        Some(MagFilter::Nearest) => wgpu::FilterMode::Nearest,
+        _ => wgpu::FilterMode::Linear,
+    };
+    // textures have no mipmaps, only the base filter matters
+    let min_filter = match sampler.min_filter() {
+        Some(
+            MinFilter::Nearest | MinFilter::NearestMipmapNearest | MinFilter::NearestMipmapLinear,
+        ) => wgpu::FilterMode::Nearest,
+        _ => wgpu::FilterMode::Linear,
+    };
+    result.sampler = renderer.device.create_sampler(&wgpu::SamplerDescriptor {
+        label: sampler.name(),
+        address_mode_u: address_mode(sampler.wrap_s()),
+        address_mode_v: address_mode(sampler.wrap_t()),
+        mag_filter,
+        min_filter,
+        ..Default::default()
+    });
+    Ok(result)
+}
+
+/// gltf decodes images into raw native endian bytes, rebuild the matching DynamicImage
+fn image_data_to_dynamic(data: &gltf::image::Data) -> anyhow::Result<DynamicImage> {
+    let (width, height) = (data.width, data.height);
+    let bytes = data.pixels.clone();
+    let words = || -> Vec<u16> {
+        data.pixels
+            .chunks_exact(2)
+            .map(|c| u16::from_ne_bytes([c[0], c[1]]))
+            .collect()
+    };
+    let floats = || -> Vec<f32> {
+        data.pixels
+            .chunks_exact(4)
+            .map(|c| f32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+            .collect()
+    };
+    let image = match data.format {
+        Format::R8 => ImageBuffer::from_raw(width, height, bytes).map(DynamicImage::ImageLuma8),
+        // two channel pngs are grey + alpha
+        Format::R8G8 => ImageBuffer::from_raw(width, height, bytes).map(DynamicImage::ImageLumaA8),
+        Format::R8G8B8 => ImageBuffer::from_raw(width, height, bytes).map(DynamicImage::ImageRgb8),
+        Format::R8G8B8A8 => {
+            ImageBuffer::from_raw(width, height, bytes).map(DynamicImage::ImageRgba8)
+        }
+        Format::R16 => ImageBuffer::from_raw(width, height, words()).map(DynamicImage::ImageLuma16),
+        Format::R16G16 => {
+            ImageBuffer::from_raw(width, height, words()).map(DynamicImage::ImageLumaA16)
+        }
+        Format::R16G16B16 => {
+            ImageBuffer::from_raw(width, height, words()).map(DynamicImage::ImageRgb16)
+        }
+        Format::R16G16B16A16 => {
+            ImageBuffer::from_raw(width, height, words()).map(DynamicImage::ImageRgba16)
+        }
+        Format::R32G32B32FLOAT => {
+            ImageBuffer::from_raw(width, height, floats()).map(DynamicImage::ImageRgb32F)
+        }
+        Format::R32G32B32A32FLOAT => {
+            ImageBuffer::from_raw(width, height, floats()).map(DynamicImage::ImageRgba32F)
+        }
+    };
+    image.ok_or_else(|| {
+        anyhow!(
+            "image data of {} bytes doesn't match {}x{} {:?}",
+            data.pixels.len(),
+            width,
+            height,
+            data.format
+        )
+    })
+}
+
+/// walk the default scene (or the first one) and add its nodes under parent.
+/// skins and animations are imported after the nodes they reference,
+/// the clips go to an AnimationPlayer on parent.
+/// cameras and lights are placed by the node's global matrix at import, parent is taken as the origin
+pub fn append_scene_nodes(
+    parent: EntityId,
     mini_gpu: &mut MiniGPU,
     model: &Document,
-    buffers: &Vec<gltf::buffer::Data>,
+    buffers: &[gltf::buffer::Data],
+    images: &[gltf::image::Data],
     materials: Vec<Box<dyn MaterialTrait>>,
-) -> usize {
-    let material_ids: Vec<usize> = materials
+    options: GltfLoadOptions,
+) -> anyhow::Result<EntityId> {
+    let material_ids: Vec<ComponentId> = materials
         .into_iter()
         .map(|m| mini_gpu.scene.add_component::<Box<dyn MaterialTrait>>(m))
         .collect();
-    let meshs = model.meshes();
-    meshs.into_iter().for_each(|mesh| {
-        let mesh_group = build_group_mesh(&mesh, mini_gpu, &buffers, &material_ids);
-        mini_gpu.scene.add_entity_child(parent, mesh_group); //添加到父节点
-    });
-    parent
+    let mut builder = NodeBuilder {
+        buffers,
+        images,
+        material_ids,
+        material_formats: HashMap::new(),
+        material_variants: HashMap::new(),
+        meshes: HashMap::new(),
+        node_entities: HashMap::new(),
+        skinned_nodes: vec![],
+        morphed_nodes: vec![],
+        cameras: vec![],
+    };
+    if let Some(scene) = model.default_scene().or_else(|| model.scenes().next()) {
+        for node in scene.nodes() {
+            builder.append_node(parent, Mat4::IDENTITY, &node, mini_gpu)?;
+        }
+    }
+    if options.use_camera {
+        if let Some(camera_id) = builder.cameras.first() {
+            mini_gpu.scene.default_camera = Some(*camera_id);
+        }
+    }
+    builder.build_skins(model, mini_gpu)?;
+    // after skins, a skinned and morphed primitive binds both through its weights
+    builder.build_morph_weights(mini_gpu);
+    let clips = builder.build_animations(model)?;
+    if !clips.is_empty() {
+        mini_gpu.scene.set_entity_component(
+            parent,
+            AnimationPlayer::new(clips),
+            "animation_player",
+        );
+    }
+    Ok(parent)
 }
 
-pub fn build_group_mesh(
-    mesh: &gltf::Mesh,
-    mini_gpu: &mut MiniGPU,
-    buffers: &Vec<Data>,
-    material_ids: &Vec<usize>,
-) -> Entity {
-    let i = 0;
-    let mut group = Entity::new();
-    for primitive in mesh.primitives() {
-        let mesh_instance = build_mesh(&mini_gpu.renderer, &primitive, &buffers);
-        let mut child = Entity::new();
-        child.name = format!("{}-primitive-{}", mesh.name().unwrap_or("Unnamed mesh"), i);
-        let primitive_mateiral_index = primitive.material().index().unwrap_or(0);
-        let material_index = material_ids.get(primitive_mateiral_index);
-        if material_index.is_none() {
-            println!("material_index is none {:?}", primitive.material());
-            continue;
-        }
-
-        let child_id = mini_gpu.scene.add_entity(child);
+struct NodeBuilder<'a> {
+    buffers: &'a [gltf::buffer::Data],
+    images: &'a [gltf::image::Data],
+    material_ids: Vec<ComponentId>,
+    // gltf material index -> the vertex format its material in material_ids is used with
+    material_formats: HashMap<usize, VertexFormat>,
+    // (gltf material index, skinned, morphed, vertex format) -> a copy of the material for that pipeline,
+    // None is the glTF default material
+    material_variants: HashMap<(Option<usize>, bool, bool, VertexFormat), ComponentId>,
+    // (gltf mesh index, skinned) -> (mesh, material) of each primitive
+    meshes: HashMap<(usize, bool), Vec<(ComponentId, ComponentId)>>,
+    node_entities: HashMap<usize, EntityId>,
+    // (gltf skin index, primitive entities of the node)
+    skinned_nodes: Vec<(usize, Vec<EntityId>)>,
+    // (default weights, primitive entities) of nodes whose mesh has morph targets
+    morphed_nodes: Vec<(Vec<f32>, Vec<EntityId>)>,
+    // camera entities in traversal order
+    cameras: Vec<EntityId>,
+}
+
+impl NodeBuilder<'_> {
+    fn append_node(
+        &mut self,
+        parent: EntityId,
+        parent_matrix: Mat4,
+        node: &gltf::Node,
+        mini_gpu: &mut MiniGPU,
+    ) -> anyhow::Result<()> {
+        let mut entity = Entity::new();
+        entity.name = node
+            .name()
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| format!("node-{}", node.index()));
+        let node_id = mini_gpu.scene.add_entity_child(parent, entity)?;
+        self.node_entities.insert(node.index(), node_id);
 
+        let (translation, rotation, scale) = node.transform().decomposed();
+        let transform = Transform::new(
+            &mini_gpu.renderer,
+            Vec3::from_array(translation),
+            Quat::from_array(rotation),
+            Vec3::from_array(scale),
+        );
         mini_gpu
             .scene
-            .set_entity_component(child_id, mesh_instance, "mesh");
-        mini_gpu
+            .set_entity_component(node_id, transform, "transform");
+        let global_matrix = parent_matrix * Mat4::from_cols_array_2d(&node.transform().matrix());
+
+        if let Some(camera) = node.camera() {
+            let camera = make_camera(&camera, global_matrix, &mini_gpu.renderer);
+            mini_gpu
+                .scene
+                .set_entity_component::<Box<dyn CameraTrait>>(node_id, camera, "camera");
+            self.cameras.push(node_id);
+        }
+        if let Some(light) = node.light() {
+            let light = make_light(&light, global_matrix);
+            mini_gpu
+                .scene
+                .set_entity_component::<Box<dyn LightTrait>>(node_id, light, "light");
+        }
+
+        if let Some(mesh) = node.mesh() {
+            let name = mesh.name().unwrap_or("Unnamed mesh").to_string();
+            let skin = node.skin();
+            let mut primitive_ids = vec![];
+            for (i, (mesh_id, material_id)) in self
+                .mesh_primitives(&mesh, skin.is_some(), mini_gpu)?
+                .into_iter()
+                .enumerate()
+            {
+                let mut child = Entity::new();
+                child.name = format!("{}-primitive-{}", name, i);
+                let child_id = mini_gpu.scene.add_entity_child(node_id, child)?;
+                mini_gpu
+                    .scene
+                    .set_entity_component_index(child_id, mesh_id, "mesh")?;
+                mini_gpu
+                    .scene
+                    .set_entity_component_index(child_id, material_id, "material")?;
+                let transform = Transform::default(&mini_gpu.renderer);
+                mini_gpu
+                    .scene
+                    .set_entity_component(child_id, transform, "transform");
+                primitive_ids.push(child_id);
+            }
+            let target_count = mesh
+                .primitives()
+                .map(|primitive| primitive.morph_targets().len())
+                .max()
+                .unwrap_or(0);
+            if target_count > 0 {
+                // node weights override the mesh's, both default to zero
+                let weights = node
+                    .weights()
+                    .or_else(|| mesh.weights())
+                    .map(|weights| weights.to_vec())
+                    .unwrap_or_else(|| vec![0.; target_count]);
+                self.morphed_nodes.push((weights, primitive_ids.clone()));
+            }
+            if let Some(skin) = skin {
+                self.skinned_nodes.push((skin.index(), primitive_ids));
+            }
+        }
+
+        for child in node.children() {
+            self.append_node(node_id, global_matrix, &child, mini_gpu)?;
+        }
+        Ok(())
+    }
+
+    fn mesh_primitives(
+        &mut self,
+        mesh: &gltf::Mesh,
+        skinned: bool,
+        mini_gpu: &mut MiniGPU,
+    ) -> anyhow::Result<Vec<(ComponentId, ComponentId)>> {
+        if let Some(primitives) = self.meshes.get(&(mesh.index(), skinned)) {
+            return Ok(primitives.clone());
+        }
+        let mut primitives = Vec::new();
+        for primitive in mesh.primitives() {
+            if !is_triangles(primitive.mode()) {
+                log::warn!(
+                    "mesh {} primitive {} is skipped, {:?} primitives are not supported",
+                    mesh.name().unwrap_or("Unnamed mesh"),
+                    primitive.index(),
+                    primitive.mode()
+                );
+                continue;
+            }
+            let material = primitive.material();
+            let options = BuildMeshOptions {
+                needs_tex_coords: material_has_texture(&material),
+                skinned,
+            };
+            let mesh_instance = build_mesh(&mini_gpu.renderer, &primitive, self.buffers, options)
+                .map_err(|e| {
+                e.context(format!(
+                    "mesh {} primitive {}",
+                    mesh.name().unwrap_or("Unnamed mesh"),
+                    primitive.index()
+                ))
+            })?;
+            // the pipeline is built for the vertex layout of the first mesh drawn with the material
+            let morphed = mesh_instance.morph_targets.is_some();
+            let material_id = self.primitive_material(
+                &material,
+                skinned,
+                morphed,
+                mesh_instance.vertex_format,
+                mini_gpu,
+            )?;
+            let mesh_id = mini_gpu.scene.add_component(mesh_instance);
+            primitives.push((mesh_id, material_id));
+        }
+        self.meshes
+            .insert((mesh.index(), skinned), primitives.clone());
+        Ok(primitives)
+    }
+
+    /// the loaded material serves the vertex format of its first primitive, other formats get a copy
+    fn primitive_material(
+        &mut self,
+        material: &gltf::Material,
+        skinned: bool,
+        morphed: bool,
+        vertex_format: VertexFormat,
+        mini_gpu: &mut MiniGPU,
+    ) -> anyhow::Result<ComponentId> {
+        if let (Some(index), false) = (material.index(), skinned || morphed) {
+            let material_id = *self
+                .material_ids
+                .get(index)
+                .ok_or_else(|| anyhow!("material {} not found", index))?;
+            if *self.material_formats.entry(index).or_insert(vertex_format) == vertex_format {
+                return Ok(material_id);
+            }
+        }
+        self.material_variant(material, skinned, morphed, vertex_format, mini_gpu)
+    }
+
+    /// skinned and morphed meshes need their own pipeline, so the material is built again with them on.
+    /// primitives without a material get the glTF default material
+    fn material_variant(
+        &mut self,
+        material: &gltf::Material,
+        skinned: bool,
+        morphed: bool,
+        vertex_format: VertexFormat,
+        mini_gpu: &mut MiniGPU,
+    ) -> anyhow::Result<ComponentId> {
+        let key = (material.index(), skinned, morphed, vertex_format);
+        if let Some(material_id) = self.material_variants.get(&key) {
+            return Ok(*material_id);
+        }
+        let mut config = match material.index() {
+            Some(_) => make_pbr_config(material, self.images, &mini_gpu.renderer)?,
+            None => PBRMaterialConfig::default(),
+        };
+        config.skinned = skinned;
+        config.morph_targets = morphed;
+        let pbr_material = PBRMaterial::new(config, &mini_gpu.renderer);
+        let material_id = mini_gpu
             .scene
-            .set_entity_component_index(child_id, *material_index.unwrap(), "material");
-
-        group.add_child(child_id);
-    }
-    group
-}
-
-pub fn build_mesh(renderer: &Renderer, primitive: &gltf::Primitive, buffers: &Vec<Data>) -> Mesh {
-    let mut vertices: Vec<f32> = Vec::new();
-    let mut indices: Vec<u32> = Vec::new();
-    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
-    let positions = reader.read_positions().unwrap();
-    let normals = reader.read_normals().unwrap();
-    let tex_coords = reader.read_tex_coords(0).unwrap().into_f32();
-    let indices_iter = reader.read_indices().unwrap().into_u32();
-    positions
-        .zip(normals)
-        .zip(tex_coords)
-        .for_each(|((position, normal), tex_coord)| {
-            vertices.extend_from_slice(&position);
-            vertices.extend_from_slice(&normal);
-            vertices.extend_from_slice(&tex_coord);
-        });
-    indices.extend(indices_iter);
-    Mesh::new(
-        bytemuck::cast_slice(&vertices),
-        indices,
-        crate::components::mesh::VertexFormat::PositionNormalTexture,
-        renderer,
+            .add_component::<Box<dyn MaterialTrait>>(Box::new(pbr_material));
+        self.material_variants.insert(key, material_id);
+        Ok(material_id)
+    }
+
+    /// every skinned node gets its own Skin, the joint matrices depend on the node's global matrix
+    fn build_skins(&self, model: &Document, mini_gpu: &mut MiniGPU) -> anyhow::Result<()> {
+        for (skin_index, primitive_ids) in &self.skinned_nodes {
+            let skin = model
+                .skins()
+                .nth(*skin_index)
+                .ok_or_else(|| anyhow!("skin {} not found", skin_index))?;
+            let joints = skin
+                .joints()
+                .map(|joint| {
+                    self.node_entities
+                        .get(&joint.index())
+                        .copied()
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "joint node {} of skin {} is not in the scene",
+                                joint.index(),
+                                skin_index
+                            )
+                        })
+                })
+                .collect::<anyhow::Result<Vec<EntityId>>>()?;
+            if joints.len() > MAX_JOINTS {
+                return Err(anyhow!(
+                    "skin {} has {} joints, at most {} are supported",
+                    skin_index,
+                    joints.len(),
+                    MAX_JOINTS
+                ));
+            }
+            let reader =
+                skin.reader(|buffer| self.buffers.get(buffer.index()).map(|data| &data.0[..]));
+            let inverse_bind_matrices: Vec<Mat4> = match reader.read_inverse_bind_matrices() {
+                Some(matrices) => matrices.map(|m| Mat4::from_cols_array_2d(&m)).collect(),
+                None => vec![Mat4::IDENTITY; joints.len()],
+            };
+            let skin_component = Skin::new(&mini_gpu.renderer, joints, inverse_bind_matrices);
+            let skin_id = mini_gpu.scene.add_component(skin_component);
+            for primitive_id in primitive_ids {
+                mini_gpu
+                    .scene
+                    .set_entity_component_index(*primitive_id, skin_id, "skin")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// every primitive with morph targets gets its own "weights", animations of the node update them all
+    fn build_morph_weights(&self, mini_gpu: &mut MiniGPU) {
+        for (weights, primitive_ids) in &self.morphed_nodes {
+            for primitive_id in primitive_ids {
+                let scene = &mini_gpu.scene;
+                let Ok(mesh) = scene.get_entity_component::<Mesh>(*primitive_id, "mesh") else {
+                    continue;
+                };
+                if mesh.morph_targets.is_none() {
+                    continue;
+                }
+                let skin = scene
+                    .get_entity_component::<Skin>(*primitive_id, "skin")
+                    .ok();
+                let morph_weights =
+                    MorphWeights::new(&mini_gpu.renderer, mesh, skin, weights.clone());
+                mini_gpu
+                    .scene
+                    .set_entity_component(*primitive_id, morph_weights, "weights");
+            }
+        }
+    }
+
+    fn build_animations(&self, model: &Document) -> anyhow::Result<Vec<AnimationClip>> {
+        let mut clips = vec![];
+        for animation in model.animations() {
+            let mut channels = vec![];
+            for channel in animation.channels() {
+                let node = channel.target().node();
+                let Some(target) = self.node_entities.get(&node.index()).copied() else {
+                    log::warn!("animation target node {} is not in the scene", node.index());
+                    continue;
+                };
+                let reader = channel
+                    .reader(|buffer| self.buffers.get(buffer.index()).map(|data| &data.0[..]));
+                let times: Vec<f32> = reader
+                    .read_inputs()
+                    .ok_or_else(|| anyhow!("animation channel has no input"))?
+                    .collect();
+                let (property, values): (AnimationProperty, Vec<f32>) = match reader
+                    .read_outputs()
+                    .ok_or_else(|| anyhow!("animation channel has no output"))?
+                {
+                    ReadOutputs::Translations(values) => {
+                        (AnimationProperty::Translation, values.flatten().collect())
+                    }
+                    ReadOutputs::Rotations(values) => (
+                        AnimationProperty::Rotation,
+                        values.into_f32().flatten().collect(),
+                    ),
+                    ReadOutputs::Scales(values) => {
+                        (AnimationProperty::Scale, values.flatten().collect())
+                    }
+                    ReadOutputs::MorphTargetWeights(values) => {
+                        (AnimationProperty::Weights, values.into_f32().collect())
+                    }
+                };
+                let interpolation = match channel.sampler().interpolation() {
+                    gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                    gltf::animation::Interpolation::Step => Interpolation::Step,
+                    gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+                };
+                channels.push(AnimationChannel {
+                    target,
+                    property,
+                    interpolation,
+                    times,
+                    values,
+                });
+            }
+            clips.push(AnimationClip {
+                name: animation
+                    .name()
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| format!("animation-{}", animation.index())),
+                channels,
+            });
+        }
+        Ok(clips)
+    }
+}
+
+/// glTF cameras look down -z of their node with +y up
+fn make_camera(
+    camera: &gltf::Camera,
+    global_matrix: Mat4,
+    renderer: &Renderer,
+) -> Box<dyn CameraTrait> {
+    let position = global_matrix.transform_point3(Vec3::ZERO);
+    let target = position + global_matrix.transform_vector3(Vec3::NEG_Z).normalize();
+    let up = global_matrix.transform_vector3(Vec3::Y).normalize();
+    match camera.projection() {
+        gltf::camera::Projection::Perspective(perspective) => {
+            let config = PerspectiveCameraConfig {
+                position,
+                target,
+                fov: perspective.yfov().to_degrees(),
+                aspect: perspective
+                    .aspect_ratio()
+                    .unwrap_or(renderer.config.width as f32 / renderer.config.height as f32),
+                near: perspective.znear(),
+                // an infinite projection isn't supported, use the default far plane
+                far: perspective
+                    .zfar()
+                    .unwrap_or(PerspectiveCameraConfig::default().far),
+                up,
+                bind_index: DEFAULT_CAMERA_BIND_INDEX,
+            };
+            Box::new(PerspectiveCamera::new(config, renderer))
+        }
+        gltf::camera::Projection::Orthographic(orthographic) => {
+            // xmag and ymag are half the view size
+            let config = OrthographicCameraConfig {
+                width: orthographic.xmag() * 2.,
+                aspect: orthographic.xmag() / orthographic.ymag(),
+                near: orthographic.znear(),
+                far: orthographic.zfar(),
+                position,
+                target,
+                up,
+                zoom: 1.,
+            };
+            Box::new(OrthographicCamera::new(config, renderer))
+        }
+    }
+}
+
+/// KHR_lights_punctual lights point down -z of their node, intensity is kept in the glTF units.
+/// a missing range means no limit
+fn make_light(
+    light: &gltf::khr_lights_punctual::Light,
+    global_matrix: Mat4,
+) -> Box<dyn LightTrait> {
+    let [r, g, b] = light.color();
+    let direction = global_matrix.transform_vector3(Vec3::NEG_Z).normalize();
+    let position = global_matrix.transform_point3(Vec3::ZERO);
+    match light.kind() {
+        gltf::khr_lights_punctual::Kind::Directional => {
+            let uniform = DirectionalLightUniform {
+                direction: direction.to_array(),
+                color: [r, g, b],
+                intensity: light.intensity(),
+            };
+            Box::new(DirectionalLight::new(uniform))
+        }
+        gltf::khr_lights_punctual::Kind::Point => {
+            let uniform = PointLightUniform {
+                position: position.to_array(),
+                intensity: light.intensity(),
+                color: [r, g, b],
+                range: light.range().unwrap_or(0.),
+                ..Default::default()
+            };
+            Box::new(PointLight::new(uniform))
+        }
+        gltf::khr_lights_punctual::Kind::Spot {
+            inner_cone_angle,
+            outer_cone_angle,
+        } => {
+            let mut uniform = SpotLightUniform {
+                position: position.to_array(),
+                intensity: light.intensity(),
+                color: [r, g, b],
+                range: light.range().unwrap_or(0.),
+                direction: direction.to_array(),
+                ..Default::default()
+            };
+            uniform.set_cone_angles(inner_cone_angle, outer_cone_angle);
+            Box::new(SpotLight::new(uniform))
+        }
+    }
+}
+
+fn material_has_texture(material: &gltf::Material) -> bool {
+    let pbr = material.pbr_metallic_roughness();
+    pbr.base_color_texture().is_some()
+        || pbr.metallic_roughness_texture().is_some()
+        || material.normal_texture().is_some()
+        || material.occlusion_texture().is_some()
+        || material.emissive_texture().is_some()
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BuildMeshOptions {
+    /// fill in zero uvs when the primitive has none, for textured materials
+    pub needs_tex_coords: bool,
+    /// read JOINTS_0 and WEIGHTS_0 into a PositionNormalTextureSkin mesh
+    pub skinned: bool,
+}
+
+/// decode a primitive into a triangle list mesh. the vertex format follows the attributes present:
+/// missing normals are generated flat as the glTF spec asks, non-indexed primitives get sequential indices,
+/// and zero uvs are filled in only when the material samples a texture. morph targets go to mesh.morph_targets
+pub fn build_mesh(
+    renderer: &Renderer,
+    primitive: &gltf::Primitive,
+    buffers: &[Data],
+    options: BuildMeshOptions,
+) -> anyhow::Result<Mesh> {
+    let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data.0[..]));
+    let positions: Vec<[f32; 3]> = reader
+        .read_positions()
+        .ok_or_else(|| anyhow!("primitive has no POSITION attribute"))?
+        .collect();
+    let normals: Option<Vec<[f32; 3]>> = reader.read_normals().map(|normals| normals.collect());
+    let mut tex_coords: Option<Vec<[f32; 2]>> = reader
+        .read_tex_coords(0)
+        .map(|tex_coords| tex_coords.into_f32().collect());
+    let mut skin: Option<SkinStreams> = None;
+    if options.skinned {
+        let joints = reader
+            .read_joints(0)
+            .ok_or_else(|| anyhow!("skinned primitive has no JOINTS_0 attribute"))?
+            .into_u16()
+            .map(|joints| joints.map(u32::from))
+            .collect();
+        let weights = reader
+            .read_weights(0)
+            .ok_or_else(|| anyhow!("skinned primitive has no WEIGHTS_0 attribute"))?
+            .into_f32()
+            .collect();
+        skin = Some((joints, weights));
+    }
+    let mut morph_targets: Vec<MorphTarget> = reader
+        .read_morph_targets()
+        .map(|(positions, normals, _)| MorphTarget {
+            // a target may only move normals
+            positions: positions.map_or_else(Vec::new, |p| p.collect()),
+            normals: normals.map(|n| n.collect()),
+        })
+        .collect();
+    let indices: Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..positions.len() as u32).collect(),
+    };
+    let mut indices = triangulate(primitive.mode(), indices)?;
+
+    if let Some(index) = indices.iter().find(|i| **i as usize >= positions.len()) {
+        return Err(anyhow!(
+            "index {} out of range of {} vertices",
+            index,
+            positions.len()
+        ));
+    }
+    if normals.as_ref().is_some_and(|n| n.len() != positions.len()) {
+        return Err(anyhow!("NORMAL count doesn't match POSITION count"));
+    }
+    if tex_coords
+        .as_ref()
+        .is_some_and(|t| t.len() != positions.len())
+    {
+        return Err(anyhow!("TEXCOORD_0 count doesn't match POSITION count"));
+    }
+    if skin
+        .as_ref()
+        .is_some_and(|(j, w)| j.len() != positions.len() || w.len() != positions.len())
+    {
+        return Err(anyhow!(
+            "JOINTS_0 or WEIGHTS_0 count doesn't match POSITION count"
+        ));
+    }
+    if morph_targets.len() > MAX_MORPH_TARGETS {
+        return Err(anyhow!(
+            "primitive has {} morph targets, at most {} are supported",
+            morph_targets.len(),
+            MAX_MORPH_TARGETS
+        ));
+    }
+    for target in morph_targets.iter_mut() {
+        if target.positions.is_empty() {
+            target.positions = vec![[0.; 3]; positions.len()];
+        }
+        if target.positions.len() != positions.len()
+            || target
+                .normals
+                .as_ref()
+                .is_some_and(|n| n.len() != positions.len())
+        {
+            return Err(anyhow!("morph target count doesn't match POSITION count"));
+        }
+    }
+
+    let (positions, normals) = match normals {
+        Some(normals) => (positions, normals),
+        None => {
+            // flat normals need every corner to be its own vertex
+            let corners = unweld(&positions, &indices);
+            tex_coords = tex_coords.map(|uvs| unweld(&uvs, &indices));
+            skin = skin
+                .map(|(joints, weights)| (unweld(&joints, &indices), unweld(&weights, &indices)));
+            // target normals offset smooth normals, they don't apply to generated flat ones
+            for target in morph_targets.iter_mut() {
+                target.positions = unweld(&target.positions, &indices);
+                target.normals = None;
+            }
+            indices = (0..corners.len() as u32).collect();
+            let normals = flat_normals(&corners);
+            (corners, normals)
+        }
+    };
+    if tex_coords.is_none() && (options.needs_tex_coords || skin.is_some()) {
+        if options.needs_tex_coords {
+            log::warn!(
+                "primitive without TEXCOORD_0 uses a textured material, uvs are set to zero"
+            );
+        }
+        tex_coords = Some(vec![[0.0; 2]; positions.len()]);
+    }
+
+    let vertex_count = positions.len();
+    let mut mesh = match (tex_coords, skin) {
+        (Some(tex_coords), Some((joints, weights))) => {
+            let vertices: Vec<VertexPositionNormalTextureSkin> = (0..positions.len())
+                .map(|i| VertexPositionNormalTextureSkin {
+                    position: positions[i],
+                    normal: normals[i],
+                    tex_coords: tex_coords[i],
+                    joints: joints[i],
+                    weights: weights[i],
+                })
+                .collect();
+            Mesh::new(
+                bytemuck::cast_slice(&vertices),
+                indices,
+                VertexFormat::PositionNormalTextureSkin,
+                renderer,
+            )
+        }
+        (Some(tex_coords), None) => {
+            let vertices: Vec<VertexPositionNormalTexture> = positions
+                .into_iter()
+                .zip(normals)
+                .zip(tex_coords)
+                .map(
+                    |((position, normal), tex_coords)| VertexPositionNormalTexture {
+                        position,
+                        normal,
+                        tex_coords,
+                    },
+                )
+                .collect();
+            Mesh::new(
+                bytemuck::cast_slice(&vertices),
+                indices,
+                VertexFormat::PositionNormalTexture,
+                renderer,
+            )
+        }
+        (None, _) => {
+            let vertices: Vec<VertexPositionNormal> = positions
+                .into_iter()
+                .zip(normals)
+                .map(|(position, normal)| VertexPositionNormal { position, normal })
+                .collect();
+            Mesh::new(
+                bytemuck::cast_slice(&vertices),
+                indices,
+                VertexFormat::PositionNormal,
+                renderer,
+            )
+        }
+    };
+    if !morph_targets.is_empty() {
+        // the mesh is still drawn without them, e.g. a face rig too big for the device
+        match MorphTargets::new(renderer, vertex_count, &morph_targets) {
+            Ok(targets) => mesh.morph_targets = Some(targets),
+            Err(e) => log::warn!("{}, the morph targets are skipped", e),
+        }
+    }
+    Ok(mesh)
+}
+
+// JOINTS_0 and WEIGHTS_0 of every vertex
+type SkinStreams = (Vec<[u32; 4]>, Vec<[f32; 4]>);
+
+fn unweld<T: Copy>(values: &[T], indices: &[u32]) -> Vec<T> {
+    indices.iter().map(|i| values[*i as usize]).collect()
+}
+
+/// turn strips and fans into a triangle list, points and lines can't be drawn by the mesh pipeline
+/// modes triangulate can turn into a triangle list, lines and points are not drawn
+fn is_triangles(mode: gltf::mesh::Mode) -> bool {
+    use gltf::mesh::Mode;
+    matches!(
+        mode,
+        Mode::Triangles | Mode::TriangleStrip | Mode::TriangleFan
     )
 }
 
+fn triangulate(mode: gltf::mesh::Mode, indices: Vec<u32>) -> anyhow::Result<Vec<u32>> {
+    use gltf::mesh::Mode;
+    match mode {
+        Mode::Triangles => {
+            if !indices.len().is_multiple_of(3) {
+                return Err(anyhow!(
+                    "{} indices is not a whole number of triangles",
+                    indices.len()
+                ));
+            }
+            Ok(indices)
+        }
+        Mode::TriangleStrip => Ok((0..indices.len().saturating_sub(2))
+            .flat_map(|i| {
+                // keep the winding of every other triangle
+                let (b, c) = if i % 2 == 0 { (1, 2) } else { (2, 1) };
+                [indices[i], indices[i + b], indices[i + c]]
+            })
+            .collect()),
+        Mode::TriangleFan => Ok((1..indices.len().saturating_sub(1))
+            .flat_map(|i| [indices[i], indices[i + 1], indices[0]])
+            .collect()),
+        mode => Err(anyhow!("primitive mode {:?} is not supported", mode)),
+    }
+}
+
 pub fn get_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
     use std::mem;
     wgpu::VertexBufferLayout {
@@ -0,0 +1,430 @@
+use std::{borrow::Cow, collections::BTreeMap, collections::HashMap, io::Cursor};
+
+use anyhow::anyhow;
+use gltf::json::{
+    self,
+    accessor::{ComponentType, GenericComponentType, Type},
+    buffer::{Stride, Target},
+    material::{AlphaCutoff, EmissiveFactor, PbrBaseColorFactor, StrengthFactor},
+    mesh::{Mode, Semantic},
+    validation::{Checked::Valid, USize64},
+    Index,
+};
+
+use crate::{
+    component_store::ComponentId,
+    components::{
+        instance::Instance,
+        material::{Material, MaterialTrait},
+        materials::{
+            basic::BasicMaterial,
+            blinn_phong::BlinnPhongMaterial,
+            pbr::{AlphaMode, PBRMaterial},
+        },
+        mesh::Mesh,
+        transform::Transform,
+    },
+    entity::EntityId,
+    renderer::Renderer,
+    scene::Scene,
+};
+
+use super::texture::Texture;
+
+/// write an entity and its descendants to a binary glTF, every entity becomes a node with its local transform.
+/// meshes are exported with positions, normals, uvs, vertex colors and indices, BasicMaterial,
+/// BlinnPhongMaterial and PBRMaterial become metallic-roughness materials, textures are read back from the gpu
+/// and embedded as png. an Instance is exported as one child node per instance matrix.
+/// skins, morph targets, cameras and lights are skipped
+pub fn export_glb(scene: &Scene, root: EntityId, renderer: &Renderer) -> anyhow::Result<Vec<u8>> {
+    let mut exporter = GlbExporter {
+        scene,
+        renderer,
+        root: json::Root::default(),
+        bin: vec![],
+        meshes: HashMap::new(),
+        materials: HashMap::new(),
+        textures: HashMap::new(),
+    };
+    exporter.root.asset.generator = Some("mini_gpu".to_string());
+    let node = exporter.export_node(root)?;
+    let scene_index = exporter.root.push(json::Scene {
+        extensions: Default::default(),
+        extras: Default::default(),
+        name: None,
+        nodes: vec![node],
+    });
+    exporter.root.scene = Some(scene_index);
+    exporter.finish()
+}
+
+struct GlbExporter<'a> {
+    scene: &'a Scene,
+    renderer: &'a Renderer,
+    root: json::Root,
+    bin: Vec<u8>,
+    // mesh and material components shared by several entities are written once
+    meshes: HashMap<(ComponentId, Option<ComponentId>), Index<json::Mesh>>,
+    materials: HashMap<ComponentId, Option<Index<json::Material>>>,
+    textures: HashMap<wgpu::Id<wgpu::Texture>, Index<json::Texture>>,
+}
+
+impl GlbExporter<'_> {
+    fn export_node(&mut self, entity_id: EntityId) -> anyhow::Result<Index<json::Node>> {
+        let scene = self.scene;
+        let entity = scene
+            .get_entity(entity_id)
+            .ok_or_else(|| anyhow!("export_glb: entity {} not found", entity_id))?;
+
+        let mut node = json::Node {
+            name: (!entity.name.is_empty()).then(|| entity.name.clone()),
+            ..Default::default()
+        };
+        if let Some(transform) = entity
+            .get_component_id::<Transform>()
+            .and_then(|id| scene.get_component::<Transform>(id).ok())
+        {
+            node.translation = Some(transform.position.to_array());
+            node.rotation = Some(json::scene::UnitQuaternion(transform.rotation.to_array()));
+            node.scale = Some(transform.scale.to_array());
+        }
+
+        let mut children = vec![];
+        if let Some(mesh_id) = entity.get_component_id::<Mesh>() {
+            let mesh =
+                self.export_mesh(mesh_id, entity.get_component_id::<Box<dyn MaterialTrait>>())?;
+            let instance = entity
+                .get_component_id::<Instance>()
+                .and_then(|id| scene.get_component::<Instance>(id).ok());
+            match instance.and_then(|instance| instance.matrices()) {
+                // instance matrices are applied in the entity's local space
+                Some(matrices) => {
+                    for matrix in matrices {
+                        children.push(self.root.push(json::Node {
+                            mesh: Some(mesh),
+                            matrix: Some(matrix.to_cols_array()),
+                            ..Default::default()
+                        }));
+                    }
+                }
+                None => node.mesh = Some(mesh),
+            }
+        }
+        for child in &entity.children {
+            children.push(self.export_node(*child)?);
+        }
+        if !children.is_empty() {
+            node.children = Some(children);
+        }
+        Ok(self.root.push(node))
+    }
+
+    fn export_mesh(
+        &mut self,
+        mesh_id: ComponentId,
+        material_id: Option<ComponentId>,
+    ) -> anyhow::Result<Index<json::Mesh>> {
+        if let Some(index) = self.meshes.get(&(mesh_id, material_id)) {
+            return Ok(*index);
+        }
+        let scene = self.scene;
+        let mesh = scene.get_component::<Mesh>(mesh_id)?;
+        let material = material_id
+            .map(|id| scene.get_component::<Box<dyn MaterialTrait>>(id))
+            .transpose()?;
+
+        // the interleaved vertex buffer is written as is, every attribute is an accessor into it
+        let stride = mesh.vertex_buffer_layout.array_stride as usize;
+        if stride == 0 || mesh.vertices.is_empty() || mesh.vertices.len() % stride != 0 {
+            return Err(anyhow!("export_glb: mesh has no cpu vertex data"));
+        }
+        let vertex_count = mesh.vertices.len() / stride;
+        let vertex_view = self.push_view(&mesh.vertices, Some(stride), Some(Target::ArrayBuffer));
+
+        let mut attributes = BTreeMap::new();
+        for attribute in &mesh.vertex_attributes {
+            let (semantic, type_) = match (attribute.shader_location, attribute.format) {
+                (0, wgpu::VertexFormat::Float32x3) => (Semantic::Positions, Type::Vec3),
+                (1, wgpu::VertexFormat::Float32x3) => (Semantic::Normals, Type::Vec3),
+                (2, wgpu::VertexFormat::Float32x2) => (Semantic::TexCoords(0), Type::Vec2),
+                (9, wgpu::VertexFormat::Float32x4) => (Semantic::Colors(0), Type::Vec4),
+                // joints are u32, which glTF doesn't allow, and skins aren't exported
+                _ => continue,
+            };
+            let (min, max) = if semantic == Semantic::Positions {
+                let (min, max) = position_bounds(mesh, attribute.offset as usize, stride);
+                (
+                    Some(json::Value::from(min.to_vec())),
+                    Some(json::Value::from(max.to_vec())),
+                )
+            } else {
+                (None, None)
+            };
+            let accessor = self.root.push(json::Accessor {
+                buffer_view: Some(vertex_view),
+                byte_offset: Some(USize64(attribute.offset)),
+                count: USize64::from(vertex_count),
+                component_type: Valid(GenericComponentType(ComponentType::F32)),
+                extensions: Default::default(),
+                extras: Default::default(),
+                type_: Valid(type_),
+                min,
+                max,
+                name: None,
+                normalized: false,
+                sparse: None,
+            });
+            attributes.insert(Valid(semantic), accessor);
+        }
+        if !attributes.contains_key(&Valid(Semantic::Positions)) {
+            return Err(anyhow!(
+                "export_glb: mesh has no Float32x3 position at location 0"
+            ));
+        }
+
+        let indices = if mesh.indices.is_empty() {
+            None
+        } else {
+            let view = self.push_view(
+                bytemuck::cast_slice(&mesh.indices),
+                None,
+                Some(Target::ElementArrayBuffer),
+            );
+            Some(self.root.push(json::Accessor {
+                buffer_view: Some(view),
+                byte_offset: None,
+                count: USize64::from(mesh.indices.len()),
+                component_type: Valid(GenericComponentType(ComponentType::U32)),
+                extensions: Default::default(),
+                extras: Default::default(),
+                type_: Valid(Type::Scalar),
+                min: None,
+                max: None,
+                name: None,
+                normalized: false,
+                sparse: None,
+            }))
+        };
+
+        // only the generic Material lets the topology be chosen
+        let topology = material
+            .and_then(|material| material.as_any_ref().downcast_ref::<Material>())
+            .map_or(wgpu::PrimitiveTopology::TriangleList, |material| {
+                material.config.topology
+            });
+        let mode = match topology {
+            wgpu::PrimitiveTopology::PointList => Mode::Points,
+            wgpu::PrimitiveTopology::LineList => Mode::Lines,
+            wgpu::PrimitiveTopology::LineStrip => Mode::LineStrip,
+            wgpu::PrimitiveTopology::TriangleList => Mode::Triangles,
+            wgpu::PrimitiveTopology::TriangleStrip => Mode::TriangleStrip,
+        };
+
+        let material = match (material_id, material) {
+            (Some(id), Some(material)) => self.export_material(id, material.as_ref())?,
+            _ => None,
+        };
+        let index = self.root.push(json::Mesh {
+            extensions: Default::default(),
+            extras: Default::default(),
+            name: None,
+            primitives: vec![json::mesh::Primitive {
+                attributes,
+                extensions: Default::default(),
+                extras: Default::default(),
+                indices,
+                material,
+                mode: Valid(mode),
+                targets: None,
+            }],
+            weights: None,
+        });
+        self.meshes.insert((mesh_id, material_id), index);
+        Ok(index)
+    }
+
+    /// None for materials glTF can't describe, the primitive is exported without a material
+    fn export_material(
+        &mut self,
+        material_id: ComponentId,
+        material: &dyn MaterialTrait,
+    ) -> anyhow::Result<Option<Index<json::Material>>> {
+        if let Some(index) = self.materials.get(&material_id) {
+            return Ok(*index);
+        }
+        let material = material.as_any_ref();
+        let mut result = json::Material::default();
+        if let Some(basic) = material.downcast_ref::<BasicMaterial>() {
+            let config = &basic.config;
+            result.name = Some(config.name.clone());
+            // the textured shader ignores color
+            if config.texture.is_none() {
+                result.pbr_metallic_roughness.base_color_factor = PbrBaseColorFactor(config.color);
+            }
+            result.pbr_metallic_roughness.base_color_texture =
+                self.export_texture_info(config.texture.as_ref())?;
+            result.pbr_metallic_roughness.metallic_factor = StrengthFactor(0.0);
+        } else if let Some(blinn_phong) = material.downcast_ref::<BlinnPhongMaterial>() {
+            let config = &blinn_phong.config;
+            result.name = Some(config.name.clone());
+            result.pbr_metallic_roughness.base_color_factor =
+                PbrBaseColorFactor(config.diffuse_color.extend(config.opacity).to_array());
+            result.pbr_metallic_roughness.base_color_texture =
+                self.export_texture_info(config.diffuse_texture.as_ref())?;
+            result.pbr_metallic_roughness.metallic_factor = StrengthFactor(0.0);
+            // usual blinn-phong exponent to ggx roughness mapping
+            result.pbr_metallic_roughness.roughness_factor =
+                StrengthFactor((2.0 / (config.shininess + 2.0)).sqrt());
+            if config.opacity < 1.0 {
+                result.alpha_mode = Valid(json::material::AlphaMode::Blend);
+            }
+        } else if let Some(pbr) = material.downcast_ref::<PBRMaterial>() {
+            let config = &pbr.config;
+            result.name = Some(config.name.clone());
+            let pbr_metallic_roughness = &mut result.pbr_metallic_roughness;
+            pbr_metallic_roughness.base_color_factor =
+                PbrBaseColorFactor(config.base_color_factor.to_array());
+            pbr_metallic_roughness.base_color_texture =
+                self.export_texture_info(config.base_color_texture.as_ref())?;
+            pbr_metallic_roughness.metallic_factor = StrengthFactor(config.metallic_factor);
+            pbr_metallic_roughness.roughness_factor = StrengthFactor(config.roughness_factor);
+            pbr_metallic_roughness.metallic_roughness_texture =
+                self.export_texture_info(config.metallic_roughness_texture.as_ref())?;
+            result.normal_texture = self
+                .export_texture_info(config.normal_texture.as_ref())?
+                .map(|info| json::material::NormalTexture {
+                    index: info.index,
+                    scale: config.normal_scale,
+                    tex_coord: 0,
+                    extensions: Default::default(),
+                    extras: Default::default(),
+                });
+            result.occlusion_texture = self
+                .export_texture_info(config.occlusion_texture.as_ref())?
+                .map(|info| json::material::OcclusionTexture {
+                    index: info.index,
+                    strength: StrengthFactor(config.occlusion_strength),
+                    tex_coord: 0,
+                    extensions: Default::default(),
+                    extras: Default::default(),
+                });
+            result.emissive_factor = EmissiveFactor(config.emissive_factor.to_array());
+            result.emissive_texture = self.export_texture_info(config.emissive_texture.as_ref())?;
+            result.alpha_mode = Valid(match config.alpha_mode {
+                AlphaMode::Opaque => json::material::AlphaMode::Opaque,
+                AlphaMode::Mask => json::material::AlphaMode::Mask,
+                AlphaMode::Blend => json::material::AlphaMode::Blend,
+            });
+            if config.alpha_mode == AlphaMode::Mask {
+                result.alpha_cutoff = Some(AlphaCutoff(config.alpha_cutoff));
+            }
+            result.double_sided = config.double_sided;
+        } else {
+            self.materials.insert(material_id, None);
+            return Ok(None);
+        }
+        let index = Some(self.root.push(result));
+        self.materials.insert(material_id, index);
+        Ok(index)
+    }
+
+    fn export_texture_info(
+        &mut self,
+        texture: Option<&Texture>,
+    ) -> anyhow::Result<Option<json::texture::Info>> {
+        let Some(texture) = texture else {
+            return Ok(None);
+        };
+        let id = texture.texture.global_id();
+        let index = match self.textures.get(&id) {
+            Some(index) => *index,
+            None => {
+                let image = texture.read_image(&self.renderer.device, &self.renderer.queue)?;
+                let mut png = Cursor::new(vec![]);
+                image.write_to(&mut png, image::ImageOutputFormat::Png)?;
+                let view = self.push_view(png.get_ref(), None, None);
+                let source = self.root.push(json::Image {
+                    buffer_view: Some(view),
+                    mime_type: Some(json::image::MimeType("image/png".to_string())),
+                    name: None,
+                    uri: None,
+                    extensions: Default::default(),
+                    extras: Default::default(),
+                });
+                let index = self.root.push(json::Texture {
+                    name: None,
+                    sampler: None,
+                    source,
+                    extensions: Default::default(),
+                    extras: Default::default(),
+                });
+                self.textures.insert(id, index);
+                index
+            }
+        };
+        Ok(Some(json::texture::Info {
+            index,
+            tex_coord: 0,
+            extensions: Default::default(),
+            extras: Default::default(),
+        }))
+    }
+
+    /// append bytes to the binary chunk, views start on a 4 byte boundary
+    fn push_view(
+        &mut self,
+        bytes: &[u8],
+        stride: Option<usize>,
+        target: Option<Target>,
+    ) -> Index<json::buffer::View> {
+        self.bin.resize(self.bin.len().next_multiple_of(4), 0);
+        let offset = self.bin.len();
+        self.bin.extend_from_slice(bytes);
+        self.root.push(json::buffer::View {
+            buffer: Index::new(0),
+            byte_length: USize64::from(bytes.len()),
+            byte_offset: Some(USize64::from(offset)),
+            byte_stride: stride.map(Stride),
+            name: None,
+            target: target.map(Valid),
+            extensions: Default::default(),
+            extras: Default::default(),
+        })
+    }
+
+    fn finish(mut self) -> anyhow::Result<Vec<u8>> {
+        self.bin.resize(self.bin.len().next_multiple_of(4), 0);
+        self.root.push(json::Buffer {
+            byte_length: USize64::from(self.bin.len()),
+            name: None,
+            uri: None,
+            extensions: Default::default(),
+            extras: Default::default(),
+        });
+        let json = self.root.to_vec()?;
+        let glb = gltf::binary::Glb {
+            // to_vec computes the real length
+            header: gltf::binary::Header {
+                magic: *b"glTF",
+                version: 2,
+                length: 0,
+            },
+            json: Cow::Owned(json),
+            bin: Some(Cow::Owned(self.bin)),
+        };
+        Ok(glb.to_vec()?)
+    }
+}
+
+fn position_bounds(mesh: &Mesh, offset: usize, stride: usize) -> ([f32; 3], [f32; 3]) {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for vertex in mesh.vertices.chunks_exact(stride) {
+        let position: [f32; 3] = bytemuck::pod_read_unaligned(&vertex[offset..offset + 12]);
+        for i in 0..3 {
+            min[i] = min[i].min(position[i]);
+            max[i] = max[i].max(position[i]);
+        }
+    }
+    (min, max)
+}
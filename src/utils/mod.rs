@@ -1,7 +1,13 @@
 pub mod axis;
+pub mod buffer;
 pub mod camera;
 pub mod depth_texture;
 pub mod gltf;
+pub mod gltf_export;
 pub mod obj; //i need a group first ,so i can pack the meshs into a group
+pub mod ply;
+pub mod render_target;
 pub mod resource;
+pub mod shadow_map;
+pub mod stl;
 pub mod texture;
@@ -1,18 +1,33 @@
-use std::io::{BufReader, Cursor};
+use std::{
+    collections::HashMap,
+    io::{BufReader, Cursor},
+};
 
 use anyhow::Ok;
 use tobj::Model;
-use wgpu::util::DeviceExt;
+
+use glam::Vec3;
 
 use crate::{
-    components::{material::MaterialTrait, materials, mesh::Mesh},
-    entity::Entity,
+    component_store::{ComponentError, ComponentId},
+    components::{
+        material::MaterialTrait,
+        materials::{
+            blinn_phong::{BlinnPhongMaterial, BlinnPhongMaterialConfig},
+            render_state::RenderState,
+        },
+        mesh::{Mesh, VertexFormat, VertexPositionNormal, VertexPositionNormalTexture},
+        transform::Transform,
+    },
+    entity::{Entity, EntityId},
+    geometry::normals::{grouped_smooth_normals, FLAT_GROUP},
     mini_gpu::MiniGPU,
     renderer::Renderer,
+    scene::Scene,
 };
 
 use super::{
-    resource::{load_path, load_texture},
+    resource::{load_binary, load_path},
     texture::Texture,
 };
 
@@ -24,8 +39,9 @@ pub struct ModelVertex {
     pub normal: [f32; 3],
 }
 
-pub async fn load_obj(path: &std::path::Path, mini_gpu: &mut MiniGPU) -> anyhow::Result<usize> {
+pub async fn load_obj(path: &std::path::Path, mini_gpu: &mut MiniGPU) -> anyhow::Result<EntityId> {
     let obj_text: String = load_path(path).await?;
+    let smoothing_groups = parse_smoothing_groups(&obj_text);
     let mut obj_reader = BufReader::new(Cursor::new(obj_text));
     let (models, obj_materials) = tobj::load_obj_buf_async(
         &mut obj_reader,
@@ -36,7 +52,9 @@ pub async fn load_obj(path: &std::path::Path, mini_gpu: &mut MiniGPU) -> anyhow:
         },
         |p| async move {
             let mtl_path = path.parent().unwrap().join(&p);
-            let mat_text = load_path(&mtl_path).await.unwrap();
+            let mat_text = load_path(&mtl_path)
+                .await
+                .map_err(|_| tobj::LoadError::OpenFileFailed)?;
             tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mat_text)))
         },
     )
@@ -46,23 +64,28 @@ pub async fn load_obj(path: &std::path::Path, mini_gpu: &mut MiniGPU) -> anyhow:
     let materials = make_material_map(
         path.parent().unwrap(),
         obj_materials,
-        &std::collections::HashMap::new(),
+        &HashMap::new(),
         &mini_gpu.renderer,
     )
     .await?;
 
     let parent_id = &mini_gpu.scene.add_default_entity();
+    let transform = Transform::default(&mini_gpu.renderer);
+    mini_gpu
+        .scene
+        .set_entity_component(*parent_id, transform, "transform");
 
-    append_mesh_children(*parent_id, mini_gpu, models, materials);
+    append_mesh_children(*parent_id, mini_gpu, models, materials, &smoothing_groups)?;
     Ok(*parent_id)
 }
 
 pub async fn load_obj_by_url(
     obj_path: &str,
-    dir_buffer_map: &std::collections::HashMap<String, &[u8]>,
+    dir_buffer_map: &HashMap<String, &[u8]>,
     mini_gpu: &mut MiniGPU,
-) -> anyhow::Result<usize> {
+) -> anyhow::Result<EntityId> {
     let obj_text = dir_buffer_map.get(obj_path).unwrap();
+    let smoothing_groups = parse_smoothing_groups(&String::from_utf8_lossy(obj_text));
     let mut obj_reader = BufReader::new(Cursor::new(obj_text));
     let (models, obj_materials) = tobj::load_obj_buf_async(
         &mut obj_reader,
@@ -72,7 +95,9 @@ pub async fn load_obj_by_url(
             ..Default::default()
         },
         |p| async move {
-            let mtl_text = dir_buffer_map.get(&p).unwrap();
+            let Some(mtl_text) = dir_buffer_map.get(&p) else {
+                return Err(tobj::LoadError::OpenFileFailed);
+            };
             tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mtl_text)))
         },
     )
@@ -88,104 +113,360 @@ pub async fn load_obj_by_url(
     .await?;
 
     let parent_id = &mini_gpu.scene.add_default_entity();
+    let transform = Transform::default(&mini_gpu.renderer);
+    mini_gpu
+        .scene
+        .set_entity_component(*parent_id, transform, "transform");
 
-    append_mesh_children(*parent_id, mini_gpu, models, materials);
+    append_mesh_children(*parent_id, mini_gpu, models, materials, &smoothing_groups)?;
     Ok(*parent_id)
 }
 
+/// one BlinnPhongMaterial per MTL material, in MTL order so `material_id` indexes it.
+/// a material that fails to load is replaced by the default one, missing textures are skipped with a warning
 pub async fn make_material_map<'a>(
     material_path: &'a std::path::Path,
     obj_materials: Result<Vec<tobj::Material>, tobj::LoadError>,
-    dir_buffer_map: &'a std::collections::HashMap<String, &[u8]>,
+    dir_buffer_map: &'a HashMap<String, &[u8]>,
     renderer: &Renderer,
 ) -> anyhow::Result<Vec<Box<dyn MaterialTrait>>> {
+    let obj_materials = obj_materials.unwrap_or_else(|e| {
+        log::warn!("obj: failed to load mtl, {}", e);
+        vec![]
+    });
     let mut materials: Vec<Box<dyn MaterialTrait>> = Vec::new();
-    for m in obj_materials? {
-        let m_string = match m.diffuse_texture {
-            Some(ref name) => name,
-            None => {
-                continue;
-            }
+    for m in obj_materials {
+        let mut config = BlinnPhongMaterialConfig {
+            name: m.name.clone(),
+            ..Default::default()
         };
+        if let Some(ambient) = m.ambient {
+            config.ambient_color = Vec3::from_array(ambient);
+        }
+        if let Some(diffuse) = m.diffuse {
+            config.diffuse_color = Vec3::from_array(diffuse);
+        }
+        if let Some(specular) = m.specular {
+            config.specular_color = Vec3::from_array(specular);
+        }
+        if let Some(shininess) = m.shininess {
+            config.shininess = shininess;
+        }
+        // Tr is the inverse of d, some exporters only write that one
+        let transparency = m
+            .unknown_param
+            .get("Tr")
+            .and_then(|tr| tr.parse::<f32>().ok());
+        if let Some(opacity) = m.dissolve.or(transparency.map(|tr| 1.0 - tr)) {
+            config.opacity = opacity.clamp(0.0, 1.0);
+        }
+        if config.opacity < 1.0 {
+            // blended, and not hiding what's drawn behind it later
+            config.render_state = RenderState {
+                depth_write: false,
+                ..RenderState::transparent()
+            };
+        }
 
-        let diffuse_path = material_path.join(m_string);
-        let diffuse_path_string = diffuse_path.to_str().unwrap();
-        // use different material here,but we now only have image material
-
-        let diffuse_buffer = dir_buffer_map.get(diffuse_path_string);
-        let diffuse_texture: Texture = {
-            if let Some(buffer) = diffuse_buffer {
-                Texture::from_bytes(
-                    &renderer.device,
-                    &renderer.queue,
-                    &buffer,
-                    diffuse_path_string,
-                )?
-            } else {
-                load_texture(diffuse_path_string, &renderer.device, &renderer.queue).await?
-            }
-        };
+        // map_Bump and bump are parsed into normal_texture by tobj, norm is left in unknown_param
+        let normal_texture = m.normal_texture.as_ref().or(m.unknown_param.get("norm"));
+        config.ambient_texture = load_material_texture(
+            material_path,
+            m.ambient_texture.as_ref(),
+            false,
+            dir_buffer_map,
+            renderer,
+        )
+        .await;
+        config.diffuse_texture = load_material_texture(
+            material_path,
+            m.diffuse_texture.as_ref(),
+            false,
+            dir_buffer_map,
+            renderer,
+        )
+        .await;
+        config.specular_texture = load_material_texture(
+            material_path,
+            m.specular_texture.as_ref(),
+            false,
+            dir_buffer_map,
+            renderer,
+        )
+        .await;
+        // normal maps hold vectors, not colors
+        config.normal_texture = load_material_texture(
+            material_path,
+            normal_texture,
+            true,
+            dir_buffer_map,
+            renderer,
+        )
+        .await;
+        config.use_texture = config.diffuse_texture.is_some()
+            || config.specular_texture.is_some()
+            || config.normal_texture.is_some()
+            || config.ambient_texture.is_some();
 
-        let material = materials::blinn_phong::BlinnPhongMaterial::new(
-            materials::blinn_phong::BlinnPhongMaterialConfig {
-                name: m.name,
-                diffuse_texture: Some(diffuse_texture),
-                ..Default::default()
-            },
-            &renderer,
-        );
+        let material = BlinnPhongMaterial::new(config, renderer);
         materials.push(Box::new(material));
-        break;
     }
     Ok(materials)
 }
 
+/// texture statements can carry options before the file name, e.g. `map_Bump -bm 0.5 normal.png`
+fn texture_file_name(statement: &str) -> &str {
+    if statement.starts_with('-') {
+        statement.split_whitespace().last().unwrap_or(statement)
+    } else {
+        statement
+    }
+}
+
+async fn load_material_texture(
+    material_path: &std::path::Path,
+    statement: Option<&String>,
+    linear: bool,
+    dir_buffer_map: &HashMap<String, &[u8]>,
+    renderer: &Renderer,
+) -> Option<Texture> {
+    let path = material_path.join(texture_file_name(statement?));
+    let path_string = path.to_string_lossy();
+    let bytes = match dir_buffer_map.get(path_string.as_ref()) {
+        Some(buffer) => Ok(buffer.to_vec()),
+        None => load_binary(&path_string).await,
+    };
+    let texture = bytes.and_then(|bytes| {
+        let image = image::load_from_memory(&bytes)?;
+        if linear {
+            Texture::from_image_linear(
+                &renderer.device,
+                &renderer.queue,
+                &image,
+                Some(&path_string),
+            )
+        } else {
+            Texture::from_image(
+                &renderer.device,
+                &renderer.queue,
+                &image,
+                Some(&path_string),
+            )
+        }
+    });
+    if let Err(e) = &texture {
+        log::warn!("obj: failed to load texture {}, {}", path_string, e);
+    }
+    texture.ok()
+}
+
+/// children get the material at the model's material_id, models without one share a default BlinnPhongMaterial.
+/// meshes with and without uvs never share a material, its pipeline has one vertex layout.
+/// smoothing_groups has one group per triangle in file order (see parse_smoothing_groups), it's only used
+/// for meshes without normals. fails when parent is stale
 pub fn append_mesh_children(
-    parent: usize,
+    parent: EntityId,
     mini_gpu: &mut MiniGPU,
     models: Vec<Model>,
     materials: Vec<Box<dyn MaterialTrait>>,
-) -> usize {
-    let mut i = 0;
-    let material_ids: Vec<usize> = materials
+    smoothing_groups: &[u32],
+) -> anyhow::Result<EntityId> {
+    if !mini_gpu.scene.contains_entity(parent) {
+        return Err(ComponentError::EntityNotFound(parent).into());
+    }
+    let material_ids: Vec<ComponentId> = materials
         .into_iter()
         .map(|m| mini_gpu.scene.add_component(m))
         .collect();
-    models.into_iter().for_each(|model| {
-        let material_index = material_ids[model.mesh.material_id.unwrap_or(0)];
-        let mesh = build_mesh(&mini_gpu.renderer, model.mesh);
+    // whether each loaded material is drawn with uvs, a textured one always is.
+    // the pipeline is built for one vertex layout, meshes with the other one get an untextured copy
+    let mut material_tex_coords: HashMap<usize, bool> = HashMap::new();
+    // (material_id, has_tex_coords) -> the copy, None is the default material
+    let mut material_copies: HashMap<(Option<usize>, bool), ComponentId> = HashMap::new();
+    let mut first_triangle = 0;
+    for model in models {
+        let triangle_count = model.mesh.indices.len() / 3;
+        let groups = smoothing_groups
+            .get(first_triangle..first_triangle + triangle_count)
+            .unwrap_or(&[]);
+        first_triangle += triangle_count;
+
+        let has_tex_coords = has_tex_coords(&model.mesh);
+        let material_id = model.mesh.material_id.filter(|id| *id < material_ids.len());
+        let loaded_tex_coords = material_id.map(|id| {
+            *material_tex_coords.entry(id).or_insert_with(|| {
+                has_tex_coords || uses_texture(&mini_gpu.scene, material_ids[id])
+            })
+        });
+        let material_index = match material_id {
+            Some(id) if loaded_tex_coords == Some(has_tex_coords) => material_ids[id],
+            _ => *material_copies
+                .entry((material_id, has_tex_coords))
+                .or_insert_with(|| match material_id {
+                    Some(id) => untextured_material(
+                        &mut mini_gpu.scene,
+                        material_ids[id],
+                        &mini_gpu.renderer,
+                    )
+                    .unwrap_or(material_ids[id]),
+                    None => {
+                        let material: Box<dyn MaterialTrait> = Box::new(BlinnPhongMaterial::new(
+                            BlinnPhongMaterialConfig::default(),
+                            &mini_gpu.renderer,
+                        ));
+                        mini_gpu.scene.add_component(material)
+                    }
+                }),
+        };
+        let mesh = build_mesh(&mini_gpu.renderer, model.mesh, groups);
         let mut child = Entity::new();
         child.name = model.name;
+        child.set_component_index("material", material_index);
+        let child_id = mini_gpu.scene.add_entity_child(parent, child)?;
         mini_gpu
             .scene
-            .set_entity_component::<Mesh>(child.id, mesh, "mesh");
-        child.set_component_index("material", material_index);
-        let _ = &mini_gpu.scene.add_entity_child(parent, child);
-        i += 1;
-    });
-    parent
+            .set_entity_component::<Mesh>(child_id, mesh, "mesh");
+        let transform = Transform::default(&mini_gpu.renderer);
+        mini_gpu
+            .scene
+            .set_entity_component(child_id, transform, "transform");
+    }
+    Ok(parent)
 }
 
-pub fn build_mesh(renderer: &Renderer, mesh: tobj::Mesh) -> Mesh {
-    let mut vertices: Vec<f32> = Vec::new();
-    (0..mesh.positions.len() / 3).for_each(|i| {
-        vertices.append(&mut vec![
-            mesh.positions[i * 3],
-            mesh.positions[i * 3 + 1],
-            mesh.positions[i * 3 + 2],
-            mesh.normals[i * 3],
-            mesh.normals[i * 3 + 1],
-            mesh.normals[i * 3 + 2],
-            mesh.texcoords[i * 2],
-            mesh.texcoords[i * 2 + 1],
-        ])
-    });
-    Mesh::new(
-        bytemuck::cast_slice(&vertices),
-        mesh.indices,
-        crate::components::mesh::VertexFormat::PositionNormalTexture,
+fn uses_texture(scene: &Scene, material_id: ComponentId) -> bool {
+    scene
+        .get_component::<Box<dyn MaterialTrait>>(material_id)
+        .ok()
+        .and_then(|material| material.as_any_ref().downcast_ref::<BlinnPhongMaterial>())
+        .is_some_and(|material| material.config.use_texture)
+}
+
+/// a copy of a BlinnPhongMaterial without its textures, None for other materials
+fn untextured_material(
+    scene: &mut Scene,
+    material_id: ComponentId,
+    renderer: &Renderer,
+) -> Option<ComponentId> {
+    let material = scene
+        .get_component::<Box<dyn MaterialTrait>>(material_id)
+        .ok()?;
+    let config = &material
+        .as_any_ref()
+        .downcast_ref::<BlinnPhongMaterial>()?
+        .config;
+    let material: Box<dyn MaterialTrait> = Box::new(BlinnPhongMaterial::new(
+        BlinnPhongMaterialConfig {
+            name: config.name.clone(),
+            ambient_color: config.ambient_color,
+            diffuse_color: config.diffuse_color,
+            diffuse_strength: config.diffuse_strength,
+            specular_color: config.specular_color,
+            specular_strength: config.specular_strength,
+            shininess: config.shininess,
+            opacity: config.opacity,
+            render_state: config.render_state,
+            ..Default::default()
+        },
         renderer,
-    )
+    ));
+    Some(scene.add_component(material))
+}
+
+fn has_tex_coords(mesh: &tobj::Mesh) -> bool {
+    !mesh.texcoords.is_empty() && mesh.texcoords.len() / 2 == mesh.positions.len() / 3
+}
+
+/// smoothing group of every triangle, in the order tobj triangulates the faces (a fan per face).
+/// `s off` and `s 0` make faces flat, faces before the first `s` statement are smoothed together
+pub fn parse_smoothing_groups(obj_text: &str) -> Vec<u32> {
+    let mut groups = vec![];
+    let mut group = DEFAULT_SMOOTHING_GROUP;
+    for line in obj_text.lines() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("s") => {
+                group = match words.next() {
+                    Some("off") | None => FLAT_GROUP,
+                    Some(value) => value.parse().unwrap_or(FLAT_GROUP),
+                };
+            }
+            Some("f") => {
+                let triangles = words.count().saturating_sub(2);
+                groups.extend(std::iter::repeat_n(group, triangles));
+            }
+            _ => {}
+        }
+    }
+    groups
+}
+
+// not a valid group number in a file, so it doesn't merge with `s 1`
+const DEFAULT_SMOOTHING_GROUP: u32 = u32::MAX;
+
+/// positions are always read, missing normals are generated from the smoothing groups and
+/// the VertexFormat has texture coordinates only if the mesh has them
+pub fn build_mesh(renderer: &Renderer, mesh: tobj::Mesh, smoothing_groups: &[u32]) -> Mesh {
+    let positions: Vec<[f32; 3]> = mesh
+        .positions
+        .chunks_exact(3)
+        .map(|p| [p[0], p[1], p[2]])
+        .collect();
+    let tex_coords: Option<Vec<[f32; 2]>> = has_tex_coords(&mesh).then(|| {
+        mesh.texcoords
+            .chunks_exact(2)
+            .map(|t| [t[0], t[1]])
+            .collect()
+    });
+    let has_normals = !mesh.normals.is_empty() && mesh.normals.len() == mesh.positions.len();
+
+    // without normals vertices are split by smoothing group, so every attribute is read through sources
+    let (sources, indices, normals) = if has_normals {
+        let normals = mesh
+            .normals
+            .chunks_exact(3)
+            .map(|n| [n[0], n[1], n[2]])
+            .collect();
+        ((0..positions.len() as u32).collect(), mesh.indices, normals)
+    } else {
+        grouped_smooth_normals(&positions, &mesh.indices, smoothing_groups)
+    };
+
+    match tex_coords {
+        Some(tex_coords) => {
+            let vertices: Vec<VertexPositionNormalTexture> = sources
+                .iter()
+                .zip(normals)
+                .map(|(source, normal)| VertexPositionNormalTexture {
+                    position: positions[*source as usize],
+                    normal,
+                    tex_coords: tex_coords[*source as usize],
+                })
+                .collect();
+            Mesh::new(
+                bytemuck::cast_slice(&vertices),
+                indices,
+                VertexFormat::PositionNormalTexture,
+                renderer,
+            )
+        }
+        None => {
+            let vertices: Vec<VertexPositionNormal> = sources
+                .iter()
+                .zip(normals)
+                .map(|(source, normal)| VertexPositionNormal {
+                    position: positions[*source as usize],
+                    normal,
+                })
+                .collect();
+            Mesh::new(
+                bytemuck::cast_slice(&vertices),
+                indices,
+                VertexFormat::PositionNormal,
+                renderer,
+            )
+        }
+    }
 }
 
 pub fn get_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
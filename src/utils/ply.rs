@@ -0,0 +1,404 @@
+use anyhow::anyhow;
+
+use crate::{
+    components::{
+        material::MaterialTrait,
+        materials::{
+            basic::{BasicMaterial, BasicMaterialConfig},
+            blinn_phong::{BlinnPhongMaterial, BlinnPhongMaterialConfig},
+        },
+        mesh::{
+            Mesh, VertexFormat, VertexPositionColor, VertexPositionNormal,
+            VertexPositionNormalColor,
+        },
+        transform::Transform,
+    },
+    entity::{Entity, EntityId},
+    geometry::normals::smooth_normals,
+    mini_gpu::MiniGPU,
+};
+
+/// vertices and triangles of a ply file, faces are fan triangulated
+pub struct PlyData {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Option<Vec<[f32; 3]>>,
+    /// rgba in 0..1, integer colors are normalized by their type's max
+    pub colors: Option<Vec<[f32; 4]>>,
+    /// empty for point clouds
+    pub indices: Vec<u32>,
+}
+
+/// load an ascii or binary ply into a child entity of the returned one, like load_obj.
+/// meshes get a BlinnPhongMaterial, files without faces are point clouds drawn by a BasicMaterial with PointList topology.
+/// vertex colors are multiplied into the material color
+pub fn load_ply(bytes: &[u8], mini_gpu: &mut MiniGPU) -> anyhow::Result<EntityId> {
+    let ply = parse_ply(bytes)?;
+    if ply.positions.is_empty() {
+        return Err(anyhow!("ply: no vertices"));
+    }
+    let renderer = &mini_gpu.renderer;
+    let (mesh, material): (Mesh, Box<dyn MaterialTrait>) = if ply.indices.is_empty() {
+        let indices = (0..ply.positions.len() as u32).collect();
+        let mesh = match &ply.colors {
+            Some(colors) => {
+                let vertices: Vec<VertexPositionColor> = ply
+                    .positions
+                    .iter()
+                    .zip(colors)
+                    .map(|(position, color)| VertexPositionColor {
+                        position: *position,
+                        color: *color,
+                    })
+                    .collect();
+                Mesh::new(
+                    bytemuck::cast_slice(&vertices),
+                    indices,
+                    VertexFormat::PositionColor,
+                    renderer,
+                )
+            }
+            None => Mesh::new_position_only(ply.positions.concat(), indices, renderer),
+        };
+        let material = BasicMaterial::new(
+            BasicMaterialConfig {
+                vertex_color: ply.colors.is_some(),
+                topology: wgpu::PrimitiveTopology::PointList,
+                ..Default::default()
+            },
+            renderer,
+        );
+        (mesh, Box::new(material))
+    } else {
+        let normals = ply
+            .normals
+            .unwrap_or_else(|| smooth_normals(&ply.positions, &ply.indices));
+        let mesh = match &ply.colors {
+            Some(colors) => {
+                let vertices: Vec<VertexPositionNormalColor> = ply
+                    .positions
+                    .iter()
+                    .zip(normals)
+                    .zip(colors)
+                    .map(|((position, normal), color)| VertexPositionNormalColor {
+                        position: *position,
+                        normal,
+                        color: *color,
+                    })
+                    .collect();
+                Mesh::new(
+                    bytemuck::cast_slice(&vertices),
+                    ply.indices,
+                    VertexFormat::PositionNormalColor,
+                    renderer,
+                )
+            }
+            None => {
+                let vertices: Vec<VertexPositionNormal> = ply
+                    .positions
+                    .iter()
+                    .zip(normals)
+                    .map(|(position, normal)| VertexPositionNormal {
+                        position: *position,
+                        normal,
+                    })
+                    .collect();
+                Mesh::new(
+                    bytemuck::cast_slice(&vertices),
+                    ply.indices,
+                    VertexFormat::PositionNormal,
+                    renderer,
+                )
+            }
+        };
+        let mut config = BlinnPhongMaterialConfig::default();
+        if ply.colors.is_some() {
+            // the vertex colors replace the default gray
+            config.diffuse_color = glam::Vec3::ONE;
+            config.vertex_color = true;
+        }
+        (mesh, Box::new(BlinnPhongMaterial::new(config, renderer)))
+    };
+
+    let parent_id = mini_gpu.scene.add_default_entity();
+    let transform = Transform::default(&mini_gpu.renderer);
+    mini_gpu
+        .scene
+        .set_entity_component(parent_id, transform, "transform");
+    let mut child = Entity::new();
+    child.name = "ply".to_string();
+    let child_id = mini_gpu.scene.add_entity_child(parent_id, child)?;
+    mini_gpu.scene.set_entity_component(child_id, mesh, "mesh");
+    mini_gpu
+        .scene
+        .set_entity_component(child_id, material, "material");
+    let transform = Transform::default(&mini_gpu.renderer);
+    mini_gpu
+        .scene
+        .set_entity_component(child_id, transform, "transform");
+    Ok(parent_id)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> anyhow::Result<ScalarType> {
+        Ok(match name {
+            "char" | "int8" => ScalarType::I8,
+            "uchar" | "uint8" => ScalarType::U8,
+            "short" | "int16" => ScalarType::I16,
+            "ushort" | "uint16" => ScalarType::U16,
+            "int" | "int32" => ScalarType::I32,
+            "uint" | "uint32" => ScalarType::U32,
+            "float" | "float32" => ScalarType::F32,
+            "double" | "float64" => ScalarType::F64,
+            _ => return Err(anyhow!("ply: unknown property type {}", name)),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::I8 | ScalarType::U8 => 1,
+            ScalarType::I16 | ScalarType::U16 => 2,
+            ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+            ScalarType::F64 => 8,
+        }
+    }
+
+    /// colors stored as integers are scaled to 0..1
+    fn color_scale(self) -> f64 {
+        match self {
+            ScalarType::U8 => 1. / 255.,
+            ScalarType::U16 => 1. / 65535.,
+            _ => 1.,
+        }
+    }
+}
+
+struct Property {
+    name: String,
+    ty: ScalarType,
+    /// count type of list properties
+    list: Option<ScalarType>,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn find(&self, names: &[&str]) -> Option<usize> {
+        self.properties
+            .iter()
+            .position(|property| names.contains(&property.name.as_str()))
+    }
+}
+
+/// reads the body value by value, ascii bodies are a stream of whitespace separated numbers
+enum BodyReader<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary {
+        bytes: &'a [u8],
+        offset: usize,
+        big_endian: bool,
+    },
+}
+
+impl BodyReader<'_> {
+    fn read(&mut self, ty: ScalarType) -> anyhow::Result<f64> {
+        match self {
+            BodyReader::Ascii(words) => words
+                .next()
+                .and_then(|word| word.parse().ok())
+                .ok_or_else(|| anyhow!("ply: missing or bad value")),
+            BodyReader::Binary {
+                bytes,
+                offset,
+                big_endian,
+            } => {
+                let value = bytes
+                    .get(*offset..*offset + ty.size())
+                    .ok_or_else(|| anyhow!("ply: unexpected end of file"))?;
+                *offset += ty.size();
+                let mut buffer = [0u8; 8];
+                buffer[..value.len()].copy_from_slice(value);
+                if *big_endian {
+                    buffer[..value.len()].reverse();
+                }
+                Ok(match ty {
+                    ScalarType::I8 => buffer[0] as i8 as f64,
+                    ScalarType::U8 => buffer[0] as f64,
+                    ScalarType::I16 => i16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+                    ScalarType::U16 => u16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+                    ScalarType::I32 => i32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+                    ScalarType::U32 => u32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+                    ScalarType::F32 => f32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+                    ScalarType::F64 => f64::from_le_bytes(buffer),
+                })
+            }
+        }
+    }
+}
+
+/// parse the header and the vertex and face elements, other elements are skipped
+pub fn parse_ply(bytes: &[u8]) -> anyhow::Result<PlyData> {
+    let header_end = bytes
+        .windows(10)
+        .position(|window| window == b"end_header")
+        .ok_or_else(|| anyhow!("ply: no end_header"))?;
+    let body_start = bytes[header_end..]
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .map_or(bytes.len(), |newline| header_end + newline + 1);
+    let header = std::str::from_utf8(&bytes[..header_end])?;
+
+    let mut lines = header.lines().map(str::trim);
+    if lines.next() != Some("ply") {
+        return Err(anyhow!("ply: missing magic number"));
+    }
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", name, ..] => {
+                format = Some(match *name {
+                    "ascii" => PlyFormat::Ascii,
+                    "binary_little_endian" => PlyFormat::BinaryLittleEndian,
+                    "binary_big_endian" => PlyFormat::BinaryBigEndian,
+                    _ => return Err(anyhow!("ply: unknown format {}", name)),
+                })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse()?,
+                properties: vec![],
+            }),
+            ["property", "list", count_type, ty, name] => elements
+                .last_mut()
+                .ok_or_else(|| anyhow!("ply: property before element"))?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    ty: ScalarType::parse(ty)?,
+                    list: Some(ScalarType::parse(count_type)?),
+                }),
+            ["property", ty, name] => elements
+                .last_mut()
+                .ok_or_else(|| anyhow!("ply: property before element"))?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    ty: ScalarType::parse(ty)?,
+                    list: None,
+                }),
+            // comment, obj_info
+            _ => {}
+        }
+    }
+
+    let body = &bytes[body_start..];
+    let mut reader = match format.ok_or_else(|| anyhow!("ply: missing format"))? {
+        PlyFormat::Ascii => BodyReader::Ascii(std::str::from_utf8(body)?.split_ascii_whitespace()),
+        format => BodyReader::Binary {
+            bytes: body,
+            offset: 0,
+            big_endian: format == PlyFormat::BinaryBigEndian,
+        },
+    };
+
+    let mut ply = PlyData {
+        positions: vec![],
+        normals: None,
+        colors: None,
+        indices: vec![],
+    };
+    let mut values = vec![];
+    for element in &elements {
+        let position = ["x", "y", "z"].map(|name| element.find(&[name]));
+        let normal = ["nx", "ny", "nz"].map(|name| element.find(&[name]));
+        let color = [
+            ["red", "r", "diffuse_red"],
+            ["green", "g", "diffuse_green"],
+            ["blue", "b", "diffuse_blue"],
+            ["alpha", "a", "diffuse_alpha"],
+        ]
+        .map(|names| element.find(&names));
+        let face = element.find(&["vertex_indices", "vertex_index"]);
+        let is_vertex = element.name == "vertex";
+        let has_normal = is_vertex && normal.iter().all(Option::is_some);
+        let has_color = is_vertex && color[..3].iter().all(Option::is_some);
+        if has_normal {
+            ply.normals = Some(vec![]);
+        }
+        if has_color {
+            ply.colors = Some(vec![]);
+        }
+
+        for _ in 0..element.count {
+            values.clear();
+            let mut face_indices = vec![];
+            for (index, property) in element.properties.iter().enumerate() {
+                match property.list {
+                    Some(count_type) => {
+                        let count = reader.read(count_type)? as usize;
+                        for _ in 0..count {
+                            let value = reader.read(property.ty)?;
+                            if element.name == "face" && face == Some(index) {
+                                face_indices.push(value as u32);
+                            }
+                        }
+                        values.push(0.);
+                    }
+                    None => values.push(reader.read(property.ty)?),
+                }
+            }
+            if is_vertex {
+                ply.positions
+                    .push(position.map(|index| index.map_or(0., |index| values[index] as f32)));
+                if let (true, Some(normals)) = (has_normal, ply.normals.as_mut()) {
+                    normals.push(normal.map(|index| values[index.unwrap()] as f32));
+                }
+                if let (true, Some(colors)) = (has_color, ply.colors.as_mut()) {
+                    colors.push(color.map(|index| match index {
+                        Some(index) => {
+                            (values[index] * element.properties[index].ty.color_scale()) as f32
+                        }
+                        None => 1.,
+                    }));
+                }
+            }
+            // fan triangulation
+            for i in 2..face_indices.len() {
+                ply.indices
+                    .extend([face_indices[0], face_indices[i - 1], face_indices[i]]);
+            }
+        }
+    }
+    if let Some(index) = ply
+        .indices
+        .iter()
+        .find(|index| **index as usize >= ply.positions.len())
+    {
+        return Err(anyhow!("ply: face index {} out of range", index));
+    }
+    Ok(ply)
+}
@@ -0,0 +1,368 @@
+use std::borrow::Cow;
+
+use glam::Mat4;
+use wgpu::{util::DeviceExt, CommandEncoder, StoreOp};
+
+use crate::{
+    components::{
+        instance::{Instance, InstanceLayout},
+        lights::light::LightTrait,
+        materials::shader::ShaderParser,
+        mesh::{Mesh, VertexFormat},
+        morph::MorphWeights,
+        shadow::MAX_SHADOW_MAPS,
+        skin::{Skin, SKIN_BIND_GROUP_INDEX},
+        transform::{Transform, TRANSFORM_BIND_GROUP_INDEX},
+    },
+    scene::Scene,
+};
+
+use super::depth_texture::DepthTexture;
+
+/// what a caster's depth pipeline depends on
+#[derive(PartialEq)]
+struct ShadowPipelineKey {
+    array_stride: wgpu::BufferAddress,
+    attributes: Vec<wgpu::VertexAttribute>,
+    instance: Option<InstanceLayout>,
+    skinned: bool,
+    // a morph bind group carries the joint matrices when the entity has a skin
+    morphed: bool,
+    has_skin: bool,
+}
+
+impl ShadowPipelineKey {
+    fn new(
+        mesh: &Mesh,
+        instance: Option<&Instance>,
+        skin: Option<&Skin>,
+        morph_weights: Option<&MorphWeights>,
+    ) -> Self {
+        Self {
+            array_stride: mesh.vertex_buffer_layout.array_stride,
+            attributes: mesh.vertex_attributes.clone(),
+            instance: instance.map(|instance| instance.layout.clone()),
+            skinned: skin.is_some()
+                && mesh.vertex_format == VertexFormat::PositionNormalTextureSkin,
+            morphed: morph_weights.is_some(),
+            has_skin: skin.is_some(),
+        }
+    }
+}
+
+/// the depth maps of all shadow casting lights, one layer of a 2d array texture each.
+/// MeshRender draws them before its main pass and binds them to the env bind group
+pub struct ShadowMaps {
+    pub texture: wgpu::Texture,
+    /// all layers, texture_depth_2d_array in the shaders
+    pub view: wgpu::TextureView,
+    /// render target of every layer
+    pub layer_views: Vec<wgpu::TextureView>,
+    pub sampler: wgpu::Sampler,
+    /// texels per side of every layer
+    pub size: u32,
+    pass_buffers: Vec<wgpu::Buffer>,
+    pass_bind_groups: Vec<wgpu::BindGroup>,
+    pass_bind_group_layout: wgpu::BindGroupLayout,
+    // group 1 is the env bind group in the main pass, the depth pass doesn't use it
+    empty_bind_group: wgpu::BindGroup,
+    empty_bind_group_layout: wgpu::BindGroupLayout,
+    pipelines: Vec<(ShadowPipelineKey, wgpu::RenderPipeline)>,
+}
+
+impl ShadowMaps {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let (texture, view, layer_views) = Self::create_texture(device, 1);
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadow Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+        let pass_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Shadow Pass Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+        let pass_buffers: Vec<wgpu::Buffer> = (0..MAX_SHADOW_MAPS)
+            .map(|_| {
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Shadow Pass Buffer"),
+                    contents: bytemuck::cast_slice(&Mat4::IDENTITY.to_cols_array()),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                })
+            })
+            .collect();
+        let pass_bind_groups = pass_buffers
+            .iter()
+            .map(|buffer| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Shadow Pass Bind Group"),
+                    layout: &pass_bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                })
+            })
+            .collect();
+        let empty_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Shadow Empty Bind Group Layout"),
+                entries: &[],
+            });
+        let empty_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shadow Empty Bind Group"),
+            layout: &empty_bind_group_layout,
+            entries: &[],
+        });
+        Self {
+            texture,
+            view,
+            layer_views,
+            sampler,
+            size: 1,
+            pass_buffers,
+            pass_bind_groups,
+            pass_bind_group_layout,
+            empty_bind_group,
+            empty_bind_group_layout,
+            pipelines: vec![],
+        }
+    }
+
+    fn create_texture(
+        device: &wgpu::Device,
+        size: u32,
+    ) -> (wgpu::Texture, wgpu::TextureView, Vec<wgpu::TextureView>) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Shadow Maps"),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: MAX_SHADOW_MAPS as u32,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DepthTexture::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Shadow Maps View"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let layer_views = (0..MAX_SHADOW_MAPS as u32)
+            .map(|layer| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Shadow Map Layer View"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        (texture, view, layer_views)
+    }
+
+    /// size the maps for the scene's lights and build the depth pipelines of new casters,
+    /// the renderer calls it before the systems run
+    pub fn prepare(&mut self, device: &wgpu::Device, scene: &Scene) {
+        let size = scene
+            .query::<&Box<dyn LightTrait>>()
+            .filter_map(|(_, light)| light.get_shadow_config())
+            .map(|config| config.map_size)
+            .max();
+        let Some(size) = size else {
+            return;
+        };
+        let size = size.clamp(1, device.limits().max_texture_dimension_2d);
+        if size != self.size {
+            (self.texture, self.view, self.layer_views) = Self::create_texture(device, size);
+            self.size = size;
+        }
+
+        let casters = scene.query::<(
+            &Mesh,
+            Option<&Instance>,
+            Option<&Skin>,
+            Option<&MorphWeights>,
+        )>();
+        for (entity_id, (mesh, instance, skin, morph_weights)) in casters {
+            if !scene.get_entity(entity_id).is_some_and(|e| e.cast_shadow) {
+                continue;
+            }
+            let key = ShadowPipelineKey::new(mesh, instance, skin, morph_weights);
+            if self.pipelines.iter().all(|(cached, _)| *cached != key) {
+                let pipeline = self.create_pipeline(device, &key, mesh, instance);
+                self.pipelines.push((key, pipeline));
+            }
+        }
+    }
+
+    fn create_pipeline(
+        &self,
+        device: &wgpu::Device,
+        key: &ShadowPipelineKey,
+        mesh: &Mesh,
+        instance: Option<&Instance>,
+    ) -> wgpu::RenderPipeline {
+        let mut parser = ShaderParser::new();
+        for (name, enabled) in [("HAS_SKIN", key.skinned), ("HAS_MORPH", key.morphed)] {
+            if enabled {
+                parser.defines.insert(name.to_string(), "true".to_string());
+            }
+        }
+        if let Some(layout) = &key.instance {
+            layout.configure_parser(&mut parser);
+        }
+        let shader_text = parser.parse_shader(include_str!(
+            "../components/materials/shaders/shadow_depth.wgsl"
+        ));
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shadow Depth Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(&shader_text)),
+        });
+
+        let transform_layout = Transform::create_bind_group_layout(device);
+        let deform_layout = if key.morphed {
+            Some(MorphWeights::create_bind_group_layout(device, key.has_skin))
+        } else if key.has_skin {
+            Some(Skin::create_bind_group_layout(device))
+        } else {
+            None
+        };
+        let mut layouts = vec![
+            &self.pass_bind_group_layout,
+            &self.empty_bind_group_layout,
+            &transform_layout,
+        ];
+        if let Some(layout) = &deform_layout {
+            layouts.push(layout);
+        }
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &layouts,
+            push_constant_ranges: &[],
+        });
+
+        let mut buffers = vec![mesh.vertex_buffer_layout.clone()];
+        if let Some(instance) = instance {
+            buffers.push(instance.vertex_buffer_layout());
+        }
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shadow Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader_module,
+                entry_point: "vs_main",
+                buffers: &buffers,
+                compilation_options: Default::default(),
+            },
+            fragment: None,
+            // both faces, so open meshes cast too
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DepthTexture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+
+    /// one depth pass per view projection, drawing every entity with cast_shadow.
+    /// casters added after `prepare` are drawn from the next frame on
+    pub fn render(
+        &self,
+        encoder: &mut CommandEncoder,
+        queue: &wgpu::Queue,
+        scene: &Scene,
+        view_projections: &[Mat4],
+        default_transform: &Transform,
+    ) {
+        for (layer, view_projection) in view_projections.iter().enumerate().take(MAX_SHADOW_MAPS) {
+            queue.write_buffer(
+                &self.pass_buffers[layer],
+                0,
+                bytemuck::cast_slice(&view_projection.to_cols_array()),
+            );
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Shadow Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.layer_views[layer],
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_bind_group(0, &self.pass_bind_groups[layer], &[]);
+            render_pass.set_bind_group(1, &self.empty_bind_group, &[]);
+
+            let casters = scene.query::<(
+                &Mesh,
+                Option<&Instance>,
+                Option<&Transform>,
+                Option<&Skin>,
+                Option<&MorphWeights>,
+            )>();
+            for (entity_id, (mesh, instance, transform, skin, morph_weights)) in casters {
+                if !scene.get_entity(entity_id).is_some_and(|e| e.cast_shadow) {
+                    continue;
+                }
+                let key = ShadowPipelineKey::new(mesh, instance, skin, morph_weights);
+                let Some((_, pipeline)) = self.pipelines.iter().find(|(cached, _)| *cached == key)
+                else {
+                    continue;
+                };
+                render_pass.set_pipeline(pipeline);
+                render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                let mut instance_len = 1;
+                if let Some(instance) = instance {
+                    render_pass
+                        .set_vertex_buffer(Instance::get_buffer_index(), instance.buffer.slice(..));
+                    instance_len = instance.count();
+                }
+                render_pass
+                    .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                let transform = transform.unwrap_or(default_transform);
+                render_pass.set_bind_group(TRANSFORM_BIND_GROUP_INDEX, &transform.bind_group, &[]);
+                if let Some(morph_weights) = morph_weights {
+                    render_pass.set_bind_group(
+                        SKIN_BIND_GROUP_INDEX,
+                        &morph_weights.bind_group,
+                        &[],
+                    );
+                } else if let Some(skin) = skin {
+                    render_pass.set_bind_group(SKIN_BIND_GROUP_INDEX, &skin.bind_group, &[]);
+                }
+                render_pass.draw_indexed(0..mesh.num_indices, 0, 0..instance_len);
+            }
+        }
+    }
+}
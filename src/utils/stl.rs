@@ -0,0 +1,148 @@
+use anyhow::anyhow;
+
+use crate::{
+    components::{
+        material::MaterialTrait,
+        materials::blinn_phong::{BlinnPhongMaterial, BlinnPhongMaterialConfig},
+        mesh::{Mesh, VertexFormat, VertexPositionNormal},
+        transform::Transform,
+    },
+    entity::{Entity, EntityId},
+    geometry::normals::flat_normals,
+    mini_gpu::MiniGPU,
+};
+
+/// one solid of an stl file, three positions per triangle
+pub struct StlSolid {
+    pub name: String,
+    pub positions: Vec<[f32; 3]>,
+}
+
+/// load a binary or ascii stl, like load_obj every solid becomes a child entity of the returned one,
+/// with a flat shaded PositionNormal mesh and a shared BlinnPhongMaterial.
+/// normals are computed from the triangle winding, the facet normals of the file are ignored
+pub fn load_stl(bytes: &[u8], mini_gpu: &mut MiniGPU) -> anyhow::Result<EntityId> {
+    let solids = parse_stl(bytes)?;
+
+    let parent_id = mini_gpu.scene.add_default_entity();
+    let transform = Transform::default(&mini_gpu.renderer);
+    mini_gpu
+        .scene
+        .set_entity_component(parent_id, transform, "transform");
+    let material: Box<dyn MaterialTrait> = Box::new(BlinnPhongMaterial::new(
+        BlinnPhongMaterialConfig::default(),
+        &mini_gpu.renderer,
+    ));
+    let material_id = mini_gpu.scene.add_component(material);
+
+    for solid in solids
+        .into_iter()
+        .filter(|solid| !solid.positions.is_empty())
+    {
+        let vertices: Vec<VertexPositionNormal> = solid
+            .positions
+            .iter()
+            .zip(flat_normals(&solid.positions))
+            .map(|(position, normal)| VertexPositionNormal {
+                position: *position,
+                normal,
+            })
+            .collect();
+        let mesh = Mesh::new(
+            bytemuck::cast_slice(&vertices),
+            (0..vertices.len() as u32).collect(),
+            VertexFormat::PositionNormal,
+            &mini_gpu.renderer,
+        );
+        let mut child = Entity::new();
+        child.name = solid.name;
+        child.set_component_index("material", material_id);
+        let child_id = mini_gpu.scene.add_entity_child(parent_id, child)?;
+        mini_gpu.scene.set_entity_component(child_id, mesh, "mesh");
+        let transform = Transform::default(&mini_gpu.renderer);
+        mini_gpu
+            .scene
+            .set_entity_component(child_id, transform, "transform");
+    }
+    Ok(parent_id)
+}
+
+/// binary files may also start with "solid", so the triangle count decides
+pub fn parse_stl(bytes: &[u8]) -> anyhow::Result<Vec<StlSolid>> {
+    // in u64, the count of an ascii file is text and overflows a 32 bit usize on wasm
+    let binary_size = bytes.get(80..84).and_then(|count| {
+        u64::from(u32::from_le_bytes(count.try_into().unwrap()))
+            .checked_mul(50)?
+            .checked_add(84)
+    });
+    let len = bytes.len() as u64;
+    let is_ascii = bytes.trim_ascii_start().starts_with(b"solid");
+    match binary_size {
+        Some(size) if size == len || (!is_ascii && size <= len) => {
+            Ok(vec![parse_binary_stl(bytes)])
+        }
+        _ if is_ascii => parse_ascii_stl(std::str::from_utf8(bytes)?),
+        _ => Err(anyhow!("stl: file is too short")),
+    }
+}
+
+fn parse_binary_stl(bytes: &[u8]) -> StlSolid {
+    let count = u32::from_le_bytes(bytes[80..84].try_into().unwrap()) as usize;
+    // normal, 3 vertices and a 2 byte attribute per triangle
+    let positions = bytes[84..84 + count * 50]
+        .chunks_exact(50)
+        .flat_map(|triangle| {
+            (0..3).map(move |i| {
+                let vertex = &triangle[12 + i * 12..24 + i * 12];
+                [0, 4, 8].map(|j| f32::from_le_bytes(vertex[j..j + 4].try_into().unwrap()))
+            })
+        })
+        .collect();
+    // the header is free text, exporters often write "solid name" like ascii files
+    let header = String::from_utf8_lossy(&bytes[..80]);
+    let header = header.trim_end_matches('\0').trim();
+    StlSolid {
+        name: header
+            .strip_prefix("solid")
+            .unwrap_or(header)
+            .trim()
+            .to_string(),
+        positions,
+    }
+}
+
+fn parse_ascii_stl(text: &str) -> anyhow::Result<Vec<StlSolid>> {
+    let mut solids: Vec<StlSolid> = vec![];
+    for (line_number, line) in text.lines().enumerate() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("solid") => solids.push(StlSolid {
+                name: words.collect::<Vec<_>>().join(" "),
+                positions: vec![],
+            }),
+            Some("vertex") => {
+                let solid = solids
+                    .last_mut()
+                    .ok_or_else(|| anyhow!("stl: vertex outside of a solid"))?;
+                let mut position = [0.0; 3];
+                for value in position.iter_mut() {
+                    *value = words
+                        .next()
+                        .and_then(|word| word.parse().ok())
+                        .ok_or_else(|| anyhow!("stl: bad vertex at line {}", line_number + 1))?;
+                }
+                solid.positions.push(position);
+            }
+            Some("endsolid") => {
+                if let Some(solid) = solids.last() {
+                    if solid.positions.len() % 3 != 0 {
+                        return Err(anyhow!("stl: solid {} has an incomplete facet", solid.name));
+                    }
+                }
+            }
+            // facet normals, loops and blank lines
+            _ => {}
+        }
+    }
+    Ok(solids)
+}